@@ -0,0 +1,276 @@
+//! A manifest of the values that affect how a font renders, used by `validate` to detect changes
+//! between builds of a font.
+//!
+//! The hashes are computed from parsed data so that re-serialising a font without changing it,
+//! for example with a different table order or glyph encoding, does not register as a change.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use allsorts::binary::read::ReadScope;
+use allsorts::cff::cff2::CFF2;
+use allsorts::cff::outline::CFF2Outlines;
+use allsorts::cff::CFF;
+use allsorts::font::{read_cmap_subtable, Font};
+use allsorts::layout::{LangSys, LayoutTable};
+use allsorts::outline::{OutlineBuilder, OutlineSink};
+use allsorts::pathfinder_geometry::line_segment::LineSegment2F;
+use allsorts::pathfinder_geometry::vector::Vector2F;
+use allsorts::tables::cmap::Cmap;
+use allsorts::tables::glyf::GlyfTable;
+use allsorts::tables::loca::LocaTable;
+use allsorts::tables::{FontTableProvider, HeadTable, MaxpTable};
+use allsorts::tag::{self, DisplayTag};
+
+use crate::{BoxError, ErrorMessage};
+
+const HEADER: &str = "# allsorts validate baseline";
+
+/// Values keyed by name, such as `num_glyphs` or `glyph 12`
+pub(crate) struct Manifest(BTreeMap<String, String>);
+
+impl Manifest {
+    pub fn from_font<P: FontTableProvider>(
+        provider: &P,
+        mut font: Font<P>,
+    ) -> Result<Manifest, BoxError> {
+        let table = provider.read_table_data(tag::MAXP)?;
+        let maxp = ReadScope::new(table.borrow()).read::<MaxpTable>()?;
+        let mut entries = BTreeMap::new();
+        entries.insert(String::from("num_glyphs"), maxp.num_glyphs.to_string());
+
+        if let Some(table) = provider.table_data(tag::CMAP)? {
+            let cmap = ReadScope::new(table.borrow()).read::<Cmap>()?;
+            if let Some((_encoding, cmap_subtable)) = read_cmap_subtable(&cmap)? {
+                let mut mappings = BTreeMap::new();
+                cmap_subtable.mappings_fn(|ch, gid| {
+                    mappings.insert(ch, gid);
+                })?;
+                let mut hasher = Fnv1a::new();
+                for (ch, gid) in mappings {
+                    hasher.write(&ch.to_be_bytes());
+                    hasher.write(&gid.to_be_bytes());
+                }
+                entries.insert(String::from("cmap"), hasher.hex());
+            }
+        }
+
+        let mut hasher = Fnv1a::new();
+        for glyph_id in 0..maxp.num_glyphs {
+            let advance = font.horizontal_advance(glyph_id).unwrap_or(0);
+            hasher.write(&advance.to_be_bytes());
+        }
+        entries.insert(String::from("advances"), hasher.hex());
+
+        if let Some(gsub_cache) = font.gsub_cache()? {
+            let features = feature_tags(&gsub_cache.layout_table)?;
+            entries.insert(String::from("features GSUB"), features);
+        }
+        if let Some(gpos_cache) = font.gpos_cache()? {
+            let features = feature_tags(&gpos_cache.layout_table)?;
+            entries.insert(String::from("features GPOS"), features);
+        }
+
+        for (glyph_id, hash) in outline_hashes(provider, maxp.num_glyphs)?
+            .into_iter()
+            .enumerate()
+        {
+            entries.insert(format!("glyph {}", glyph_id), hash);
+        }
+
+        Ok(Manifest(entries))
+    }
+
+    pub fn parse(contents: &str) -> Result<Manifest, BoxError> {
+        let mut entries = BTreeMap::new();
+        for line in contents.lines().filter(|line| !line.starts_with('#')) {
+            let (key, value) = line
+                .rsplit_once(' ')
+                .ok_or_else(|| format!("invalid baseline entry: '{}'", line))?;
+            entries.insert(key.to_string(), value.to_string());
+        }
+        if entries.is_empty() {
+            return Err(ErrorMessage("baseline is empty").into());
+        }
+        Ok(Manifest(entries))
+    }
+
+    /// Describe each difference between `self`, the baseline, and `current`
+    pub fn drift(&self, current: &Manifest) -> Vec<String> {
+        let keys = self
+            .0
+            .keys()
+            .chain(current.0.keys())
+            .collect::<BTreeSet<_>>();
+        let mut drift = Vec::new();
+        for key in keys {
+            match (self.0.get(key), current.0.get(key)) {
+                (Some(before), Some(after)) if before == after => {}
+                (Some(before), Some(after)) => match key.as_str() {
+                    "num_glyphs" => {
+                        drift.push(format!("number of glyphs changed: {} -> {}", before, after))
+                    }
+                    key if key.starts_with("features ") => drift.push(format!(
+                        "{} changed: {} -> {}",
+                        key,
+                        or_none(before),
+                        or_none(after)
+                    )),
+                    key if key.starts_with("glyph ") => {
+                        drift.push(format!("{} outline changed", key))
+                    }
+                    key => drift.push(format!("{} changed", key)),
+                },
+                (Some(_), None) => drift.push(format!("{} removed", key)),
+                (None, Some(_)) => drift.push(format!("{} added", key)),
+                (None, None) => unreachable!(),
+            }
+        }
+        drift
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for (key, value) in &self.0 {
+            writeln!(f, "{} {}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+fn or_none(features: &str) -> &str {
+    if features.is_empty() {
+        "none"
+    } else {
+        features
+    }
+}
+
+/// The sorted, comma-separated tags of the features used by any script and language
+fn feature_tags<T>(layout_table: &LayoutTable<T>) -> Result<String, BoxError> {
+    let mut tags = BTreeSet::new();
+    if let Some(script_list) = &layout_table.opt_script_list {
+        for script_record in script_list.script_records() {
+            let script_table = script_record.script_table();
+            let mut add_features = |langsys: &LangSys| -> Result<(), BoxError> {
+                for feature_index in langsys.feature_indices_iter() {
+                    let feature_record = layout_table.feature_by_index(*feature_index)?;
+                    tags.insert(DisplayTag(feature_record.feature_tag).to_string());
+                }
+                Ok(())
+            };
+            if let Some(default_langsys) = script_table.default_langsys_record() {
+                add_features(&default_langsys)?;
+            }
+            for langsys in script_table.langsys_records() {
+                add_features(langsys.langsys_table())?;
+            }
+        }
+    }
+    Ok(tags.into_iter().collect::<Vec<_>>().join(","))
+}
+
+fn outline_hashes(
+    provider: &impl FontTableProvider,
+    num_glyphs: u16,
+) -> Result<Vec<String>, BoxError> {
+    if provider.has_table(tag::GLYF) {
+        let table = provider.read_table_data(tag::HEAD)?;
+        let head = ReadScope::new(table.borrow()).read::<HeadTable>()?;
+        let loca_data = provider.read_table_data(tag::LOCA)?;
+        let loca = ReadScope::new(&loca_data)
+            .read_dep::<LocaTable>((usize::from(num_glyphs), head.index_to_loc_format))?;
+        let glyf_data = provider.read_table_data(tag::GLYF)?;
+        let mut glyf = ReadScope::new(&glyf_data).read_dep::<GlyfTable>(&loca)?;
+        hash_outlines(&mut glyf, num_glyphs)
+    } else if provider.has_table(tag::CFF) {
+        let cff_data = provider.read_table_data(tag::CFF)?;
+        let mut cff = ReadScope::new(&cff_data).read::<CFF>()?;
+        hash_outlines(&mut cff, num_glyphs)
+    } else if provider.has_table(tag::CFF2) {
+        let cff_data = provider.read_table_data(tag::CFF2)?;
+        let cff = ReadScope::new(&cff_data).read::<CFF2>()?;
+        let mut cff2_outlines = CFF2Outlines {
+            table: &cff,
+            tuple: None,
+        };
+        hash_outlines(&mut cff2_outlines, num_glyphs)
+    } else {
+        Err(ErrorMessage("no glyf or CFF table").into())
+    }
+}
+
+fn hash_outlines<T>(builder: &mut T, num_glyphs: u16) -> Result<Vec<String>, BoxError>
+where
+    T: OutlineBuilder,
+    T::Error: std::error::Error + 'static,
+{
+    let mut hashes = Vec::with_capacity(usize::from(num_glyphs));
+    for glyph_id in 0..num_glyphs {
+        let mut sink = HashSink(Fnv1a::new());
+        builder.visit(glyph_id, &mut sink)?;
+        hashes.push(sink.0.hex());
+    }
+    Ok(hashes)
+}
+
+/// 64-bit FNV-1a, used because its output is stable across Rust versions
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+/// An `OutlineSink` that hashes the path commands it is fed
+struct HashSink(Fnv1a);
+
+impl HashSink {
+    fn command(&mut self, command: u8, points: &[Vector2F]) {
+        self.0.write(&[command]);
+        for point in points {
+            // Normalise -0 so that it hashes the same as 0
+            let x = point.x() + 0.;
+            let y = point.y() + 0.;
+            self.0.write(&x.to_bits().to_be_bytes());
+            self.0.write(&y.to_bits().to_be_bytes());
+        }
+    }
+}
+
+impl OutlineSink for HashSink {
+    fn move_to(&mut self, point: Vector2F) {
+        self.command(b'M', &[point]);
+    }
+
+    fn line_to(&mut self, point: Vector2F) {
+        self.command(b'L', &[point]);
+    }
+
+    fn quadratic_curve_to(&mut self, control: Vector2F, point: Vector2F) {
+        self.command(b'Q', &[control, point]);
+    }
+
+    fn cubic_curve_to(&mut self, control: LineSegment2F, point: Vector2F) {
+        self.command(b'C', &[control.from(), control.to(), point]);
+    }
+
+    fn close(&mut self) {
+        self.command(b'Z', &[]);
+    }
+}
@@ -1,8 +1,12 @@
 use std::ffi::OsString;
+use std::ops::RangeInclusive;
 
 use gumdrop::Options;
 
-use crate::writer::{Colour, Margin};
+use crate::script::Direction;
+use crate::shape::{Convention, ShapeFormat};
+use crate::subset::Check;
+use crate::writer::{Align, Colour, ImageFormat, Margin, Waterfall};
 
 #[derive(Debug, Options)]
 pub struct Cli {
@@ -39,6 +43,9 @@ pub enum Command {
     #[options(help = "generate a specimen page for a font")]
     Specimen(SpecimenOpts),
 
+    #[options(help = "print an overview of a font")]
+    Stats(StatsOpts),
+
     #[options(help = "subset a font")]
     Subset(SubsetOpts),
 
@@ -96,6 +103,12 @@ pub struct CmapOpts {
         default = "0"
     )]
     pub index: usize,
+
+    #[options(
+        help = "check the sub-tables for conflicts and out of range glyphs instead of dumping",
+        no_short
+    )]
+    pub lint: bool,
 }
 
 #[derive(Debug, Options)]
@@ -106,6 +119,12 @@ pub struct DumpOpts {
     #[options(help = "treat the file as a CFF font/table")]
     pub cff: bool,
 
+    #[options(
+        help = "include the default values of operators missing from CFF DICTs",
+        no_short
+    )]
+    pub cff_verbose: bool,
+
     #[options(help = "dump the raw binary content of this table", meta = "TABLE")]
     pub table: Option<String>,
 
@@ -119,8 +138,25 @@ pub struct DumpOpts {
     #[options(help = "include CMAP encodings in output", no_short)]
     pub encodings: bool,
 
-    #[options(help = "dump the specified glyph", meta = "GLYPH_ID")]
-    pub glyph: Option<u16>,
+    #[options(
+        help = "dump the specified glyph, or the glyphs in an inclusive range such as 300..360",
+        meta = "GLYPH_ID",
+        parse(try_from_str = "parse_glyph_range")
+    )]
+    pub glyph: Option<RangeInclusive<u16>>,
+
+    #[options(
+        help = "also print the outline of --glyph as an SVG path element",
+        no_short
+    )]
+    pub svg_path: bool,
+
+    #[options(
+        help = "with --glyph, compare the glyph at the default location and at this user-tuple",
+        meta = "TUPLE",
+        no_short
+    )]
+    pub tuple: Option<String>,
 
     #[options(help = "include glyph names in output", no_short)]
     pub glyph_names: bool,
@@ -128,17 +164,144 @@ pub struct DumpOpts {
     #[options(help = "include strings from the name table in output", no_short)]
     pub name: bool,
 
+    #[options(
+        help = "only include name records for this platform id, e.g. 3 for Windows (implies --name)",
+        meta = "PLATFORM",
+        no_short,
+        parse(try_from_str = "parse_u16")
+    )]
+    pub name_platform: Option<u16>,
+
+    #[options(
+        help = "only include name records for this language id, e.g. 0x409 (implies --name)",
+        meta = "LANGUAGE",
+        no_short,
+        parse(try_from_str = "parse_u16")
+    )]
+    pub name_language: Option<u16>,
+
+    #[options(help = "print the summary of the font as JSON", no_short)]
+    pub json: bool,
+
+    #[options(
+        help = "print a one-line summary of the font as key=value pairs",
+        no_short
+    )]
+    pub count: bool,
+
+    #[options(
+        help = "guess the tools and vendor that produced the font, with the evidence for each",
+        no_short
+    )]
+    pub provenance: bool,
+
     #[options(help = "print the head table", no_short)]
     pub head: bool,
 
+    #[options(help = "print the hhea table", no_short)]
+    pub hhea: bool,
+
     #[options(help = "print the hmtx table", no_short)]
     pub hmtx: bool,
 
+    #[options(help = "print the maxp table", no_short)]
+    pub maxp: bool,
+
+    #[options(
+        help = "print the version, italic angle, underline, and fixed pitch fields of the post table",
+        no_short
+    )]
+    pub post: bool,
+
+    #[options(
+        help = "print the GDEF glyph classes and mark attachment classes",
+        no_short
+    )]
+    pub gdef: bool,
+
+    #[options(help = "print the OS/2 table", no_short)]
+    pub os2: bool,
+
+    #[options(help = "print the PANOSE classification of the OS/2 table", no_short)]
+    pub panose: bool,
+
     #[options(help = "print the loca table")]
     pub loca: bool,
 
+    #[options(help = "print the EBLC/CBLC bitmap size tables", no_short)]
+    pub eblc: bool,
+
+    #[options(
+        help = "print the table containing OFFSET (hex with 0x prefix, or decimal) in the file",
+        meta = "OFFSET",
+        no_short,
+        parse(try_from_str = "parse_offset")
+    )]
+    pub locate: Option<usize>,
+
+    #[options(
+        help = "report bytes that aren't part of any table and tables that aren't padded",
+        no_short
+    )]
+    pub gaps: bool,
+
+    #[options(
+        help = "like --gaps, with a hex dump of gaps that aren't zero",
+        no_short
+    )]
+    pub show_gap_data: bool,
+
+    #[options(
+        help = "recompute the checksum of each table and checkSumAdjustment, failing on mismatches",
+        no_short
+    )]
+    pub verify_checksums: bool,
+
+    #[options(
+        help = "compare table TAG across the fonts of a collection, failing unless all are equal",
+        meta = "TAG",
+        no_short
+    )]
+    pub table_equal: Option<String>,
+
+    #[options(
+        help = "list the glyphs that set the glyf OVERLAP_SIMPLE or OVERLAP_COMPOUND flags",
+        no_short
+    )]
+    pub overlap: bool,
+
+    #[options(
+        help = "list the composite glyphs that take their metrics from a component",
+        no_short
+    )]
+    pub use_my_metrics: bool,
+
+    #[options(
+        help = "list glyphs with zero advance, degenerate contours, or broken components",
+        no_short
+    )]
+    pub anomalies: bool,
+
+    #[options(help = "print the pairs of the kern table", no_short)]
+    pub kern: bool,
+
+    #[options(
+        help = "compare the pairs of the kern table with the GPOS kern feature",
+        no_short
+    )]
+    pub compare_kerning: bool,
+
+    #[options(
+        help = "print the OS/2 fsType embedding permissions of each font, one line per font",
+        no_short
+    )]
+    pub fstype: bool,
+
     #[options(free, required, help = "path to font to dump")]
     pub font: String,
+
+    #[options(free, help = "paths of more fonts, with --fstype")]
+    pub fonts: Vec<String>,
 }
 
 #[derive(Debug, Options)]
@@ -182,10 +345,10 @@ pub struct InstanceOpts {
     #[options(help = "comma-separated list of user-tuple values", meta = "TUPLE")]
     pub tuple: String,
 
-    #[options(required, help = "path to destination font")]
+    #[options(required, help = "path to destination font, - for stdout")]
     pub output: String,
 
-    #[options(free, required, help = "path to input variable font file")]
+    #[options(free, required, help = "path to input variable font file, - for stdin")]
     pub font: String,
 }
 
@@ -221,20 +384,154 @@ pub struct ShapeOpts {
     )]
     pub index: usize,
 
-    #[options(required, help = "script to shape", meta = "SCRIPT")]
-    pub script: String,
+    #[options(help = "script to shape", meta = "SCRIPT")]
+    pub script: Option<String>,
 
-    #[options(required, help = "language to shape", meta = "LANG")]
-    pub lang: String,
+    #[options(help = "language to shape", meta = "LANG")]
+    pub lang: Option<String>,
 
-    #[options(free, required, help = "text to shape")]
-    pub text: String,
+    #[options(free, help = "text to shape")]
+    pub text: Option<String>,
+
+    #[options(
+        help = "read the script, language, features, tuple, and text from a JSON file",
+        meta = "PATH",
+        no_short
+    )]
+    pub config: Option<String>,
+
+    #[options(
+        help = "shape each line of FILE instead of TEXT",
+        meta = "FILE",
+        no_short
+    )]
+    pub tests: Option<String>,
+
+    #[options(
+        help = "shape each line of PATH instead of TEXT, prefixed with its line number, - for stdin",
+        meta = "PATH",
+        no_short
+    )]
+    pub text_file: Option<String>,
+
+    #[options(
+        help = "print one line of tab-separated glyphs per input, with errors inline",
+        no_short
+    )]
+    pub porcelain: bool,
+
+    #[options(
+        help = "print the glyphs as a JSON array of objects, one line per input",
+        no_short
+    )]
+    pub json: bool,
+
+    #[options(
+        help = "print the glyphs in another format, hb for hb-shape's",
+        meta = "FORMAT",
+        no_short
+    )]
+    pub format: Option<ShapeFormat>,
+
+    #[options(
+        help = "print the positions the way a platform API reports them, coretext or directwrite",
+        meta = "API",
+        no_short
+    )]
+    pub convention: Option<Convention>,
 
     #[options(help = "comma-separated list of user-tuple values", meta = "TUPLE")]
     pub tuple: Option<String>,
 
     #[options(help = "vertical layout, default horizontal", no_short)]
     pub vertical: bool,
+
+    #[options(
+        help = "direction of the text for --stage-svgs, ltr or rtl (default: from the script)",
+        meta = "DIR",
+        no_short
+    )]
+    pub direction: Option<Direction>,
+
+    #[options(
+        help = "include the hmtx and vmtx advances of each glyph, in either layout",
+        no_short
+    )]
+    pub both_advances: bool,
+
+    #[options(
+        help = "include the bounding box of each glyph and of the whole run",
+        no_short
+    )]
+    pub extents: bool,
+
+    #[options(
+        help = "report how many glyphs each enabled feature changes for the text",
+        no_short
+    )]
+    pub feature_stats: bool,
+
+    #[options(
+        help = "after the glyphs, list the substitutions and positioning shaping applied",
+        no_short
+    )]
+    pub trace: bool,
+
+    #[options(
+        help = "shape the text after STR, which is left out of the output",
+        meta = "STR",
+        no_short
+    )]
+    pub context_before: Option<String>,
+
+    #[options(
+        help = "shape the text before STR, which is left out of the output",
+        meta = "STR",
+        no_short
+    )]
+    pub context_after: Option<String>,
+
+    #[options(
+        help = "comma-separated OpenType features to apply, or +tag and -tag to modify the defaults",
+        meta = "FEATURES"
+    )]
+    pub features: Option<String>,
+
+    #[options(
+        help = "file of features to apply, as tag or tag=value entries",
+        meta = "PATH",
+        no_short
+    )]
+    pub features_file: Option<String>,
+
+    #[options(
+        help = "write an SVG of the glyphs after cmap mapping, GSUB, and GPOS to DIR",
+        meta = "DIR",
+        no_short
+    )]
+    pub stage_svgs: Option<String>,
+
+    #[options(
+        help = "render characters the font can't map with the glyph for CODEPOINT (e.g. U+003F)",
+        meta = "CODEPOINT",
+        no_short,
+        parse(try_from_str = "parse_codepoint")
+    )]
+    pub fallback_codepoint: Option<char>,
+
+    #[options(
+        help = "shape the text N times and report timing instead of the glyphs",
+        meta = "N",
+        no_short
+    )]
+    pub bench: Option<u32>,
+
+    #[options(
+        help = "exit with an error unless shaping gives N glyphs, printing nothing otherwise",
+        meta = "N",
+        no_short
+    )]
+    pub expect_glyphs: Option<usize>,
 }
 
 #[derive(Debug, Options)]
@@ -260,17 +557,80 @@ pub struct SpecimenOpts {
     pub font: String,
 }
 
+#[derive(Debug, Options)]
+pub struct StatsOpts {
+    #[options(help = "print help message")]
+    pub help: bool,
+
+    #[options(
+        help = "index of the font to read (for TTC, WOFF2)",
+        meta = "INDEX",
+        default = "0"
+    )]
+    pub index: usize,
+
+    #[options(free, required, help = "path to font file")]
+    pub font: String,
+}
+
 #[derive(Debug, Options)]
 pub struct SubsetOpts {
     #[options(help = "print help message")]
     pub help: bool,
 
-    #[options(help = "subset the font to include glyphs from TEXT", meta = "TEXT")]
-    pub text: Option<String>,
+    #[options(
+        help = "subset the font to include glyphs from TEXT, may be repeated",
+        meta = "TEXT"
+    )]
+    pub text: Vec<String>,
+
+    #[options(
+        help = "include glyphs for the text in PATH, may be repeated",
+        meta = "PATH",
+        no_short
+    )]
+    pub text_file: Vec<String>,
+
+    #[options(
+        help = "include glyphs for comma-separated hex codepoints or ranges, may be repeated",
+        meta = "UNICODES",
+        no_short
+    )]
+    pub unicodes: Vec<String>,
 
     #[options(help = "include all glyphs in the subset font")]
     pub all: bool,
 
+    #[options(
+        help = "keep the glyphs the text shapes to with SCRIPT instead of its mapped glyphs",
+        meta = "SCRIPT",
+        no_short
+    )]
+    pub shape: Option<String>,
+
+    #[options(help = "language to shape with, with --shape", meta = "LANG", no_short)]
+    pub lang: Option<String>,
+
+    #[options(
+        help = "also keep the glyphs GSUB can substitute into the subset, instead of warning",
+        no_short
+    )]
+    pub closure: bool,
+
+    #[options(
+        help = "validate the subset font and on problems don't write it (fail) or write it (warn)",
+        meta = "MODE",
+        no_short
+    )]
+    pub check: Option<Check>,
+
+    #[options(
+        help = "fail unless the comma-separated tables are copied unchanged, e.g. name,OS/2",
+        meta = "TAGS",
+        no_short
+    )]
+    pub preserve_tables: Option<String>,
+
     #[options(
         help = "index of the font to subset (for TTC, WOFF2)",
         meta = "INDEX",
@@ -278,7 +638,7 @@ pub struct SubsetOpts {
     )]
     pub index: usize,
 
-    #[options(free, required, help = "path to source font")]
+    #[options(free, required, help = "path to source font, - for stdin")]
     pub input: String,
 
     #[options(free, required, help = "path to destination font")]
@@ -311,6 +671,9 @@ pub struct SvgOpts {
 
     #[options(help = "flip output (rotate 180deg)", no_short)]
     pub flip: bool,
+
+    #[options(help = "write the SVG to PATH instead of stdout", meta = "PATH")]
+    pub output: Option<String>,
 }
 
 #[derive(Debug, Options)]
@@ -318,6 +681,23 @@ pub struct ValidateOpts {
     #[options(help = "print help message")]
     pub help: bool,
 
+    #[options(
+        help = "validate the variable font instance at these axis:value coordinates",
+        meta = "AXES",
+        no_short
+    )]
+    pub at: Option<String>,
+
+    #[options(
+        help = "compare glyph outlines, cmap, advances, and features with the manifest in FILE",
+        meta = "FILE",
+        no_short
+    )]
+    pub baseline: Option<String>,
+
+    #[options(help = "write the --baseline manifest instead of comparing", no_short)]
+    pub write_baseline: bool,
+
     #[options(free, required, help = "path to font")]
     pub font: String,
 }
@@ -349,15 +729,66 @@ pub struct ViewOpts {
     #[options(required, help = "path to font file", meta = "PATH")]
     pub font: String,
 
-    #[options(required, help = "script to shape", meta = "SCRIPT")]
-    pub script: String,
+    #[options(help = "write the SVG to PATH instead of stdout", meta = "PATH")]
+    pub output: Option<String>,
+
+    #[options(
+        help = "image format to write, svg or png (default svg), png requires --output",
+        meta = "FORMAT",
+        no_short
+    )]
+    pub format: Option<ImageFormat>,
+
+    #[options(
+        help = "gzip compress the SVG (SVGZ), implied by an --output ending in .svgz",
+        no_short
+    )]
+    pub gzip: bool,
+
+    #[options(help = "script to shape", meta = "SCRIPT")]
+    pub script: Option<String>,
 
     #[options(help = "language to shape", meta = "LANG")]
     pub lang: Option<String>,
 
+    #[options(help = "lay the text out vertically, top to bottom", no_short)]
+    pub vertical: bool,
+
+    #[options(
+        help = "direction of the text, ltr or rtl (default: from the script)",
+        meta = "DIR",
+        no_short
+    )]
+    pub direction: Option<Direction>,
+
+    #[options(
+        help = "don't record the font and shaping parameters in the SVG metadata",
+        no_short
+    )]
+    pub no_embed_params: bool,
+
+    #[options(
+        help = "render every glyph in the font in a grid instead of shaping text",
+        no_short
+    )]
+    pub all_glyphs: bool,
+
+    #[options(
+        help = "number of columns in the --all-glyphs grid (default 16)",
+        meta = "N",
+        no_short
+    )]
+    pub columns: Option<u16>,
+
     #[options(help = "mark the origin of each glyph with a cross-hair", no_short)]
     pub mark_origin: bool,
 
+    #[options(
+        help = "draw a marker for glyphs with an empty outline or zero advance",
+        no_short
+    )]
+    pub show_invisible: bool,
+
     #[options(
         help = "specify a margin to be added to the edge of the SVG",
         meta = "num or top,right,bottom,left",
@@ -365,6 +796,12 @@ pub struct ViewOpts {
     )]
     pub margin: Option<Margin>,
 
+    #[options(
+        help = "leave the advances of blank glyphs at the end of the text out of the SVG",
+        no_short
+    )]
+    pub trim_advance: bool,
+
     #[options(
         help = "set the fill colour of the glyphs",
         meta = "rrggbbaa",
@@ -388,6 +825,18 @@ pub struct ViewOpts {
     #[options(help = "text to render")]
     pub text: Option<String>,
 
+    #[options(
+        help = "strip leading and trailing whitespace from the text before shaping",
+        no_short
+    )]
+    pub trim_spaces: bool,
+
+    #[options(
+        help = "collapse runs of whitespace in the text to a single space",
+        no_short
+    )]
+    pub collapse_spaces: bool,
+
     #[options(
         help = "comma-separated list of codepoints (as hexadecimal numbers) to render",
         meta = "CODEPOINTS"
@@ -401,11 +850,242 @@ pub struct ViewOpts {
     pub indices: Option<String>,
 
     #[options(
-        help = "comma-separated list of OpenType features to enable (note: only enables these features)",
+        help = "comma-separated list of OpenType features to enable (note: only enables these features), or +tag and -tag to modify the defaults",
         meta = "FEATURES"
     )]
     pub features: Option<String>,
 
+    #[options(
+        help = "file of features to apply, as tag or tag=value entries",
+        meta = "PATH",
+        no_short
+    )]
+    pub features_file: Option<String>,
+
+    #[options(
+        help = "comma-separated list of OpenType features to remove from the defaults, keeping the rest",
+        meta = "FEATURES",
+        no_short
+    )]
+    pub disable: Option<String>,
+
+    #[options(
+        help = "highlight the glyphs that change when the comma-separated features are added",
+        meta = "FEATURES",
+        no_short
+    )]
+    pub diff_features: Option<String>,
+
+    #[options(
+        help = "how far in font units a glyph can move and be unchanged for --diff-features",
+        meta = "UNITS",
+        default = "0",
+        no_short
+    )]
+    pub diff_tolerance: u16,
+
+    #[options(
+        help = "with --diff-features, draw the text without the features faintly behind",
+        no_short
+    )]
+    pub diff_show_base: bool,
+
     #[options(help = "comma-separated list of user-tuple values", meta = "TUPLE")]
     pub tuple: Option<String>,
+
+    #[options(
+        help = "font size in pixels, sets the width and height of the SVG (default 1000)",
+        meta = "PX",
+        no_short,
+        parse(try_from_str = "parse_font_size")
+    )]
+    pub font_size: Option<f32>,
+
+    #[options(
+        help = "fit the text into an SVG this many pixels wide",
+        meta = "PX",
+        no_short
+    )]
+    pub width: Option<u32>,
+
+    #[options(
+        help = "fit the text into an SVG this many pixels high",
+        meta = "PX",
+        no_short
+    )]
+    pub height: Option<u32>,
+
+    #[options(
+        help = "where to place the text within --width and --height, e.g. top-left (default centre)",
+        meta = "ALIGN",
+        no_short
+    )]
+    pub align: Option<Align>,
+
+    #[options(
+        help = "render the text once per comma-separated font size, stacked vertically",
+        meta = "SIZES",
+        no_short
+    )]
+    pub waterfall: Option<Waterfall>,
+
+    #[options(
+        help = "rotate the output clockwise by DEGREES",
+        meta = "DEGREES",
+        no_short
+    )]
+    pub rotate: Option<f32>,
+
+    #[options(
+        help = "font to render characters missing from the primary font, may be repeated",
+        meta = "PATH",
+        no_short
+    )]
+    pub fallback: Vec<String>,
+
+    #[options(
+        help = "render characters no font can map with the glyph for CODEPOINT (e.g. U+003F)",
+        meta = "CODEPOINT",
+        no_short,
+        parse(try_from_str = "parse_codepoint")
+    )]
+    pub fallback_codepoint: Option<char>,
+
+    #[options(
+        help = "fill glyphs from fallback fonts with a distinct colour",
+        no_short
+    )]
+    pub tint_fallbacks: bool,
+
+    #[options(
+        help = "include the text as a transparent layer so it can be selected and searched",
+        no_short
+    )]
+    pub selectable_text: bool,
+
+    #[options(
+        help = "add a legend describing the colours and markers in the SVG",
+        no_short
+    )]
+    pub legend: bool,
+
+    #[options(
+        help = "mark the anchors of cursive attachments, numbering each chain of joined glyphs",
+        no_short
+    )]
+    pub show_cursive: bool,
+
+    #[options(
+        help = "draw a line from each attached mark to the anchor of its base glyph",
+        no_short
+    )]
+    pub show_anchors: bool,
+
+    #[options(
+        help = "mark the start point of each contour with an arrow showing its direction",
+        no_short
+    )]
+    pub show_points: bool,
+
+    #[options(help = "draw the bounding box of each glyph", no_short)]
+    pub show_bboxes: bool,
+
+    #[options(
+        help = "draw dashed lines at the baseline, ascender, and descender",
+        no_short
+    )]
+    pub guides: bool,
+
+    #[options(
+        help = "label each glyph with its glyph index, below the descender",
+        no_short
+    )]
+    pub label_indices: bool,
+
+    #[options(
+        help = "label each glyph with the codepoints it came from, below the descender",
+        no_short
+    )]
+    pub label_unicodes: bool,
+
+    #[options(
+        help = "write a path per glyph placed instead of symbols and uses (output is larger)",
+        no_short
+    )]
+    pub inline_paths: bool,
+
+    #[options(
+        help = "decimal places of the glyph outline coordinates (default: 2)",
+        meta = "N",
+        default = "2",
+        no_short
+    )]
+    pub precision: usize,
+
+    #[options(
+        help = "fill the glyphs of each cluster of source characters with a different colour",
+        no_short
+    )]
+    pub colour_by_cluster: bool,
+
+    #[options(
+        help = "also write each glyph placed to its own SVG in DIR, with a positions.json",
+        meta = "DIR",
+        no_short
+    )]
+    pub per_glyph_dir: Option<String>,
+}
+
+fn parse_offset(s: &str) -> Result<usize, std::num::ParseIntError> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+}
+
+/// Parse a number that may be given in hex with a 0x prefix, or decimal
+fn parse_u16(s: &str) -> Result<u16, std::num::ParseIntError> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+}
+
+/// Parse a glyph id or an inclusive range of glyph ids written `start..end` or `start-end`
+fn parse_glyph_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    let parse = |id: &str| {
+        id.trim()
+            .parse::<u16>()
+            .map_err(|err| format!("invalid glyph id '{}': {}", id, err))
+    };
+    let (start, end) = match s.split_once("..").or_else(|| s.split_once('-')) {
+        Some((start, end)) => (parse(start)?, parse(end)?),
+        None => {
+            let glyph_id = parse(s)?;
+            (glyph_id, glyph_id)
+        }
+    };
+    if start > end {
+        return Err(format!("glyph range {} is empty", s));
+    }
+    Ok(start..=end)
+}
+
+fn parse_font_size(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(size) if size > 0. && size.is_finite() => Ok(size),
+        Ok(_) => Err(format!("font size must be greater than zero: {}", s)),
+        Err(err) => Err(format!("invalid font size '{}': {}", s, err)),
+    }
+}
+
+fn parse_codepoint(s: &str) -> Result<char, String> {
+    let hex = s
+        .strip_prefix("U+")
+        .or_else(|| s.strip_prefix("u+"))
+        .unwrap_or(s);
+    u32::from_str_radix(hex, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| format!("invalid codepoint: {}", s))
 }
@@ -1,19 +1,26 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
 use allsorts::binary::read::ReadScope;
 use allsorts::error::ParseError;
 use allsorts::font::Encoding;
 use allsorts::font_data::FontData;
 use allsorts::tables::cmap::CmapSubtable;
-use allsorts::tables::FontTableProvider;
+use allsorts::tables::{FontTableProvider, MaxpTable};
+use allsorts::tag;
 use allsorts::Font;
 
 use crate::cli::CmapOpts;
-use crate::BoxError;
+use crate::{scope_at_offset, BoxError};
 
 pub fn main(opts: CmapOpts) -> Result<i32, BoxError> {
     let buffer = std::fs::read(&opts.font)?;
     let scope = ReadScope::new(&buffer);
     let font_file = scope.read::<FontData>()?;
     let table_provider = font_file.table_provider(opts.index)?;
+    if opts.lint {
+        return lint_cmap(&table_provider);
+    }
     let mut font = Font::new(Box::new(table_provider))?;
     dump_cmap(&mut font)?;
 
@@ -45,3 +52,180 @@ fn dump_cmap<T: FontTableProvider>(font: &mut Font<T>) -> Result<(), ParseError>
 
     Ok(())
 }
+
+/// Maximum number of codepoints listed for each finding
+const LINT_EXAMPLES: usize = 10;
+
+/// A cmap sub-table with the non-zero glyph indices it maps each code to
+struct LintSubtable {
+    platform_id: u16,
+    encoding_id: u16,
+    format: u16,
+    mappings: BTreeMap<u32, u16>,
+}
+
+impl fmt::Display for LintSubtable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}) format {}",
+            self.platform_id, self.encoding_id, self.format
+        )
+    }
+}
+
+impl LintSubtable {
+    fn is_unicode(&self) -> bool {
+        matches!(
+            (self.platform_id, self.encoding_id),
+            (0, _) | (3, 1) | (3, 10)
+        )
+    }
+}
+
+/// Check the cmap sub-tables for disagreements and mappings to glyphs that don't exist.
+///
+/// Returns 1 if any errors or warnings were found.
+fn lint_cmap(provider: &impl FontTableProvider) -> Result<i32, BoxError> {
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+    let cmap_data = provider.read_table_data(tag::CMAP)?;
+    let scope = ReadScope::new(&cmap_data);
+    let mut ctxt = scope.ctxt();
+    let _version = ctxt.read_u16be()?;
+    let num_tables = ctxt.read_u16be()?;
+    let mut subtables = Vec::new();
+    for _ in 0..num_tables {
+        let platform_id = ctxt.read_u16be()?;
+        let encoding_id = ctxt.read_u16be()?;
+        let offset = ctxt.read_u32be()? as usize;
+        let subtable_scope = scope_at_offset(&scope, offset)?;
+        let format = subtable_scope.ctxt().read_u16be()?;
+        // Format 14 (variation sequences) and other formats allsorts doesn't read are skipped
+        let Ok(subtable) = subtable_scope.read::<CmapSubtable<'_>>() else {
+            continue;
+        };
+        let mut mappings = BTreeMap::new();
+        subtable.mappings_fn(|ch, gid| {
+            if gid != 0 {
+                mappings.insert(ch, gid);
+            }
+        })?;
+        subtables.push(LintSubtable {
+            platform_id,
+            encoding_id,
+            format,
+            mappings,
+        });
+    }
+
+    let mut errors = 0;
+    let mut warnings = 0;
+    for subtable in &subtables {
+        let out_of_range = subtable
+            .mappings
+            .iter()
+            .filter(|(_, gid)| **gid >= maxp.num_glyphs)
+            .map(|(&ch, &gid)| format!("U+{:04X} -> {}", ch, gid))
+            .collect::<Vec<_>>();
+        if !out_of_range.is_empty() {
+            errors += 1;
+            println!(
+                "error: {} maps {} codepoint(s) to glyphs beyond the {} in the font: {}",
+                subtable,
+                out_of_range.len(),
+                maxp.num_glyphs,
+                examples(&out_of_range)
+            );
+        }
+    }
+
+    let unicode = subtables
+        .iter()
+        .filter(|subtable| subtable.is_unicode())
+        .collect::<Vec<_>>();
+    for (i, a) in unicode.iter().enumerate() {
+        for b in &unicode[i + 1..] {
+            let conflicts = a
+                .mappings
+                .iter()
+                .filter_map(|(ch, gid_a)| match b.mappings.get(ch) {
+                    Some(gid_b) if gid_b != gid_a => {
+                        Some(format!("U+{:04X} -> {} vs {}", ch, gid_a, gid_b))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            if !conflicts.is_empty() {
+                errors += 1;
+                println!(
+                    "error: {} and {} map {} codepoint(s) to different glyphs: {}",
+                    a,
+                    b,
+                    conflicts.len(),
+                    examples(&conflicts)
+                );
+            }
+
+            // A format 12 sub-table should contain everything in the BMP only format 4 one
+            let (format4, format12) = match (a.format, b.format) {
+                (4, 12) => (a, b),
+                (12, 4) => (b, a),
+                _ => continue,
+            };
+            let missing = format4
+                .mappings
+                .keys()
+                .filter(|ch| !format12.mappings.contains_key(ch))
+                .map(|ch| format!("U+{:04X}", ch))
+                .collect::<Vec<_>>();
+            if missing.is_empty() {
+                println!("info: {} is a superset of {}", format12, format4);
+            } else {
+                errors += 1;
+                println!(
+                    "error: {} is missing {} codepoint(s) mapped by {}: {}",
+                    format12,
+                    missing.len(),
+                    format4,
+                    examples(&missing)
+                );
+            }
+        }
+    }
+
+    if !unicode.is_empty() {
+        for subtable in subtables
+            .iter()
+            .filter(|subtable| (subtable.platform_id, subtable.encoding_id) == (3, 0))
+        {
+            warnings += 1;
+            println!(
+                "warning: symbol sub-table {} is present alongside Unicode sub-tables, \
+                 some applications will prefer it",
+                subtable
+            );
+        }
+    }
+
+    if errors + warnings == 0 {
+        println!("no problems found");
+        Ok(0)
+    } else {
+        println!("{} error(s), {} warning(s)", errors, warnings);
+        Ok(1)
+    }
+}
+
+/// Join the first few `items`, noting how many were left out
+fn examples(items: &[String]) -> String {
+    let mut list = items
+        .iter()
+        .take(LINT_EXAMPLES)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if items.len() > LINT_EXAMPLES {
+        list.push_str(&format!(" and {} more", items.len() - LINT_EXAMPLES));
+    }
+    list
+}
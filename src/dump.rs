@@ -1,6 +1,8 @@
-use std::borrow::Borrow;
-use std::convert::{self, TryFrom};
+use std::borrow::{Borrow, Cow};
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryFrom;
 use std::io::{self, IsTerminal, Write};
+use std::ops::RangeInclusive;
 use std::str;
 
 use encoding_rs::{MACINTOSH, UTF_16BE};
@@ -8,30 +10,95 @@ use encoding_rs::{MACINTOSH, UTF_16BE};
 use allsorts::binary::read::ReadScope;
 use allsorts::cff::{self, CFFVariant, Charset, FontDict, Operand, Operator, CFF};
 use allsorts::error::ParseError;
-use allsorts::font::read_cmap_subtable;
 use allsorts::font_data::FontData;
-use allsorts::glyph_info::GlyphNames;
+use allsorts::outline::OutlineBuilder;
+use allsorts::post::PostTable;
 use allsorts::tables::cmap::{Cmap, CmapSubtable};
-use allsorts::tables::glyf::GlyfTable;
+use allsorts::tables::glyf::{CompositeGlyphFlag, GlyfRecord, GlyfTable, Glyph, SimpleGlyphFlag};
 use allsorts::tables::loca::LocaTable;
+use allsorts::tables::os2::Os2;
 use allsorts::tables::{
-    FontTableProvider, HeadTable, HheaTable, HmtxTable, MaxpTable, NameTable, OffsetTable,
-    OpenTypeData, TTCHeader,
+    FontTableProvider, HeadTable, HheaTable, HmtxTable, IndexToLocFormat, MaxpTable, NameTable,
+    OffsetTable, OpenTypeData, TTCHeader,
 };
 use allsorts::tag::{self, DisplayTag};
 use allsorts::woff::WoffFont;
 use allsorts::woff2::{Woff2Font, Woff2GlyfTable, Woff2LocaTable};
 
+use self::report::DumpReport;
 use crate::cli::DumpOpts;
-use crate::{decode, BoxError, ErrorMessage};
+use crate::kerning::{self, KerningPairs};
+use crate::stats::table_tags;
+use crate::writer::PathSink;
+use crate::{
+    decode, glyph_names, parse_tuple, read_class_def, read_font_file, scope_at_offset, BoxError,
+    ErrorMessage,
+};
+
+mod anomalies;
+mod checksums;
+mod gaps;
+mod panose;
+mod provenance;
+mod report;
 
 type Tag = u32;
 
+const KERN: Tag = allsorts::tag!(b"kern");
+
+/// The names a font must have: family, subfamily, full name, version, and PostScript name
+const REQUIRED_NAMES: [u16; 5] = [
+    NameTable::FONT_FAMILY_NAME,
+    NameTable::FONT_SUBFAMILY_NAME,
+    NameTable::FULL_FONT_NAME,
+    NameTable::VERSION_STRING,
+    NameTable::POSTSCRIPT_NAME,
+];
+
+const GLYPH_CLASS_NAMES: [&str; 4] = ["Base", "Ligature", "Mark", "Component"];
+
+/// Top DICT operators that have a default value
+const TOP_DICT_OPERATORS: &[Operator] = &[
+    Operator::IsFixedPitch,
+    Operator::ItalicAngle,
+    Operator::UnderlinePosition,
+    Operator::UnderlineThickness,
+    Operator::PaintType,
+    Operator::CharstringType,
+    Operator::FontMatrix,
+    Operator::FontBBox,
+    Operator::StrokeWidth,
+];
+
+/// Private DICT operators that have a default value, or are worth noting when absent
+const PRIVATE_DICT_OPERATORS: &[Operator] = &[
+    Operator::StdHW,
+    Operator::StdVW,
+    Operator::BlueScale,
+    Operator::BlueShift,
+    Operator::BlueFuzz,
+    Operator::ForceBold,
+    Operator::LanguageGroup,
+    Operator::ExpansionFactor,
+    Operator::InitialRandomSeed,
+    Operator::DefaultWidthX,
+    Operator::NominalWidthX,
+];
+
 #[derive(Copy, Clone)]
 struct Flags {
     encodings: bool,
     glyphs_names: bool,
     name: bool,
+    name_filter: NameFilter,
+    cff_verbose: bool,
+}
+
+/// The platform and language that `--name` records must have to be included, if given
+#[derive(Copy, Clone, Default)]
+struct NameFilter {
+    platform: Option<u16>,
+    language: Option<u16>,
 }
 
 pub fn main(opts: DumpOpts) -> Result<i32, BoxError> {
@@ -40,29 +107,145 @@ pub fn main(opts: DumpOpts) -> Result<i32, BoxError> {
         .table
         .map(|table| tag::from_string(&table))
         .transpose()?;
+    if table.is_some() && opts.json {
+        return Err(
+            ErrorMessage("--json can't be combined with --table, table data is binary").into(),
+        );
+    }
+    if opts.json {
+        // The options that print something other than the summary
+        let other_modes = [
+            ("--cff", opts.cff),
+            ("--glyph", opts.glyph.is_some()),
+            ("--count", opts.count),
+            ("--provenance", opts.provenance),
+            ("--head", opts.head),
+            ("--hhea", opts.hhea),
+            ("--hmtx", opts.hmtx),
+            ("--maxp", opts.maxp),
+            ("--post", opts.post),
+            ("--gdef", opts.gdef),
+            ("--os2", opts.os2),
+            ("--panose", opts.panose),
+            ("--loca", opts.loca),
+            ("--eblc", opts.eblc),
+            ("--locate", opts.locate.is_some()),
+            ("--gaps", opts.gaps),
+            ("--show-gap-data", opts.show_gap_data),
+            ("--verify-checksums", opts.verify_checksums),
+            ("--table-equal", opts.table_equal.is_some()),
+            ("--overlap", opts.overlap),
+            ("--use-my-metrics", opts.use_my_metrics),
+            ("--anomalies", opts.anomalies),
+            ("--kern", opts.kern),
+            ("--compare-kerning", opts.compare_kerning),
+            ("--fstype", opts.fstype),
+        ];
+        if let Some((option, _)) = other_modes.iter().find(|(_, given)| *given) {
+            return Err(format!("--json can't be combined with {}", option).into());
+        }
+    }
+    if opts.tuple.is_some() && opts.glyph.is_none() {
+        return Err(ErrorMessage("--tuple requires --glyph").into());
+    }
+    if opts.svg_path && (opts.glyph.is_none() || opts.tuple.is_some()) {
+        return Err(
+            ErrorMessage("--svg-path requires --glyph and can't be combined with --tuple").into(),
+        );
+    }
     if table.is_some() && io::stdout().is_terminal() {
         return Err(ErrorMessage("Not printing binary data to tty.").into());
     }
+    if opts.fstype {
+        return Ok(dump_fs_types(&opts));
+    }
+    if !opts.fonts.is_empty() {
+        return Err(ErrorMessage("only --fstype accepts more than one font").into());
+    }
 
-    let buffer = std::fs::read(&opts.font)?;
+    let buffer = read_font_file(&opts.font)?;
 
     if opts.cff {
-        dump_cff_table(ReadScope::new(&buffer))?;
+        dump_cff_table(ReadScope::new(&buffer), flags)?;
         return Ok(0);
     }
 
     let scope = ReadScope::new(&buffer);
     let font_file = scope.read::<FontData>()?;
+
+    if let Some(offset) = opts.locate {
+        locate_offset(&font_file, offset)?;
+        return Ok(0);
+    }
+    if opts.gaps || opts.show_gap_data {
+        gaps::dump_gaps(&font_file, &buffer, opts.show_gap_data)?;
+        return Ok(0);
+    }
+    if opts.verify_checksums {
+        let all_match = checksums::verify_checksums(&font_file, &buffer)?;
+        return Ok(if all_match { 0 } else { 1 });
+    }
+    if let Some(table_equal) = &opts.table_equal {
+        let all_equal = dump_table_equal(&font_file, tag::from_string(table_equal)?)?;
+        return Ok(if all_equal { 0 } else { 1 });
+    }
+
     let table_provider = font_file.table_provider(opts.index)?;
 
     if opts.loca {
         dump_loca_table(&table_provider)?;
     } else if opts.head {
         dump_head_table(&table_provider)?;
+    } else if opts.hhea {
+        dump_hhea_table(&table_provider)?;
     } else if opts.hmtx {
         dump_hmtx_table(&table_provider)?;
-    } else if let Some(glyph_id) = opts.glyph {
-        dump_glyph(&table_provider, glyph_id)?;
+    } else if opts.maxp {
+        dump_maxp_table(&table_provider)?;
+    } else if opts.post {
+        dump_post_table(&table_provider)?;
+    } else if opts.gdef {
+        dump_gdef_table(&table_provider)?;
+    } else if opts.os2 {
+        dump_os2_table(&table_provider)?;
+    } else if opts.panose {
+        dump_panose(&table_provider)?;
+    } else if opts.eblc {
+        dump_eblc_table(&table_provider)?;
+    } else if opts.overlap {
+        dump_overlap_flags(&table_provider)?;
+    } else if opts.use_my_metrics {
+        dump_use_my_metrics(&table_provider)?;
+    } else if opts.anomalies {
+        anomalies::dump_anomalies(&table_provider)?;
+    } else if opts.kern {
+        dump_kern_table(&table_provider)?;
+    } else if opts.compare_kerning {
+        compare_kerning(&table_provider)?;
+    } else if opts.provenance {
+        provenance::dump_provenance(&table_provider)?;
+    } else if opts.count {
+        dump_count(&font_file, &table_provider, opts.index)?;
+    } else if opts.json {
+        let report = DumpReport::new(&font_file, &table_provider, opts.index, flags)?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(0);
+    } else if let Some(glyphs) = &opts.glyph {
+        let is_range = glyphs.start() != glyphs.end();
+        let glyphs = if is_range {
+            clamp_glyph_range(&table_provider, glyphs)?
+        } else {
+            glyphs.clone()
+        };
+        for glyph_id in glyphs {
+            if is_range {
+                println!("=== glyph {} ===", glyph_id);
+            }
+            match &opts.tuple {
+                Some(tuple) => dump_glyph_variation(&table_provider, glyph_id, tuple)?,
+                None => dump_glyph(&table_provider, glyph_id, opts.svg_path)?,
+            }
+        }
     } else {
         match &font_file {
             FontData::OpenType(font_file) => match &font_file.data {
@@ -106,10 +289,214 @@ fn dump_ttc<'a>(
         let offset_table = scope.offset(offset_table_offset).read::<OffsetTable>()?;
         dump_ttf(scope, &offset_table, tag, flags)?;
     }
+    if tag.is_none() {
+        dump_ttc_sharing(scope, ttc)?;
+    }
     println!();
     Ok(())
 }
 
+/// Compare the `table_tag` table of each font of a collection, printing the groups of fonts whose
+/// tables have identical bytes and where each copy of the table is stored. Returns whether every
+/// font has the same table.
+fn dump_table_equal(font_file: &FontData<'_>, table_tag: Tag) -> Result<bool, BoxError> {
+    let (scope, ttc) = match font_file {
+        FontData::OpenType(font_file) => match &font_file.data {
+            OpenTypeData::Collection(ttc) => (&font_file.scope, ttc),
+            OpenTypeData::Single(_) => {
+                return Err(ErrorMessage("--table-equal requires a font collection").into())
+            }
+        },
+        FontData::Woff(_) | FontData::Woff2(_) => {
+            return Err(ErrorMessage("--table-equal requires a font collection").into())
+        }
+    };
+
+    // The distinct versions of the table, each with the fonts using each copy of it by offset
+    let mut groups: Vec<(&[u8], BTreeMap<u32, Vec<usize>>)> = Vec::new();
+    let mut missing = Vec::new();
+    for (index, offset_table_offset) in ttc.offset_tables.iter().enumerate() {
+        let offset_table_offset = usize::try_from(offset_table_offset).map_err(ParseError::from)?;
+        let offset_table = scope.offset(offset_table_offset).read::<OffsetTable>()?;
+        let record = match offset_table
+            .table_records
+            .iter()
+            .find(|record| record.table_tag == table_tag)
+        {
+            Some(record) => record,
+            None => {
+                missing.push(index);
+                continue;
+            }
+        };
+        let data = scope
+            .offset_length(
+                usize::try_from(record.offset)?,
+                usize::try_from(record.length)?,
+            )?
+            .data();
+        let group = match groups
+            .iter()
+            .position(|(group_data, _)| *group_data == data)
+        {
+            Some(group) => group,
+            None => {
+                groups.push((data, BTreeMap::new()));
+                groups.len() - 1
+            }
+        };
+        groups[group]
+            .1
+            .entry(record.offset)
+            .or_default()
+            .push(index);
+    }
+
+    let font_list = |indices: &[usize]| {
+        indices
+            .iter()
+            .map(|index| index.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    println!("{}:", DisplayTag(table_tag));
+    for (number, (data, copies)) in groups.iter().enumerate() {
+        let descriptions = copies
+            .iter()
+            .map(|(offset, indices)| format!("fonts {} @ {}", font_list(indices), offset))
+            .collect::<Vec<_>>();
+        let num_fonts = copies.values().map(Vec::len).sum::<usize>();
+        let storage = if copies.len() > 1 {
+            format!(" (same data stored {} times)", copies.len())
+        } else if num_fonts > 1 {
+            String::from(" (shared)")
+        } else {
+            String::new()
+        };
+        println!(
+            " - group {}: {} bytes, {}{}",
+            number + 1,
+            data.len(),
+            descriptions.join("; "),
+            storage
+        );
+    }
+    if !missing.is_empty() {
+        println!(" - missing from fonts {}", font_list(&missing));
+    }
+
+    let all_equal = groups.len() == 1 && missing.is_empty();
+    if all_equal {
+        println!("identical in all {} fonts", ttc.offset_tables.len());
+    } else {
+        println!("differs between fonts");
+    }
+    Ok(all_equal)
+}
+
+/// Summarise which tables are shared between the fonts of a collection
+fn dump_ttc_sharing<'a>(scope: &ReadScope<'a>, ttc: &TTCHeader<'a>) -> Result<(), BoxError> {
+    // The font index, offset, and length of each table, by tag
+    let mut tables: BTreeMap<Tag, Vec<(usize, u32, u32)>> = BTreeMap::new();
+    let mut units_per_em = Vec::new();
+    for (index, offset_table_offset) in ttc.offset_tables.iter().enumerate() {
+        let offset_table_offset = usize::try_from(offset_table_offset).map_err(ParseError::from)?;
+        let offset_table = scope.offset(offset_table_offset).read::<OffsetTable>()?;
+        for record in &offset_table.table_records {
+            tables
+                .entry(record.table_tag)
+                .or_default()
+                .push((index, record.offset, record.length));
+        }
+        if let Some(head) = offset_table.read_table(scope, tag::HEAD)? {
+            units_per_em.push((index, head.read::<HeadTable>()?.units_per_em));
+        }
+    }
+
+    println!("Collection summary");
+    // Version 2 headers have the DSIG tag, length, and offset after the offset table offsets
+    let dsig = if ttc.major_version >= 2 {
+        let mut ctxt = scope.offset(12 + 4 * ttc.offset_tables.len()).ctxt();
+        let dsig_tag = ctxt.read_u32be()?;
+        let dsig_length = ctxt.read_u32be()?;
+        let dsig_offset = ctxt.read_u32be()?;
+        (dsig_tag == allsorts::tag!(b"DSIG")).then(|| (dsig_offset, dsig_length))
+    } else {
+        None
+    };
+    match dsig {
+        Some((offset, length)) => println!(" - DSIG: offset {}, length {}", offset, length),
+        None => println!(" - DSIG: none"),
+    }
+
+    let mut actual_size = 0;
+    let mut deduplicated_size = 0;
+    let mut unique_tables = HashSet::new();
+    for (table_tag, records) in &tables {
+        // Group the fonts by the copy of the table they use
+        let mut copies: BTreeMap<u32, (u32, Vec<usize>)> = BTreeMap::new();
+        for &(index, offset, length) in records {
+            copies
+                .entry(offset)
+                .or_insert_with(|| (length, Vec::new()))
+                .1
+                .push(index);
+        }
+
+        let mut distinct_data = HashSet::new();
+        let mut descriptions = Vec::new();
+        for (&offset, (length, indices)) in &copies {
+            let data = scope
+                .offset_length(usize::try_from(offset)?, usize::try_from(*length)?)?
+                .data();
+            distinct_data.insert(data);
+            actual_size += *length;
+            if unique_tables.insert((*table_tag, data)) {
+                deduplicated_size += *length;
+            }
+            let indices = indices
+                .iter()
+                .map(|index| index.to_string())
+                .collect::<Vec<_>>();
+            descriptions.push(format!("fonts {} @ {}", indices.join(","), offset));
+        }
+        let sharing = if copies.len() == 1 && records.len() > 1 {
+            " (shared)"
+        } else if distinct_data.len() < copies.len() {
+            " (identical copies)"
+        } else {
+            ""
+        };
+        println!(
+            " - {}: {}{}",
+            DisplayTag(*table_tag),
+            descriptions.join("; "),
+            sharing
+        );
+    }
+    println!(
+        " - table bytes: {} actual, {} if identical tables were shared",
+        actual_size, deduplicated_size
+    );
+
+    let first_units_per_em = units_per_em.first().map(|&(_, units_per_em)| units_per_em);
+    if units_per_em
+        .iter()
+        .any(|&(_, units_per_em)| Some(units_per_em) != first_units_per_em)
+    {
+        let fonts = units_per_em
+            .iter()
+            .map(|(index, units_per_em)| format!("font {}: {}", index, units_per_em))
+            .collect::<Vec<_>>();
+        println!(
+            " - WARNING: fonts have different units_per_em ({})",
+            fonts.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
 fn dump_ttf<'a>(
     scope: &ReadScope<'a>,
     ttf: &OffsetTable<'a>,
@@ -141,13 +528,13 @@ fn dump_ttf<'a>(
     }
     if let Some(cff_table_data) = ttf.read_table(scope, tag::CFF)? {
         println!();
-        dump_cff_table(cff_table_data)?;
+        dump_cff_table(cff_table_data, flags)?;
     }
     println!();
     if flags.name {
         if let Some(name_table_data) = ttf.read_table(scope, tag::NAME)? {
             let name_table = name_table_data.read::<NameTable>()?;
-            dump_name_table(&name_table)?;
+            dump_name_table(&name_table, flags.name_filter)?;
         }
     }
     Ok(())
@@ -195,7 +582,7 @@ fn dump_woff(woff: &WoffFont<'_>, tag: Option<Tag>, flags: Flags) -> Result<(),
         {
             let table = entry.read_table(&woff.scope)?;
             let name_table = table.scope().read::<NameTable>()?;
-            dump_name_table(&name_table)?;
+            dump_name_table(&name_table, flags.name_filter)?;
         }
     }
 
@@ -268,15 +655,139 @@ fn dump_woff2<'a>(
         if let Some(table) = woff.read_table(tag::NAME, index)? {
             println!();
             let name_table = table.scope().read::<NameTable>()?;
-            dump_name_table(&name_table)?;
+            dump_name_table(&name_table, flags.name_filter)?;
         }
     }
 
     Ok(())
 }
 
-fn dump_name_table(name_table: &NameTable) -> Result<(), ParseError> {
+/// Print the table that contains the byte at `offset` in the font file
+fn locate_offset(font_file: &FontData<'_>, offset: usize) -> Result<(), BoxError> {
+    println!("offset {} (0x{:x})", offset, offset);
+    let found = match font_file {
+        FontData::OpenType(otf) => match &otf.data {
+            OpenTypeData::Single(ttf) => locate_in_font(font_file, 0, ttf, offset)?,
+            OpenTypeData::Collection(ttc) => {
+                let mut found = false;
+                for (index, offset_table_offset) in ttc.offset_tables.iter().enumerate() {
+                    let offset_table_offset =
+                        usize::try_from(offset_table_offset).map_err(ParseError::from)?;
+                    let ttf = otf
+                        .scope
+                        .offset(offset_table_offset)
+                        .read::<OffsetTable>()?;
+                    if locate_in_font(font_file, index, &ttf, offset)? {
+                        println!(" - used by font {}", index);
+                        found = true;
+                    }
+                }
+                found
+            }
+        },
+        FontData::Woff(_) => {
+            println!("WOFF table data is compressed, offsets can't be mapped to tables");
+            return Ok(());
+        }
+        FontData::Woff2(woff) => {
+            println!("WOFF2 offsets are within the decompressed table data block");
+            let entry = woff.table_directory.iter().find(|entry| {
+                let length = usize::try_from(entry.length()).unwrap_or(0);
+                entry.offset <= offset && offset < entry.offset + length
+            });
+            if let Some(entry) = entry {
+                println!(
+                    "{}: offset {} within table (table offset {}, length {})",
+                    DisplayTag(entry.tag),
+                    offset - entry.offset,
+                    entry.offset,
+                    entry.length()
+                );
+                if entry.tag == tag::GLYF {
+                    println!(" - glyph ids are not reported for the WOFF2 glyf table");
+                }
+            }
+            entry.is_some()
+        }
+    };
+
+    if !found {
+        println!("offset is not within any table");
+    }
+
+    Ok(())
+}
+
+/// Print the table in the font at `index` that contains `offset`, returning false if there isn't
+/// one
+fn locate_in_font(
+    font_file: &FontData<'_>,
+    index: usize,
+    ttf: &OffsetTable<'_>,
+    offset: usize,
+) -> Result<bool, BoxError> {
+    let mut record = None;
+    for table_record in &ttf.table_records {
+        let start = usize::try_from(table_record.offset)?;
+        let length = usize::try_from(table_record.length)?;
+        if start <= offset && offset < start + length {
+            record = Some((table_record, offset - start));
+            break;
+        }
+    }
+    let (table_record, table_offset) = match record {
+        Some(record) => record,
+        None => return Ok(false),
+    };
+
+    println!(
+        "{}: offset {} within table (table offset {}, length {})",
+        DisplayTag(table_record.table_tag),
+        table_offset,
+        table_record.offset,
+        table_record.length
+    );
+    if table_record.table_tag == tag::GLYF {
+        let provider = font_file.table_provider(index)?;
+        if let Some((glyph_id, glyph_offset)) = locate_glyph(&provider, table_offset)? {
+            println!(
+                " - glyph {}: offset {} within glyph",
+                glyph_id, glyph_offset
+            );
+        }
+    }
+
+    Ok(true)
+}
+
+/// Find the glyph whose `loca` range covers `table_offset` in the `glyf` table
+fn locate_glyph(
+    provider: &impl FontTableProvider,
+    table_offset: usize,
+) -> Result<Option<(usize, usize)>, ParseError> {
+    let head = ReadScope::new(&provider.read_table_data(tag::HEAD)?).read::<HeadTable>()?;
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+    let loca_data = provider.read_table_data(tag::LOCA)?;
+    let loca = ReadScope::new(&loca_data)
+        .read_dep::<LocaTable>((usize::from(maxp.num_glyphs), head.index_to_loc_format))?;
+
+    let offsets = loca
+        .offsets
+        .iter()
+        .map(usize::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    let glyph = offsets
+        .windows(2)
+        .position(|range| range[0] <= table_offset && table_offset < range[1])
+        .map(|glyph_id| (glyph_id, table_offset - offsets[glyph_id]));
+    Ok(glyph)
+}
+
+fn dump_name_table(name_table: &NameTable, filter: NameFilter) -> Result<(), ParseError> {
     for name_record in &name_table.name_records {
+        if !filter.matches(name_record.platform_id, name_record.language_id) {
+            continue;
+        }
         let platform = name_record.platform_id;
         let encoding = name_record.encoding_id;
         let language = name_record.language_id;
@@ -286,25 +797,30 @@ fn dump_name_table(name_table: &NameTable) -> Result<(), ParseError> {
             .string_storage
             .offset_length(offset, length)?
             .data();
-        let name = match (platform, encoding, language) {
-            (0, _, _) => decode(UTF_16BE, name_data),
-            (1, 0, _) => decode(MACINTOSH, name_data),
-            (3, 0, _) => decode(UTF_16BE, name_data),
-            (3, 1, _) => decode(UTF_16BE, name_data),
-            (3, 10, _) => decode(UTF_16BE, name_data),
-            _ => format!(
-                "(unknown platform={} encoding={} language={})",
-                platform, encoding, language
-            ),
-        };
+        let name = decode_name(platform, encoding, language, name_data);
         match get_name_meaning(name_record.name_id) {
-            Some(meaning) => println!("{}", meaning,),
-            None => println!("name {}", name_record.name_id,),
+            Some(meaning) => println!("{} ({})", meaning, platform_name(platform)),
+            None => println!("name {} ({})", name_record.name_id, platform_name(platform)),
         }
         println!("{:?}", name);
         println!();
     }
 
+    // Each required name must be present for at least one platform
+    for &name_id in REQUIRED_NAMES.iter() {
+        let present = name_table
+            .name_records
+            .iter()
+            .any(|name_record| name_record.name_id == name_id);
+        if !present {
+            eprintln!(
+                "warning: required name {} ({}) is missing",
+                name_id,
+                get_name_meaning(name_id).unwrap_or("unknown")
+            );
+        }
+    }
+
     if let Some(langtag_records) = &name_table.opt_langtag_records {
         for langtag in langtag_records.iter() {
             let name_data = name_table
@@ -319,12 +835,379 @@ fn dump_name_table(name_table: &NameTable) -> Result<(), ParseError> {
     Ok(())
 }
 
+fn platform_name(platform: u16) -> Cow<'static, str> {
+    match platform {
+        0 => Cow::from("Unicode"),
+        1 => Cow::from("Macintosh"),
+        2 => Cow::from("ISO"),
+        3 => Cow::from("Windows"),
+        4 => Cow::from("Custom"),
+        _ => Cow::from(format!("platform {}", platform)),
+    }
+}
+
+fn decode_name(platform: u16, encoding: u16, language: u16, name_data: &[u8]) -> String {
+    match (platform, encoding, language) {
+        (0, _, _) => decode(UTF_16BE, name_data),
+        (1, 0, _) => decode(MACINTOSH, name_data),
+        (3, 0, _) => decode(UTF_16BE, name_data),
+        (3, 1, _) => decode(UTF_16BE, name_data),
+        (3, 10, _) => decode(UTF_16BE, name_data),
+        _ => format!(
+            "(unknown platform={} encoding={} language={})",
+            platform, encoding, language
+        ),
+    }
+}
+
 fn dump_head_table(provider: &impl FontTableProvider) -> Result<(), ParseError> {
     let head = ReadScope::new(&provider.read_table_data(tag::HEAD)?).read::<HeadTable>()?;
     println!("{:#?}", head);
+    println!();
+    match head.index_to_loc_format {
+        IndexToLocFormat::Short => println!("loca format: short (0), 16-bit offsets / 2"),
+        IndexToLocFormat::Long => println!("loca format: long (1), 32-bit offsets"),
+    }
+    if head.glyph_data_format == 0 {
+        println!("glyph data format: 0");
+    } else {
+        println!(
+            "glyph data format: {} (unusual, the only defined format is 0)",
+            head.glyph_data_format
+        );
+    }
+    Ok(())
+}
+
+fn dump_hhea_table(provider: &impl FontTableProvider) -> Result<(), ParseError> {
+    let hhea = ReadScope::new(&provider.read_table_data(tag::HHEA)?).read::<HheaTable>()?;
+    println!("{:#?}", hhea);
+    Ok(())
+}
+
+fn dump_maxp_table(provider: &impl FontTableProvider) -> Result<(), ParseError> {
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+    println!("{:#?}", maxp);
+    Ok(())
+}
+
+fn dump_post_table(provider: &impl FontTableProvider) -> Result<(), ParseError> {
+    let table = provider.read_table_data(tag::POST)?;
+    let post = ReadScope::new(&table).read::<PostTable<'_>>()?;
+    let header = &post.header;
+    let glyph_names = match (header.version, &post.opt_sub_table) {
+        (0x00010000, _) => String::from("the standard Macintosh names"),
+        (0x00020000, Some(sub_table)) => format!("stored for {} glyphs", sub_table.num_glyphs),
+        (0x00025000, _) => String::from("offsets into the standard Macintosh names (deprecated)"),
+        _ => String::from("absent"),
+    };
+    println!(
+        "version: {}.{:x} (0x{:08x})",
+        header.version >> 16,
+        (header.version >> 12) & 0xf,
+        header.version
+    );
+    println!("glyph names: {}", glyph_names);
+    println!(
+        "italic angle: {} degrees (0x{:08x})",
+        f64::from(header.italic_angle) / 65536.,
+        header.italic_angle
+    );
+    println!("underline position: {}", header.underline_position);
+    println!("underline thickness: {}", header.underline_thickness);
+    println!(
+        "fixed pitch: {}",
+        if header.is_fixed_pitch != 0 {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    Ok(())
+}
+
+/// Print the number of tables and glyphs, the kind of outlines, the layout tables, whether the font
+/// is variable, and units per em as `key=value` pairs on one line
+fn dump_count(
+    font_file: &FontData<'_>,
+    provider: &impl FontTableProvider,
+    index: usize,
+) -> Result<(), BoxError> {
+    let num_tables = table_tags(font_file, index)?.len();
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+    let head = ReadScope::new(&provider.read_table_data(tag::HEAD)?).read::<HeadTable>()?;
+    let outlines = if provider.has_table(tag::GLYF) {
+        "glyf"
+    } else if provider.has_table(tag::CFF) {
+        "cff"
+    } else if provider.has_table(tag::CFF2) {
+        "cff2"
+    } else {
+        "none"
+    };
+    let yes_no = |table| {
+        if provider.has_table(table) {
+            "yes"
+        } else {
+            "no"
+        }
+    };
+    println!(
+        "tables={} glyphs={} outlines={} gsub={} gpos={} gdef={} variable={} upm={}",
+        num_tables,
+        maxp.num_glyphs,
+        outlines,
+        yes_no(tag::GSUB),
+        yes_no(tag::GPOS),
+        yes_no(tag::GDEF),
+        yes_no(tag::FVAR),
+        head.units_per_em
+    );
+    Ok(())
+}
+
+fn dump_os2_table(provider: &impl FontTableProvider) -> Result<(), ParseError> {
+    let table = provider.read_table_data(tag::OS_2)?;
+    let os2 = ReadScope::new(&table).read_dep::<Os2>(table.len())?;
+    println!("{:#?}", os2);
+    println!();
+    match weight_class_name(os2.us_weight_class) {
+        Some(name) => println!("weight class: {} ({})", os2.us_weight_class, name),
+        None => println!("weight class: {}", os2.us_weight_class),
+    }
+    match WIDTH_CLASS_NAMES.get(usize::from(os2.us_width_class).wrapping_sub(1)) {
+        Some(name) => println!("width class: {} ({})", os2.us_width_class, name),
+        None => println!(
+            "width class: {} (invalid, expected 1 to 9)",
+            os2.us_width_class
+        ),
+    }
+    println!(
+        "fsType: 0x{:04x} {}",
+        os2.fs_type,
+        fs_type_flags(os2.fs_type).join(" | ")
+    );
+    println!(
+        "fsSelection: 0x{:04x} {}",
+        os2.fs_selection,
+        bit_flags(os2.fs_selection, &FS_SELECTION_FLAGS).join(" | ")
+    );
+    let regular = os2.fs_selection & FS_SELECTION_REGULAR != 0;
+    if regular && os2.fs_selection & (FS_SELECTION_ITALIC | FS_SELECTION_BOLD) != 0 {
+        println!("warning: REGULAR is set along with ITALIC or BOLD");
+    }
+    print_panose(&os2.panose);
     Ok(())
 }
 
+const WIDTH_CLASS_NAMES: [&str; 9] = [
+    "Ultra-condensed",
+    "Extra-condensed",
+    "Condensed",
+    "Semi-condensed",
+    "Medium",
+    "Semi-expanded",
+    "Expanded",
+    "Extra-expanded",
+    "Ultra-expanded",
+];
+
+const FS_SELECTION_ITALIC: u16 = 1 << 0;
+const FS_SELECTION_BOLD: u16 = 1 << 5;
+const FS_SELECTION_REGULAR: u16 = 1 << 6;
+
+const FS_SELECTION_FLAGS: [(u16, &str); 10] = [
+    (FS_SELECTION_ITALIC, "ITALIC"),
+    (1 << 1, "UNDERSCORE"),
+    (1 << 2, "NEGATIVE"),
+    (1 << 3, "OUTLINED"),
+    (1 << 4, "STRIKEOUT"),
+    (FS_SELECTION_BOLD, "BOLD"),
+    (FS_SELECTION_REGULAR, "REGULAR"),
+    (1 << 7, "USE_TYPO_METRICS"),
+    (1 << 8, "WWS"),
+    (1 << 9, "OBLIQUE"),
+];
+
+const FS_TYPE_FLAGS: [(u16, &str); 5] = [
+    (0x0002, "RESTRICTED_LICENSE_EMBEDDING"),
+    (0x0004, "PREVIEW_AND_PRINT_EMBEDDING"),
+    (0x0008, "EDITABLE_EMBEDDING"),
+    (0x0100, "NO_SUBSETTING"),
+    (0x0200, "BITMAP_EMBEDDING_ONLY"),
+];
+
+fn weight_class_name(weight_class: u16) -> Option<&'static str> {
+    match weight_class {
+        100 => Some("Thin"),
+        200 => Some("Extra-light"),
+        300 => Some("Light"),
+        400 => Some("Regular"),
+        500 => Some("Medium"),
+        600 => Some("Semi-bold"),
+        700 => Some("Bold"),
+        800 => Some("Extra-bold"),
+        900 => Some("Black"),
+        _ => None,
+    }
+}
+
+/// Print the fsType embedding permissions of each font as `path: fsType 0x0000 FLAGS`, returning
+/// the exit status.
+///
+/// Fonts that can't be read are reported on their line and make the status 1, so one bad file
+/// doesn't stop the rest being checked.
+fn dump_fs_types(opts: &DumpOpts) -> i32 {
+    let mut status = 0;
+    for path in std::iter::once(&opts.font).chain(&opts.fonts) {
+        match read_fs_type(path, opts.index) {
+            Ok(Some(fs_type)) => println!(
+                "{}: fsType 0x{:04x} {}",
+                path,
+                fs_type,
+                fs_type_flags(fs_type).join(" | ")
+            ),
+            Ok(None) => println!("{}: no OS/2 table", path),
+            Err(err) => {
+                println!("{}: error: {}", path, err);
+                status = 1;
+            }
+        }
+    }
+    status
+}
+
+fn read_fs_type(path: &str, index: usize) -> Result<Option<u16>, BoxError> {
+    let buffer = std::fs::read(path)?;
+    let font_file = ReadScope::new(&buffer).read::<FontData>()?;
+    let provider = font_file.table_provider(index)?;
+    let table = match provider.table_data(tag::OS_2)? {
+        Some(table) => table,
+        None => return Ok(None),
+    };
+    let os2 = ReadScope::new(&table).read_dep::<Os2>(table.len())?;
+    Ok(Some(os2.fs_type))
+}
+
+fn fs_type_flags(fs_type: u16) -> Vec<String> {
+    let mut flags = bit_flags(fs_type, &FS_TYPE_FLAGS);
+    // The usage permissions in the low 4 bits are 0 for installable embedding
+    if fs_type & 0x000f == 0 {
+        flags.insert(0, String::from("INSTALLABLE_EMBEDDING"));
+    }
+    flags
+}
+
+/// The names of the flags set in `value`, followed by any bits set that aren't named
+fn bit_flags(value: u16, names: &[(u16, &str)]) -> Vec<String> {
+    let mut flags = names
+        .iter()
+        .filter(|(bit, _)| value & bit != 0)
+        .map(|(_, name)| name.to_string())
+        .collect::<Vec<_>>();
+    let unnamed = names.iter().fold(value, |value, (bit, _)| value & !bit);
+    if unnamed != 0 {
+        flags.push(format!("reserved bits 0x{:04x}", unnamed));
+    }
+    flags
+}
+
+fn dump_panose(provider: &impl FontTableProvider) -> Result<(), ParseError> {
+    let table = provider.read_table_data(tag::OS_2)?;
+    let os2 = ReadScope::new(&table).read_dep::<Os2>(table.len())?;
+    print_panose(&os2.panose);
+    Ok(())
+}
+
+fn print_panose(panose: &[u8; 10]) {
+    println!(
+        "panose: {}",
+        panose
+            .iter()
+            .map(|digit| digit.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    for (name, label) in panose::describe(panose) {
+        println!(" - {}: {}", name, label);
+    }
+}
+
+fn dump_gdef_table(provider: &impl FontTableProvider) -> Result<(), ParseError> {
+    let Some(gdef_data) = provider.table_data(tag::GDEF)? else {
+        println!("font has no GDEF table");
+        return Ok(());
+    };
+    let scope = ReadScope::new(&gdef_data);
+    let mut ctxt = scope.ctxt();
+    let major_version = ctxt.read_u16be()?;
+    let minor_version = ctxt.read_u16be()?;
+    let glyph_class_def_offset = ctxt.read_u16be()?;
+    let _attach_list_offset = ctxt.read_u16be()?;
+    let _lig_caret_list_offset = ctxt.read_u16be()?;
+    let mark_attach_class_def_offset = ctxt.read_u16be()?;
+    // MarkGlyphSets were added in version 1.2
+    let mark_glyph_sets_offset = if minor_version >= 2 {
+        ctxt.read_u16be()?
+    } else {
+        0
+    };
+
+    let read_classes = |offset: u16| -> Result<Option<BTreeMap<u16, u16>>, ParseError> {
+        match offset {
+            0 => Ok(None),
+            _ => read_class_def(&scope_at_offset(&scope, usize::from(offset))?).map(Some),
+        }
+    };
+    let glyph_classes = read_classes(glyph_class_def_offset)?;
+    let mark_attach_classes = read_classes(mark_attach_class_def_offset)?;
+
+    println!("GDEF version {}.{}", major_version, minor_version);
+    if glyph_classes.is_none() {
+        println!("no glyph class definitions");
+    }
+    if mark_attach_classes.is_none() {
+        println!("no mark attachment class definitions");
+    }
+    match mark_glyph_sets_offset {
+        0 => println!("no MarkGlyphSets table"),
+        offset => {
+            let mut ctxt = scope_at_offset(&scope, usize::from(offset))?.ctxt();
+            let _format = ctxt.read_u16be()?;
+            let mark_glyph_set_count = ctxt.read_u16be()?;
+            println!("MarkGlyphSets table: {} set(s)", mark_glyph_set_count);
+        }
+    }
+    println!();
+
+    // Only glyphs assigned a class in either definition are listed
+    for_each_glyph_name(provider, |glyph_id, name| {
+        let glyph_class = glyph_classes
+            .as_ref()
+            .and_then(|classes| classes.get(&glyph_id));
+        let mark_attach_class = mark_attach_classes
+            .as_ref()
+            .and_then(|classes| classes.get(&glyph_id));
+        if glyph_class.is_none() && mark_attach_class.is_none() {
+            return;
+        }
+        let glyph_class = match glyph_class {
+            Some(&class) => match GLYPH_CLASS_NAMES.get(usize::from(class) - 1) {
+                Some(class_name) => class_name.to_string(),
+                None => format!("class {}", class),
+            },
+            None => String::from("no class"),
+        };
+        match mark_attach_class {
+            Some(class) => println!(
+                "{} ({}): {}, mark attachment class {}",
+                glyph_id, name, glyph_class, class
+            ),
+            None => println!("{} ({}): {}", glyph_id, name, glyph_class),
+        }
+    })
+}
+
 fn dump_hmtx_table(provider: &impl FontTableProvider) -> Result<(), ParseError> {
     let table = provider.table_data(tag::MAXP)?.expect("no maxp table");
     let scope = ReadScope::new(table.borrow());
@@ -367,7 +1250,72 @@ fn dump_loca_table(provider: &impl FontTableProvider) -> Result<(), ParseError>
     Ok(())
 }
 
-fn dump_cff_table<'a>(scope: ReadScope<'a>) -> Result<(), ParseError> {
+/// The flags of an EBLC/CBLC BitmapSize record, saying which metrics the strike has
+const BITMAP_SIZE_FLAGS: [(u16, &str); 2] = [(0x01, "horizontal"), (0x02, "vertical")];
+
+fn dump_eblc_table(provider: &impl FontTableProvider) -> Result<(), BoxError> {
+    let (table_tag, table) = if let Some(table) = provider.table_data(tag::EBLC)? {
+        (tag::EBLC, table)
+    } else if let Some(table) = provider.table_data(tag::CBLC)? {
+        (tag::CBLC, table)
+    } else {
+        return Err(ErrorMessage("no EBLC or CBLC table").into());
+    };
+    let scope = ReadScope::new(table.borrow());
+    let mut ctxt = scope.ctxt();
+    let major_version = ctxt.read_u16be()?;
+    let minor_version = ctxt.read_u16be()?;
+    let num_sizes = ctxt.read_u32be()?;
+
+    println!("{}:", DisplayTag(table_tag));
+    println!(" - version: {}.{}", major_version, minor_version);
+    println!(" - num sizes: {}", num_sizes);
+    for index in 0..num_sizes {
+        let index_sub_table_array_offset = ctxt.read_u32be()?;
+        let _index_tables_size = ctxt.read_u32be()?;
+        let number_of_index_sub_tables = ctxt.read_u32be()?;
+        let _color_ref = ctxt.read_u32be()?;
+        // Skip the hori and vert sbitLineMetrics
+        let _line_metrics = ctxt.read_slice(24)?;
+        let start_glyph_index = ctxt.read_u16be()?;
+        let end_glyph_index = ctxt.read_u16be()?;
+        let ppem_x = ctxt.read_u8()?;
+        let ppem_y = ctxt.read_u8()?;
+        let bit_depth = ctxt.read_u8()?;
+        let flags = bit_flags(u16::from(ctxt.read_u8()?), &BITMAP_SIZE_FLAGS);
+
+        println!();
+        println!(" - Strike {}", index);
+        println!("  - ppem: {}x{}", ppem_x, ppem_y);
+        println!("  - bit depth: {}", bit_depth);
+        if flags.is_empty() {
+            println!("  - flags: none");
+        } else {
+            println!("  - flags: {}", flags.join(", "));
+        }
+        println!("  - glyphs: {}..={}", start_glyph_index, end_glyph_index);
+        println!("  - index sub-tables: {}", number_of_index_sub_tables);
+
+        let array_offset = usize::try_from(index_sub_table_array_offset)?;
+        let mut array = scope.offset(array_offset).ctxt();
+        for _ in 0..number_of_index_sub_tables {
+            let first_glyph_index = array.read_u16be()?;
+            let last_glyph_index = array.read_u16be()?;
+            let additional_offset = usize::try_from(array.read_u32be()?)?;
+            let mut header = scope.offset(array_offset + additional_offset).ctxt();
+            let index_format = header.read_u16be()?;
+            let image_format = header.read_u16be()?;
+            println!(
+                "   - glyphs {}..={}: index format {}, image format {}",
+                first_glyph_index, last_glyph_index, index_format, image_format
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn dump_cff_table<'a>(scope: ReadScope<'a>, flags: Flags) -> Result<(), ParseError> {
     let cff = scope.read::<CFF>()?;
 
     println!("- CFF:");
@@ -383,25 +1331,14 @@ fn dump_cff_table<'a>(scope: ReadScope<'a>) -> Result<(), ParseError> {
     let font = cff.fonts.get(0).ok_or(ParseError::MissingValue)?;
     let char_strings_index = &font.char_strings_index;
     println!(" - num glyphs: {}", char_strings_index.len());
-    println!(
-        " - charset: {}",
-        match font.charset {
-            Charset::ISOAdobe => "ISO Adobe",
-            Charset::Expert => "Expert",
-            Charset::ExpertSubset => "Expert Subset",
-            Charset::Custom(_) => "Custom",
-        }
-    );
-    println!(
-        " - variant: {}",
-        match font.data {
-            CFFVariant::CID(_) => "CID",
-            CFFVariant::Type1(_) => "Type 1",
-        }
-    );
+    println!(" - charset: {}", charset_name(&font.charset));
+    println!(" - variant: {}", cff_variant_name(&font.data));
     println!();
     println!(" - Top DICT");
     dump_cff_dict(&cff, &font.top_dict, 2);
+    if flags.cff_verbose {
+        dump_cff_dict_defaults(&font.top_dict, TOP_DICT_OPERATORS, 2);
+    }
     match &font.data {
         CFFVariant::Type1(ref type1) => {
             println!();
@@ -416,6 +1353,10 @@ fn dump_cff_table<'a>(scope: ReadScope<'a>) -> Result<(), ParseError> {
             println!();
             println!(" - Private DICT");
             dump_cff_dict(&cff, &type1.private_dict, 2);
+            if flags.cff_verbose {
+                dump_cff_dict_defaults(&type1.private_dict, PRIVATE_DICT_OPERATORS, 2);
+            }
+            dump_cff_widths(&font.top_dict, &type1.private_dict, 2);
             let (subrs_count, subrs_size) = match type1.local_subr_index {
                 Some(ref index) => (index.len(), index.data_len()),
                 None => (0, 0),
@@ -433,6 +1374,10 @@ fn dump_cff_table<'a>(scope: ReadScope<'a>) -> Result<(), ParseError> {
                 let (private_dict, _private_dict_offset) =
                     font_dict.read_private_dict::<cff::PrivateDict>(&scope, cff::MAX_OPERANDS)?;
                 dump_cff_dict(&cff, &private_dict, 4);
+                if flags.cff_verbose {
+                    dump_cff_dict_defaults(&private_dict, PRIVATE_DICT_OPERATORS, 4);
+                }
+                dump_cff_widths(&font.top_dict, &private_dict, 4);
             }
             let (subrs_count, subrs_size) =
                 cid.local_subr_indices
@@ -445,6 +1390,9 @@ fn dump_cff_table<'a>(scope: ReadScope<'a>) -> Result<(), ParseError> {
                         (count, size)
                     });
             println!();
+            println!(" - FDSelect");
+            dump_fd_select(&cid.fd_select, char_strings_index.len())?;
+            println!();
             println!(
                 " - Local subrs: {} ({} bytes) in {} indices",
                 subrs_count,
@@ -462,7 +1410,45 @@ fn dump_cff_table<'a>(scope: ReadScope<'a>) -> Result<(), ParseError> {
     Ok(())
 }
 
-fn dump_glyph(provider: &impl FontTableProvider, glyph_id: u16) -> Result<(), ParseError> {
+fn charset_name(charset: &Charset<'_>) -> &'static str {
+    match charset {
+        Charset::ISOAdobe => "ISO Adobe",
+        Charset::Expert => "Expert",
+        Charset::ExpertSubset => "Expert Subset",
+        Charset::Custom(_) => "Custom",
+    }
+}
+
+fn cff_variant_name(variant: &CFFVariant<'_>) -> &'static str {
+    match variant {
+        CFFVariant::CID(_) => "CID",
+        CFFVariant::Type1(_) => "Type 1",
+    }
+}
+
+/// Limit the glyph ids in `glyphs` to those in the font, warning if any were dropped
+fn clamp_glyph_range(
+    provider: &impl FontTableProvider,
+    glyphs: &RangeInclusive<u16>,
+) -> Result<RangeInclusive<u16>, ParseError> {
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+    let last_glyph = maxp.num_glyphs.saturating_sub(1);
+    if *glyphs.end() <= last_glyph {
+        return Ok(glyphs.clone());
+    }
+    eprintln!(
+        "warning: the font has {} glyphs, glyph ids after {} are skipped",
+        maxp.num_glyphs, last_glyph
+    );
+    // An empty range if the whole range is past the last glyph
+    Ok(*glyphs.start()..=last_glyph)
+}
+
+fn dump_glyph(
+    provider: &impl FontTableProvider,
+    glyph_id: u16,
+    svg_path: bool,
+) -> Result<(), ParseError> {
     let table = provider.table_data(tag::HEAD)?.expect("no head table");
     let scope = ReadScope::new(table.borrow());
     let head = scope.read::<HeadTable>()?;
@@ -478,7 +1464,7 @@ fn dump_glyph(provider: &impl FontTableProvider, glyph_id: u16) -> Result<(), Pa
 
     let table = provider.table_data(tag::GLYF)?.expect("no glyf table");
     let scope = ReadScope::new(table.borrow());
-    let glyf = scope.read_dep::<GlyfTable>(&loca)?;
+    let mut glyf = scope.read_dep::<GlyfTable>(&loca)?;
 
     let mut glyph = glyf
         .records()
@@ -488,6 +1474,349 @@ fn dump_glyph(provider: &impl FontTableProvider, glyph_id: u16) -> Result<(), Pa
     glyph.parse()?;
     println!("{:#?}", glyph);
 
+    if svg_path {
+        // Components of composite glyphs are visited too. The path is in font units, which have
+        // y pointing up, so it's flipped to be upright in SVG
+        let mut sink = PathSink::default();
+        glyf.visit(glyph_id, &mut sink)?;
+        println!();
+        println!(
+            "<path transform=\"scale(1 -1)\" d=\"{}\"/>",
+            sink.path.trim_start()
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the points and metrics of a glyph at the default location and at the user-tuple `tuple`
+fn dump_glyph_variation(
+    provider: &impl FontTableProvider,
+    glyph_id: u16,
+    tuple: &str,
+) -> Result<(), BoxError> {
+    if !provider.has_table(tag::FVAR) {
+        return Err(ErrorMessage("--tuple requires a variable font").into());
+    }
+    let user_tuple = parse_tuple(tuple)?;
+    let (instance, _tuple) = allsorts::variations::instance(provider, &user_tuple)?;
+    let instance_file = ReadScope::new(&instance).read::<FontData<'_>>()?;
+    let instance_provider = instance_file.table_provider(0)?;
+
+    println!("glyph {}: default vs tuple {}", glyph_id, tuple);
+    match (
+        simple_glyph_points(provider, glyph_id)?,
+        simple_glyph_points(&instance_provider, glyph_id)?,
+    ) {
+        (Some(default), Some(varied)) if default.len() == varied.len() => {
+            println!("{:<6} {:<14} {:<14} delta", "point", "default", "varied");
+            for (i, (default, varied)) in default.iter().zip(&varied).enumerate() {
+                println!(
+                    "{:<6} {:<14} {:<14} ({}, {})",
+                    i,
+                    format!("({}, {})", default.0, default.1),
+                    format!("({}, {})", varied.0, varied.1),
+                    i32::from(varied.0) - i32::from(default.0),
+                    i32::from(varied.1) - i32::from(default.1)
+                );
+            }
+        }
+        _ => println!("not a simple glyph, only the metrics are compared"),
+    }
+
+    // Phantom point deltas in gvar, or HVAR, are applied to hmtx when instancing
+    let (default_advance, default_lsb) = glyph_h_metrics(provider, glyph_id)?;
+    let (varied_advance, varied_lsb) = glyph_h_metrics(&instance_provider, glyph_id)?;
+    println!(
+        "advance: {} -> {} ({:+})",
+        default_advance,
+        varied_advance,
+        i32::from(varied_advance) - i32::from(default_advance)
+    );
+    println!(
+        "lsb: {} -> {} ({:+})",
+        default_lsb,
+        varied_lsb,
+        i32::from(varied_lsb) - i32::from(default_lsb)
+    );
+
+    Ok(())
+}
+
+/// The points of a simple glyph, no points for an empty glyph, and `None` for a composite glyph
+fn simple_glyph_points(
+    provider: &impl FontTableProvider,
+    glyph_id: u16,
+) -> Result<Option<Vec<(i16, i16)>>, ParseError> {
+    let head = ReadScope::new(&provider.read_table_data(tag::HEAD)?).read::<HeadTable>()?;
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+    let loca_data = provider.read_table_data(tag::LOCA)?;
+    let loca = ReadScope::new(&loca_data)
+        .read_dep::<LocaTable>((usize::from(maxp.num_glyphs), head.index_to_loc_format))?;
+    let glyf_data = provider.read_table_data(tag::GLYF)?;
+    let glyf = ReadScope::new(&glyf_data).read_dep::<GlyfTable>(&loca)?;
+
+    let mut record = glyf
+        .records()
+        .get(usize::from(glyph_id))
+        .ok_or(ParseError::BadValue)?
+        .clone();
+    record.parse()?;
+    let points = match &record {
+        GlyfRecord::Parsed(Glyph::Simple(glyph)) => Some(
+            glyph
+                .coordinates
+                .iter()
+                .map(|(_flags, point)| (point.0, point.1))
+                .collect(),
+        ),
+        GlyfRecord::Parsed(Glyph::Empty(_)) => Some(Vec::new()),
+        _ => None,
+    };
+    Ok(points)
+}
+
+/// The advance width and left side bearing of a glyph from hmtx
+fn glyph_h_metrics(
+    provider: &impl FontTableProvider,
+    glyph_id: u16,
+) -> Result<(u16, i16), ParseError> {
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+    let hhea = ReadScope::new(&provider.read_table_data(tag::HHEA)?).read::<HheaTable>()?;
+    let num_glyphs = usize::from(maxp.num_glyphs);
+    let num_metrics = usize::from(hhea.num_h_metrics);
+    let hmtx_data = provider.read_table_data(tag::HMTX)?;
+    let hmtx = ReadScope::new(&hmtx_data).read_dep::<HmtxTable<'_>>((num_glyphs, num_metrics))?;
+    h_metrics(&hmtx, num_glyphs, glyph_id).ok_or(ParseError::BadIndex)
+}
+
+/// The advance and left side bearing of `glyph_id` in `hmtx`, or `None` if it's out of range
+fn h_metrics(hmtx: &HmtxTable<'_>, num_glyphs: usize, glyph_id: u16) -> Option<(u16, i16)> {
+    let num_metrics = hmtx.h_metrics.len();
+    let glyph_id = usize::from(glyph_id);
+    if glyph_id >= num_glyphs || num_metrics == 0 {
+        return None;
+    }
+    if glyph_id < num_metrics {
+        let metric = hmtx.h_metrics.get_item(glyph_id);
+        Some((metric.advance_width, metric.lsb))
+    } else {
+        // Glyphs after the last long metric share its advance
+        let metric = hmtx.h_metrics.get_item(num_metrics - 1);
+        let lsb = hmtx.left_side_bearings.get_item(glyph_id - num_metrics);
+        Some((metric.advance_width, lsb))
+    }
+}
+
+/// Print the glyphs that set the flags signalling overlapping contours or components
+fn dump_overlap_flags(provider: &impl FontTableProvider) -> Result<(), ParseError> {
+    let head = ReadScope::new(&provider.read_table_data(tag::HEAD)?).read::<HeadTable>()?;
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+    let loca_data = provider.read_table_data(tag::LOCA)?;
+    let loca = ReadScope::new(&loca_data)
+        .read_dep::<LocaTable>((usize::from(maxp.num_glyphs), head.index_to_loc_format))?;
+    let glyf_data = provider.read_table_data(tag::GLYF)?;
+    let glyf = ReadScope::new(&glyf_data).read_dep::<GlyfTable>(&loca)?;
+
+    let mut num_simple = 0;
+    let mut num_composite = 0;
+    for (glyph_id, record) in glyf.records().iter().enumerate() {
+        let mut record = record.clone();
+        record.parse()?;
+        let flag = match &record {
+            GlyfRecord::Parsed(Glyph::Simple(glyph))
+                if glyph
+                    .coordinates
+                    .iter()
+                    .any(|(flags, _)| flags.contains(SimpleGlyphFlag::OVERLAP_SIMPLE)) =>
+            {
+                num_simple += 1;
+                "OVERLAP_SIMPLE"
+            }
+            GlyfRecord::Parsed(Glyph::Composite(glyph))
+                if glyph.glyphs.iter().any(|component| {
+                    component
+                        .flags
+                        .contains(CompositeGlyphFlag::OVERLAP_COMPOUND)
+                }) =>
+            {
+                num_composite += 1;
+                "OVERLAP_COMPOUND"
+            }
+            _ => continue,
+        };
+        println!("{}: {}", glyph_id, flag);
+    }
+
+    if num_simple + num_composite == 0 {
+        println!("no glyphs set overlap flags");
+    } else {
+        println!(
+            "{} simple and {} composite glyphs set overlap flags",
+            num_simple, num_composite
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the composite glyphs that set the USE_MY_METRICS flag on a component, with the hmtx
+/// advance of the component, which is the advance the composite is rendered with, and that of
+/// the composite itself
+fn dump_use_my_metrics(provider: &impl FontTableProvider) -> Result<(), ParseError> {
+    let head = ReadScope::new(&provider.read_table_data(tag::HEAD)?).read::<HeadTable>()?;
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+    let hhea = ReadScope::new(&provider.read_table_data(tag::HHEA)?).read::<HheaTable>()?;
+    let num_glyphs = usize::from(maxp.num_glyphs);
+    let hmtx_data = provider.read_table_data(tag::HMTX)?;
+    let hmtx = ReadScope::new(&hmtx_data)
+        .read_dep::<HmtxTable<'_>>((num_glyphs, usize::from(hhea.num_h_metrics)))?;
+    let loca_data = provider.read_table_data(tag::LOCA)?;
+    let loca =
+        ReadScope::new(&loca_data).read_dep::<LocaTable>((num_glyphs, head.index_to_loc_format))?;
+    let glyf_data = provider.read_table_data(tag::GLYF)?;
+    let glyf = ReadScope::new(&glyf_data).read_dep::<GlyfTable>(&loca)?;
+
+    let mut num_composite = 0;
+    for (glyph_id, record) in glyf.records().iter().enumerate() {
+        // Glyph ids come from the glyf table which has at most u16::MAX records
+        let glyph_id = glyph_id as u16;
+        let mut record = record.clone();
+        record.parse()?;
+        let glyph = match &record {
+            GlyfRecord::Parsed(Glyph::Composite(glyph)) => glyph,
+            _ => continue,
+        };
+        let component =
+            glyph.glyphs.iter().enumerate().find(|(_, component)| {
+                component.flags.contains(CompositeGlyphFlag::USE_MY_METRICS)
+            });
+        if let Some((index, component)) = component {
+            num_composite += 1;
+            let (advance, _) =
+                h_metrics(&hmtx, num_glyphs, glyph_id).ok_or(ParseError::BadIndex)?;
+            let component_advance = match h_metrics(&hmtx, num_glyphs, component.glyph_index) {
+                Some((component_advance, _)) => component_advance,
+                None => {
+                    println!(
+                        "{}: component {}, component glyph {} out of range",
+                        glyph_id, index, component.glyph_index
+                    );
+                    continue;
+                }
+            };
+            let differs = if advance != component_advance {
+                " (differs)"
+            } else {
+                ""
+            };
+            println!(
+                "{}: component {} (glyph {}), component advance {}, own hmtx advance {}{}",
+                glyph_id, index, component.glyph_index, component_advance, advance, differs
+            );
+        }
+    }
+
+    if num_composite == 0 {
+        println!("no composite glyphs set USE_MY_METRICS");
+    } else {
+        println!("{} composite glyphs set USE_MY_METRICS", num_composite);
+    }
+
+    Ok(())
+}
+
+fn dump_kern_table(provider: &impl FontTableProvider) -> Result<(), ParseError> {
+    let Some(data) = provider.table_data(KERN)? else {
+        if provider.has_table(tag::GPOS) {
+            println!(
+                "no kern table, kerning may be in the GPOS table, \
+                 see `allsorts layout-features` for its kern feature"
+            );
+        } else {
+            println!("no kern table");
+        }
+        return Ok(());
+    };
+    let (pairs, skipped) = kerning::read_kern_table(&data)?;
+    let mut names = Vec::new();
+    for_each_glyph_name(provider, |_, name| names.push(name.to_string()))?;
+    let name = |glyph_id: u16| names.get(usize::from(glyph_id)).map_or("?", String::as_str);
+    // Pairs are sorted by left then right glyph index so that fonts can be diffed
+    for (&(left, right), value) in &pairs {
+        println!(
+            "{} ({}) {} ({}): {}",
+            left,
+            name(left),
+            right,
+            name(right),
+            value
+        );
+    }
+    println!("{} pairs", pairs.len());
+    if !skipped.is_empty() {
+        println!(
+            "skipped {} subtable(s) in unsupported formats: {:?}",
+            skipped.len(),
+            skipped
+        );
+    }
+    Ok(())
+}
+
+/// Compare the pairs of the kern table with those of the GPOS kern feature
+fn compare_kerning(provider: &impl FontTableProvider) -> Result<(), ParseError> {
+    let kern_data = provider.table_data(KERN)?;
+    let gpos_data = provider.table_data(tag::GPOS)?;
+    if kern_data.is_none() && gpos_data.is_none() {
+        println!("font has neither a kern table nor a GPOS table");
+        return Ok(());
+    }
+    let kern = match &kern_data {
+        Some(data) => kerning::read_kern_table(data)?.0,
+        None => KerningPairs::new(),
+    };
+    let gpos = match &gpos_data {
+        Some(data) => kerning::read_gpos_kerning(data)?,
+        None => KerningPairs::new(),
+    };
+    println!("kern table: {} pairs", kern.len());
+    println!("GPOS kern feature: {} pairs", gpos.len());
+
+    let only_kern = kern
+        .iter()
+        .filter(|(pair, _)| !gpos.contains_key(pair))
+        .collect::<Vec<_>>();
+    let only_gpos = gpos
+        .iter()
+        .filter(|(pair, _)| !kern.contains_key(pair))
+        .collect::<Vec<_>>();
+    let different = kern
+        .iter()
+        .filter_map(|(pair, &kern_value)| match gpos.get(pair) {
+            Some(&gpos_value) if gpos_value != kern_value => Some((pair, kern_value, gpos_value)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    println!("\nonly in kern: {}", only_kern.len());
+    for ((left, right), value) in &only_kern {
+        println!("  {} {}: {}", left, right, value);
+    }
+    println!("\nonly in GPOS: {}", only_gpos.len());
+    for ((left, right), value) in &only_gpos {
+        println!("  {} {}: {}", left, right, value);
+    }
+    println!("\ndifferent values: {}", different.len());
+    for ((left, right), kern_value, gpos_value) in &different {
+        println!(
+            "  {} {}: kern {}, GPOS {}",
+            left, right, kern_value, gpos_value
+        );
+    }
+    let agree = kern.len() - only_kern.len() - different.len();
+    println!("\n{} pairs agree", agree);
+
     Ok(())
 }
 
@@ -530,6 +1859,105 @@ fn dump_cff_dict<T: cff::DictDefault>(cff: &CFF, dict: &cff::Dict<T>, indent: us
     }
 }
 
+/// Print the runs of glyphs that use the same Font DICT
+fn dump_fd_select(fd_select: &cff::FDSelect<'_>, num_glyphs: usize) -> Result<(), ParseError> {
+    let num_glyphs = u16::try_from(num_glyphs)?;
+    let mut range: Option<(u16, u16, u8)> = None;
+    for glyph_id in 0..num_glyphs {
+        let font_dict_index = fd_select
+            .font_dict_index(glyph_id)
+            .ok_or(ParseError::BadIndex)?;
+        match range {
+            Some((_, ref mut last, index)) if index == font_dict_index => *last = glyph_id,
+            _ => {
+                if let Some((first, last, index)) = range {
+                    println!("  - glyphs {}..={}: Font DICT {}", first, last, index);
+                }
+                range = Some((glyph_id, glyph_id, font_dict_index));
+            }
+        }
+    }
+    if let Some((first, last, index)) = range {
+        println!("  - glyphs {}..={}: Font DICT {}", first, last, index);
+    }
+    Ok(())
+}
+
+/// Print the default value of each operator in `operators` that is absent from `dict`, or that
+/// it isn't set if the operator has no default
+fn dump_cff_dict_defaults<T: cff::DictDefault>(
+    dict: &cff::Dict<T>,
+    operators: &[Operator],
+    indent: usize,
+) {
+    for &op in operators {
+        if dict.iter().any(|(key, _)| *key == op) {
+            continue;
+        }
+        match T::default(op) {
+            Some(default) => println!("{:indent$}- {:?}: {:?} (default)", " ", op, default),
+            None => println!("{:indent$}- {:?}: not set", " ", op),
+        }
+    }
+}
+
+/// Print the values that determine how advance widths are encoded in charstrings.
+///
+/// A charstring that starts with a width encodes it relative to nominalWidthX, others use
+/// defaultWidthX.
+fn dump_cff_widths<T: cff::DictDefault, P: cff::DictDefault>(
+    top_dict: &cff::Dict<T>,
+    private_dict: &cff::Dict<P>,
+    indent: usize,
+) {
+    println!("{:indent$}- widths:", " ");
+    let indent = indent + 2;
+    print_cff_dict_value(top_dict, Operator::CharstringType, "CharstringType", indent);
+    print_cff_dict_value(
+        private_dict,
+        Operator::DefaultWidthX,
+        "defaultWidthX",
+        indent,
+    );
+    print_cff_dict_value(
+        private_dict,
+        Operator::NominalWidthX,
+        "nominalWidthX",
+        indent,
+    );
+}
+
+/// Print the value of `op` in `dict` labelled with `name`, falling back on its default
+fn print_cff_dict_value<T: cff::DictDefault>(
+    dict: &cff::Dict<T>,
+    op: Operator,
+    name: &str,
+    indent: usize,
+) {
+    let operands = dict
+        .iter()
+        .find_map(|(key, operands)| (*key == op).then(|| operands.as_slice()));
+    match operands {
+        Some(operands) => println!("{:indent$}- {}: {}", " ", name, format_operands(operands)),
+        None => match T::default(op) {
+            Some(default) => println!(
+                "{:indent$}- {}: {} (default)",
+                " ",
+                name,
+                format_operands(&default)
+            ),
+            None => println!("{:indent$}- {}: not set", " ", name),
+        },
+    }
+}
+
+fn format_operands(operands: &[Operand]) -> String {
+    match operands {
+        [Operand::Integer(value)] => value.to_string(),
+        operands => format!("{:?}", operands),
+    }
+}
+
 fn dump_raw_table(scope: Option<ReadScope>) -> Result<(), BoxError> {
     if let Some(scope) = scope {
         io::stdout()
@@ -572,29 +2000,23 @@ fn get_name_meaning(name_id: u16) -> Option<&'static str> {
 }
 
 fn print_glyph_names(provider: &impl FontTableProvider) -> Result<(), ParseError> {
+    for_each_glyph_name(provider, |glyph_id, name| {
+        println!("{}: {}", glyph_id, name)
+    })
+}
+
+/// Call `f` with the index and name of each glyph in the font
+fn for_each_glyph_name(
+    provider: &impl FontTableProvider,
+    mut f: impl FnMut(u16, &str),
+) -> Result<(), ParseError> {
     let table = provider.table_data(tag::MAXP)?.expect("no maxp table");
     let scope = ReadScope::new(table.borrow());
     let maxp = scope.read::<MaxpTable>()?;
 
-    let post_data = provider
-        .table_data(tag::POST)
-        .ok()
-        .and_then(convert::identity)
-        .map(|data| Box::from(&*data));
-
-    let table = provider.table_data(tag::CMAP)?;
-    let scope = table.as_ref().map(|data| ReadScope::new(data.borrow()));
-    let cmap = scope.map(|scope| scope.read::<Cmap<'_>>()).transpose()?;
-
-    let cmap_subtable = cmap
-        .as_ref()
-        .and_then(|cmap| read_cmap_subtable(cmap).ok())
-        .and_then(convert::identity);
-
-    let names = GlyphNames::new(&cmap_subtable, post_data);
-    for glyph_id in 0..maxp.num_glyphs {
-        let name = names.glyph_name(glyph_id);
-        println!("{}: {}", glyph_id, name);
+    let glyph_ids = (0..maxp.num_glyphs).collect::<Vec<_>>();
+    for (glyph_id, name) in glyph_ids.iter().zip(glyph_names(provider, &glyph_ids)?) {
+        f(*glyph_id, &name);
     }
 
     Ok(())
@@ -608,18 +2030,22 @@ fn print_cmap_encodings(provider: &impl FontTableProvider) -> Result<(), ParseEr
     println!("cmap encodings:");
     for record in cmap.encoding_records() {
         print!(" - {:?} {:?} ", record.platform_id, record.encoding_id);
-        if let Ok(subtable) = cmap
-            .scope
-            .offset(usize::try_from(record.offset)?)
-            .read::<CmapSubtable<'_>>()
-        {
-            match subtable {
-                CmapSubtable::Format0 { .. } => println!("Sub-table format 0"),
-                CmapSubtable::Format2 { .. } => println!("Sub-table format 2"),
-                CmapSubtable::Format4 { .. } => println!("Sub-table format 4"),
-                CmapSubtable::Format6 { .. } => println!("Sub-table format 6"),
-                CmapSubtable::Format10 { .. } => println!("Sub-table format 10"),
-                CmapSubtable::Format12 { .. } => println!("Sub-table format 12"),
+        let subtable_scope = cmap.scope.offset(usize::try_from(record.offset)?);
+        if let Ok(subtable) = subtable_scope.read::<CmapSubtable<'_>>() {
+            print!("Sub-table format {}", cmap_subtable_format(&subtable));
+            match cmap_subtable_language(&subtable_scope) {
+                Some(0) => println!(", language 0 (any)"),
+                // Macintosh subtables give the Macintosh language code plus one
+                Some(language) if record.platform_id == 1 => println!(
+                    ", language {} (only for Macintosh language code {})",
+                    language,
+                    language - 1
+                ),
+                Some(language) => println!(
+                    ", language {} (should be 0 for non-Macintosh platforms)",
+                    language
+                ),
+                None => println!(),
             }
         } else {
             println!("Unable to read sub-table.");
@@ -629,12 +2055,56 @@ fn print_cmap_encodings(provider: &impl FontTableProvider) -> Result<(), ParseEr
     Ok(())
 }
 
+/// The language field of the `cmap` subtable in `scope`, or `None` if its format doesn't have
+/// one.
+///
+/// It is only meaningful for Macintosh platform subtables, where it is the Macintosh language
+/// code plus one, or 0 for a subtable that isn't specific to a language.
+fn cmap_subtable_language(scope: &ReadScope<'_>) -> Option<u32> {
+    let mut ctxt = scope.ctxt();
+    match ctxt.read_u16be().ok()? {
+        0 | 2 | 4 | 6 => {
+            let _length = ctxt.read_u16be().ok()?;
+            ctxt.read_u16be().ok().map(u32::from)
+        }
+        8 | 10 | 12 | 13 => {
+            let _reserved = ctxt.read_u16be().ok()?;
+            let _length = ctxt.read_u32be().ok()?;
+            ctxt.read_u32be().ok()
+        }
+        _ => None,
+    }
+}
+
+fn cmap_subtable_format(subtable: &CmapSubtable<'_>) -> u16 {
+    match subtable {
+        CmapSubtable::Format0 { .. } => 0,
+        CmapSubtable::Format2 { .. } => 2,
+        CmapSubtable::Format4 { .. } => 4,
+        CmapSubtable::Format6 { .. } => 6,
+        CmapSubtable::Format10 { .. } => 10,
+        CmapSubtable::Format12 { .. } => 12,
+    }
+}
+
+impl NameFilter {
+    fn matches(&self, platform: u16, language: u16) -> bool {
+        self.platform.map_or(true, |filter| filter == platform)
+            && self.language.map_or(true, |filter| filter == language)
+    }
+}
+
 impl From<&DumpOpts> for Flags {
     fn from(opts: &DumpOpts) -> Self {
         Flags {
             encodings: opts.encodings,
             glyphs_names: opts.glyph_names,
-            name: opts.name,
+            name: opts.name || opts.name_platform.is_some() || opts.name_language.is_some(),
+            name_filter: NameFilter {
+                platform: opts.name_platform,
+                language: opts.name_language,
+            },
+            cff_verbose: opts.cff_verbose,
         }
     }
 }
@@ -0,0 +1,247 @@
+//! Outline hygiene problems in the glyphs of a `glyf` table, which cause artifacts in some
+//! rasterisers
+
+use std::collections::{BTreeMap, HashSet};
+
+use allsorts::binary::read::ReadScope;
+use allsorts::error::ParseError;
+use allsorts::tables::glyf::{GlyfRecord, GlyfTable, Glyph, SimpleGlyph};
+use allsorts::tables::loca::LocaTable;
+use allsorts::tables::{FontTableProvider, HeadTable, HheaTable, HmtxTable, MaxpTable};
+use allsorts::tag;
+
+use crate::{glyph_names, read_class_def, scope_at_offset, BoxError};
+
+/// The most glyphs listed for each kind of anomaly, the rest are only counted
+const MAX_LISTED: usize = 10;
+
+/// The GDEF glyph class of marks
+const MARK_CLASS: u16 = 3;
+
+/// Print the glyphs with each kind of anomaly, with a count and the first `MAX_LISTED` of them
+pub(super) fn dump_anomalies(provider: &impl FontTableProvider) -> Result<(), BoxError> {
+    let head = ReadScope::new(&provider.read_table_data(tag::HEAD)?).read::<HeadTable>()?;
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+    let hhea = ReadScope::new(&provider.read_table_data(tag::HHEA)?).read::<HheaTable>()?;
+    let num_glyphs = usize::from(maxp.num_glyphs);
+    let num_metrics = usize::from(hhea.num_h_metrics);
+    let hmtx_data = provider.read_table_data(tag::HMTX)?;
+    let hmtx = ReadScope::new(&hmtx_data).read_dep::<HmtxTable<'_>>((num_glyphs, num_metrics))?;
+    let loca_data = provider.read_table_data(tag::LOCA)?;
+    let loca =
+        ReadScope::new(&loca_data).read_dep::<LocaTable>((num_glyphs, head.index_to_loc_format))?;
+    let glyf_data = provider.read_table_data(tag::GLYF)?;
+    let glyf = ReadScope::new(&glyf_data).read_dep::<GlyfTable>(&loca)?;
+    let marks = read_marks(provider)?;
+
+    let mut glyphs = glyf.records().to_vec();
+    for record in &mut glyphs {
+        record.parse()?;
+    }
+
+    let mut anomalies = BTreeMap::<&str, Vec<u16>>::new();
+    for (glyph_id, glyph) in glyphs.iter().enumerate() {
+        // Glyph ids come from the glyf table which has at most u16::MAX records
+        let glyph_id = glyph_id as u16;
+        let mut add = |kind| anomalies.entry(kind).or_default().push(glyph_id);
+
+        let advance = if num_metrics == 0 {
+            0
+        } else {
+            let index = usize::from(glyph_id).min(num_metrics - 1);
+            hmtx.h_metrics.get_item(index).advance_width
+        };
+        if advance == 0
+            && !marks
+                .as_ref()
+                .map_or(false, |marks| marks.contains(&glyph_id))
+        {
+            add(ZERO_ADVANCE);
+        }
+
+        match glyph {
+            GlyfRecord::Parsed(Glyph::Simple(glyph)) => {
+                let contours = contours(glyph);
+                if contours.iter().any(|contour| contour.len() < 3) {
+                    add(FEW_POINTS);
+                }
+                if contours
+                    .iter()
+                    .any(|contour| contour.len() >= 3 && area(contour) == 0)
+                {
+                    add(ZERO_AREA);
+                }
+                if contours.iter().any(|contour| has_duplicate_points(contour)) {
+                    add(DUPLICATE_POINTS);
+                }
+                if !bbox_matches(glyph) {
+                    add(BBOX_MISMATCH);
+                }
+            }
+            GlyfRecord::Parsed(Glyph::Composite(composite)) => {
+                if references(&glyphs, glyph_id, glyph_id) {
+                    add(SELF_REFERENCE);
+                }
+                let empty_component = composite.glyphs.iter().any(|component| {
+                    !matches!(
+                        glyphs.get(usize::from(component.glyph_index)),
+                        Some(GlyfRecord::Parsed(Glyph::Simple(_)))
+                            | Some(GlyfRecord::Parsed(Glyph::Composite(_)))
+                    )
+                });
+                if empty_component {
+                    add(EMPTY_COMPONENT);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if anomalies.is_empty() {
+        println!("no glyph anomalies found");
+        return Ok(());
+    }
+    if marks.is_none() {
+        println!("note: no GDEF glyph classes, so marks with zero advance are included");
+    }
+    for kind in KINDS {
+        let glyph_ids = match anomalies.get(kind) {
+            Some(glyph_ids) => glyph_ids,
+            None => continue,
+        };
+        println!("{}: {} glyph(s)", kind, glyph_ids.len());
+        let listed = &glyph_ids[..glyph_ids.len().min(MAX_LISTED)];
+        for (glyph_id, name) in listed.iter().zip(glyph_names(provider, listed)?) {
+            println!(" - {} ({})", glyph_id, name);
+        }
+        if glyph_ids.len() > MAX_LISTED {
+            println!(" - and {} more", glyph_ids.len() - MAX_LISTED);
+        }
+    }
+
+    Ok(())
+}
+
+const ZERO_ADVANCE: &str = "zero advance and not a mark";
+const FEW_POINTS: &str = "contours with fewer than 3 points";
+const ZERO_AREA: &str = "contours with zero area";
+const DUPLICATE_POINTS: &str = "duplicate consecutive points";
+const BBOX_MISMATCH: &str = "bounding box differs from the points";
+const SELF_REFERENCE: &str = "composites that reference themselves";
+const EMPTY_COMPONENT: &str = "composites with empty or missing components";
+
+/// The kinds of anomaly in the order they're printed
+const KINDS: [&str; 7] = [
+    ZERO_ADVANCE,
+    FEW_POINTS,
+    ZERO_AREA,
+    DUPLICATE_POINTS,
+    BBOX_MISMATCH,
+    SELF_REFERENCE,
+    EMPTY_COMPONENT,
+];
+
+/// The glyphs that GDEF classes as marks, or `None` if there are no GDEF glyph classes
+fn read_marks(provider: &impl FontTableProvider) -> Result<Option<HashSet<u16>>, ParseError> {
+    let Some(gdef_data) = provider.table_data(tag::GDEF)? else {
+        return Ok(None);
+    };
+    let scope = ReadScope::new(&gdef_data);
+    let mut ctxt = scope.ctxt();
+    let _major_version = ctxt.read_u16be()?;
+    let _minor_version = ctxt.read_u16be()?;
+    let glyph_class_def_offset = ctxt.read_u16be()?;
+    if glyph_class_def_offset == 0 {
+        return Ok(None);
+    }
+    let classes = read_class_def(&scope_at_offset(
+        &scope,
+        usize::from(glyph_class_def_offset),
+    )?)?;
+    let marks = classes
+        .into_iter()
+        .filter(|&(_, class)| class == MARK_CLASS)
+        .map(|(glyph_id, _)| glyph_id)
+        .collect();
+    Ok(Some(marks))
+}
+
+/// The points of each contour of a simple glyph
+fn contours(glyph: &SimpleGlyph) -> Vec<Vec<(i16, i16)>> {
+    let mut contours = Vec::new();
+    let mut start = 0;
+    for &end in &glyph.end_pts_of_contours {
+        let end = usize::from(end) + 1;
+        let contour = glyph
+            .coordinates
+            .get(start..end)
+            .unwrap_or_default()
+            .iter()
+            .map(|(_flags, point)| (point.0, point.1))
+            .collect();
+        contours.push(contour);
+        start = end;
+    }
+    contours
+}
+
+/// Twice the signed area of the polygon through the points of a contour, including off-curve
+/// points, which is zero when they're all on one line
+fn area(contour: &[(i16, i16)]) -> i64 {
+    let next = contour.iter().cycle().skip(1);
+    contour
+        .iter()
+        .zip(next)
+        .map(|(&(x0, y0), &(x1, y1))| i64::from(x0) * i64::from(y1) - i64::from(x1) * i64::from(y0))
+        .sum()
+}
+
+/// Whether a point of a contour is the same as the point after it, including the last point and
+/// the first
+fn has_duplicate_points(contour: &[(i16, i16)]) -> bool {
+    contour.len() > 1
+        && contour
+            .iter()
+            .zip(contour.iter().cycle().skip(1))
+            .any(|(point, next)| point == next)
+}
+
+/// Whether the bounding box stored in the glyph matches the extent of its points
+fn bbox_matches(glyph: &SimpleGlyph) -> bool {
+    let mut points = glyph.coordinates.iter().map(|(_flags, point)| point);
+    let first = match points.next() {
+        Some(first) => first,
+        None => return true,
+    };
+    let (mut x_min, mut y_min, mut x_max, mut y_max) = (first.0, first.1, first.0, first.1);
+    for point in points {
+        x_min = x_min.min(point.0);
+        y_min = y_min.min(point.1);
+        x_max = x_max.max(point.0);
+        y_max = y_max.max(point.1);
+    }
+    let bbox = &glyph.bounding_box;
+    (bbox.x_min, bbox.y_min, bbox.x_max, bbox.y_max) == (x_min, y_min, x_max, y_max)
+}
+
+/// Whether the composite `glyph_id` uses `target` as a component, directly or through other
+/// composites
+fn references(glyphs: &[GlyfRecord], glyph_id: u16, target: u16) -> bool {
+    let mut visited = HashSet::new();
+    let mut pending = vec![glyph_id];
+    while let Some(glyph_id) = pending.pop() {
+        if let Some(GlyfRecord::Parsed(Glyph::Composite(composite))) =
+            glyphs.get(usize::from(glyph_id))
+        {
+            for component in &composite.glyphs {
+                if component.glyph_index == target {
+                    return true;
+                }
+                if visited.insert(component.glyph_index) {
+                    pending.push(component.glyph_index);
+                }
+            }
+        }
+    }
+    false
+}
@@ -0,0 +1,140 @@
+//! Recomputing the checksums of the tables of an OpenType font and its head checkSumAdjustment
+
+use std::convert::TryFrom;
+use std::num::Wrapping;
+
+use allsorts::error::ParseError;
+use allsorts::font_data::FontData;
+use allsorts::tables::{OffsetTable, OpenTypeData};
+use allsorts::tag::{self, DisplayTag};
+
+use crate::BoxError;
+
+/// The value the checksum of a whole font, including checkSumAdjustment, is meant to be
+const CHECKSUM_MAGIC: u32 = 0xb1b0afba;
+
+/// Offset of checkSumAdjustment in the head table, which is taken as zero for checksums
+const CHECKSUM_ADJUSTMENT_OFFSET: usize = 8;
+
+/// Print whether the checksum of each table in the directory matches its bytes in `data`, and
+/// whether checkSumAdjustment matches the whole file, returning whether they all did
+pub(super) fn verify_checksums(font_file: &FontData<'_>, data: &[u8]) -> Result<bool, BoxError> {
+    let otf = match font_file {
+        FontData::OpenType(otf) => otf,
+        FontData::Woff(_) | FontData::Woff2(_) => {
+            println!(
+                "WOFF and WOFF2 table data is compressed, checksums are only verified for OpenType"
+            );
+            return Ok(true);
+        }
+    };
+
+    let mut all_match = true;
+    match &otf.data {
+        OpenTypeData::Single(ttf) => {
+            all_match &= verify_tables(ttf, data)?;
+            all_match &= verify_adjustment(ttf, data)?;
+        }
+        OpenTypeData::Collection(ttc) => {
+            for (index, offset) in ttc.offset_tables.iter().enumerate() {
+                let ttf = otf
+                    .scope
+                    .offset(usize::try_from(offset)?)
+                    .read::<OffsetTable>()?;
+                println!("font {}:", index);
+                all_match &= verify_tables(&ttf, data)?;
+            }
+            println!("checkSumAdjustment: not checked for collections");
+        }
+    }
+    Ok(all_match)
+}
+
+fn verify_tables(ttf: &OffsetTable<'_>, data: &[u8]) -> Result<bool, BoxError> {
+    let mut all_match = true;
+    for table_record in ttf.table_records.iter() {
+        let start = usize::try_from(table_record.offset)?;
+        let end = start + usize::try_from(table_record.length)?;
+        let table_tag = DisplayTag(table_record.table_tag);
+        let table = match data.get(start..end) {
+            Some(table) => table,
+            None => {
+                println!(
+                    "{} (checksum: 0x{:08x}): MISMATCH, the table extends past the end of the file",
+                    table_tag, table_record.checksum
+                );
+                all_match = false;
+                continue;
+            }
+        };
+        let checksum = if table_record.table_tag == tag::HEAD {
+            checksum_without_adjustment(table, CHECKSUM_ADJUSTMENT_OFFSET)
+        } else {
+            checksum(table)
+        };
+        if checksum == table_record.checksum {
+            println!("{} (checksum: 0x{:08x}): OK", table_tag, checksum);
+        } else {
+            println!(
+                "{} (checksum: 0x{:08x}): MISMATCH, computed 0x{:08x}",
+                table_tag, table_record.checksum, checksum
+            );
+            all_match = false;
+        }
+    }
+    Ok(all_match)
+}
+
+fn verify_adjustment(ttf: &OffsetTable<'_>, data: &[u8]) -> Result<bool, BoxError> {
+    let head_record = match ttf
+        .table_records
+        .iter()
+        .find(|table_record| table_record.table_tag == tag::HEAD)
+    {
+        Some(head_record) => head_record,
+        None => {
+            println!("checkSumAdjustment: no head table");
+            return Ok(false);
+        }
+    };
+    let adjustment_offset = usize::try_from(head_record.offset)? + CHECKSUM_ADJUSTMENT_OFFSET;
+    let stored = data
+        .get(adjustment_offset..adjustment_offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or(ParseError::BadEof)?;
+    let computed =
+        CHECKSUM_MAGIC.wrapping_sub(checksum_without_adjustment(data, adjustment_offset));
+    if stored == computed {
+        println!("checkSumAdjustment (0x{:08x}): OK", stored);
+        Ok(true)
+    } else {
+        println!(
+            "checkSumAdjustment (0x{:08x}): MISMATCH, computed 0x{:08x}",
+            stored, computed
+        );
+        Ok(false)
+    }
+}
+
+/// The sum of `data` as big-endian u32 values, padded with zeros to a multiple of 4 bytes
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4)
+        .map(|chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            Wrapping(u32::from_be_bytes(word))
+        })
+        .sum::<Wrapping<u32>>()
+        .0
+}
+
+/// The checksum of `data` with the checkSumAdjustment at `adjustment_offset` taken as zero
+fn checksum_without_adjustment(data: &[u8], adjustment_offset: usize) -> u32 {
+    let adjustment = data
+        .get(adjustment_offset..adjustment_offset + 4)
+        .map_or(0, |bytes| {
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        });
+    // The adjustment is 4-byte aligned in the head table, which is itself aligned
+    checksum(data).wrapping_sub(adjustment)
+}
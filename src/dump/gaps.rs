@@ -0,0 +1,204 @@
+//! Bytes in an OpenType font that aren't part of any table or header
+
+use std::convert::TryFrom;
+
+use allsorts::binary::read::ReadScope;
+use allsorts::error::ParseError;
+use allsorts::font_data::FontData;
+use allsorts::tables::{OffsetTable, OpenTypeData};
+use allsorts::tag::DisplayTag;
+
+use crate::BoxError;
+
+/// A range of bytes in the file that belongs to a header or table
+struct Region {
+    start: usize,
+    end: usize,
+    name: String,
+    is_table: bool,
+}
+
+/// Print the bytes between and after the tables of `font_file`, read from `data`
+pub(super) fn dump_gaps(
+    font_file: &FontData<'_>,
+    data: &[u8],
+    show_data: bool,
+) -> Result<(), BoxError> {
+    let otf = match font_file {
+        FontData::OpenType(otf) => otf,
+        FontData::Woff(_) | FontData::Woff2(_) => {
+            println!(
+                "WOFF and WOFF2 table data is compressed, gaps are only reported for OpenType"
+            );
+            return Ok(());
+        }
+    };
+
+    let mut regions = Vec::new();
+    let offset_table_offsets = match &otf.data {
+        OpenTypeData::Single(_) => vec![0],
+        OpenTypeData::Collection(ttc) => {
+            let num_fonts = ttc.offset_tables.len();
+            let mut ctxt = ReadScope::new(data).ctxt();
+            let _ttc_tag = ctxt.read_u32be()?;
+            let major_version = ctxt.read_u16be()?;
+            let _minor_version = ctxt.read_u16be()?;
+            let _num_fonts = ctxt.read_u32be()?;
+            let _offset_tables = ctxt.read_slice(num_fonts * 4)?;
+            let mut header_length = 12 + num_fonts * 4;
+            // Version 2 headers add the location of the collection DSIG table
+            if major_version >= 2 {
+                header_length += 12;
+                let _dsig_tag = ctxt.read_u32be()?;
+                let dsig_length = usize::try_from(ctxt.read_u32be()?)?;
+                let dsig_offset = usize::try_from(ctxt.read_u32be()?)?;
+                if dsig_offset != 0 {
+                    regions.push(Region {
+                        start: dsig_offset,
+                        end: dsig_offset + dsig_length,
+                        name: String::from("DSIG"),
+                        is_table: true,
+                    });
+                }
+            }
+            regions.push(Region {
+                start: 0,
+                end: header_length,
+                name: String::from("collection header"),
+                is_table: false,
+            });
+            ttc.offset_tables
+                .iter()
+                .map(|offset| usize::try_from(offset).map_err(ParseError::from))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+    for offset_table_offset in offset_table_offsets {
+        let ttf = otf
+            .scope
+            .offset(offset_table_offset)
+            .read::<OffsetTable>()?;
+        regions.push(Region {
+            start: offset_table_offset,
+            end: offset_table_offset + 12 + ttf.table_records.len() * 16,
+            name: String::from("table directory"),
+            is_table: false,
+        });
+        for table_record in ttf.table_records.iter() {
+            let start = usize::try_from(table_record.offset)?;
+            // Tables shared by fonts in a collection only need to be visited once
+            if regions
+                .iter()
+                .any(|region| region.is_table && region.start == start)
+            {
+                continue;
+            }
+            regions.push(Region {
+                start,
+                end: start + usize::try_from(table_record.length)?,
+                name: DisplayTag(table_record.table_tag).to_string(),
+                is_table: true,
+            });
+        }
+    }
+    regions.sort_by_key(|region| (region.start, region.end));
+
+    let mut gap_bytes = 0;
+    let mut covered = 0;
+    let mut previous: Option<&Region> = None;
+    for region in regions.iter() {
+        if region.start > covered {
+            gap_bytes += report_gap(data, covered, region.start, previous, show_data);
+        }
+        if covered < region.end {
+            covered = region.end;
+            previous = Some(region);
+        }
+    }
+    if covered < data.len() {
+        gap_bytes += report_gap(data, covered, data.len(), previous, show_data);
+    }
+
+    for (i, region) in regions.iter().enumerate().filter(|(_, r)| r.is_table) {
+        if region.start % 4 != 0 {
+            println!(
+                "{} at offset {} (0x{:x}) is not 4-byte aligned",
+                region.name, region.start, region.start
+            );
+        }
+        let padded_end = (region.end + 3) & !3;
+        let next_start = regions.get(i + 1).map_or(data.len(), |next| next.start);
+        if padded_end > next_start.min(data.len()) && region.end <= next_start {
+            println!(
+                "{} (length {}) is not padded to a multiple of 4 bytes",
+                region.name,
+                region.end - region.start
+            );
+        }
+    }
+
+    if gap_bytes == 0 {
+        println!("no gaps between tables");
+    } else {
+        println!("{} byte(s) in gaps", gap_bytes);
+    }
+
+    Ok(())
+}
+
+/// Print the gap from `start` to `end` returning its length, or 0 if it is the padding that
+/// aligns the next table
+fn report_gap(
+    data: &[u8],
+    start: usize,
+    end: usize,
+    previous: Option<&Region>,
+    show_data: bool,
+) -> usize {
+    let end = end.min(data.len());
+    let bytes = data.get(start..end).unwrap_or(&[]);
+    let all_zero = bytes.iter().all(|&byte| byte == 0);
+    let after_table = matches!(previous, Some(region) if region.is_table);
+    if after_table && all_zero && bytes.len() < 4 && end % 4 == 0 {
+        return 0;
+    }
+
+    let location = match previous {
+        Some(region) => format!("after {}", region.name),
+        None => String::from("at start of file"),
+    };
+    println!(
+        "gap {} at offset {} (0x{:x}), {} byte(s), {}",
+        location,
+        start,
+        start,
+        bytes.len(),
+        if all_zero { "all zero" } else { "non-zero" }
+    );
+    if show_data && !all_zero {
+        hexdump(bytes, start);
+    }
+    bytes.len()
+}
+
+/// Print `bytes` as hex and ASCII, 16 bytes per line, labelled with offsets starting at `offset`
+fn hexdump(bytes: &[u8], offset: usize) {
+    for (i, line) in bytes.chunks(16).enumerate() {
+        let hex = line
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = line
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    char::from(byte)
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        println!("  {:08x}  {:<47}  |{}|", offset + i * 16, hex, ascii);
+    }
+}
@@ -0,0 +1,336 @@
+//! Labels for the digits of the PANOSE classification in the OS/2 table
+//!
+//! <https://monotype.github.io/panose/pan1.htm>
+
+/// The labels of digits whose other values aren't labelled
+const ANY_NO_FIT: [&str; 2] = ["Any", "No Fit"];
+
+const FAMILY_KIND: &[&str] = &[
+    "Any",
+    "No Fit",
+    "Latin Text",
+    "Latin Hand Written",
+    "Latin Decorative",
+    "Latin Symbol",
+];
+
+const WEIGHT: &[&str] = &[
+    "Any",
+    "No Fit",
+    "Very Light",
+    "Light",
+    "Thin",
+    "Book",
+    "Medium",
+    "Demi",
+    "Bold",
+    "Heavy",
+    "Black",
+    "Extra Black",
+];
+
+const CONTRAST: &[&str] = &[
+    "Any",
+    "No Fit",
+    "None",
+    "Very Low",
+    "Low",
+    "Medium Low",
+    "Medium",
+    "Medium High",
+    "High",
+    "Very High",
+];
+
+const LATIN_TEXT: [(&str, &[&str]); 9] = [
+    (
+        "serif style",
+        &[
+            "Any",
+            "No Fit",
+            "Cove",
+            "Obtuse Cove",
+            "Square Cove",
+            "Obtuse Square Cove",
+            "Square",
+            "Thin",
+            "Oval",
+            "Exaggerated",
+            "Triangle",
+            "Normal Sans",
+            "Obtuse Sans",
+            "Perpendicular Sans",
+            "Flared",
+            "Rounded",
+        ],
+    ),
+    ("weight", WEIGHT),
+    (
+        "proportion",
+        &[
+            "Any",
+            "No Fit",
+            "Old Style",
+            "Modern",
+            "Even Width",
+            "Extended",
+            "Condensed",
+            "Very Extended",
+            "Very Condensed",
+            "Monospaced",
+        ],
+    ),
+    ("contrast", CONTRAST),
+    (
+        "stroke variation",
+        &[
+            "Any",
+            "No Fit",
+            "No Variation",
+            "Gradual/Diagonal",
+            "Gradual/Transitional",
+            "Gradual/Vertical",
+            "Gradual/Horizontal",
+            "Rapid/Vertical",
+            "Rapid/Horizontal",
+            "Instant/Vertical",
+            "Instant/Horizontal",
+        ],
+    ),
+    (
+        "arm style",
+        &[
+            "Any",
+            "No Fit",
+            "Straight Arms/Horizontal",
+            "Straight Arms/Wedge",
+            "Straight Arms/Vertical",
+            "Straight Arms/Single Serif",
+            "Straight Arms/Double Serif",
+            "Non-Straight/Horizontal",
+            "Non-Straight/Wedge",
+            "Non-Straight/Vertical",
+            "Non-Straight/Single Serif",
+            "Non-Straight/Double Serif",
+        ],
+    ),
+    (
+        "letterform",
+        &[
+            "Any",
+            "No Fit",
+            "Normal/Contact",
+            "Normal/Weighted",
+            "Normal/Boxed",
+            "Normal/Flattened",
+            "Normal/Rounded",
+            "Normal/Off Center",
+            "Normal/Square",
+            "Oblique/Contact",
+            "Oblique/Weighted",
+            "Oblique/Boxed",
+            "Oblique/Flattened",
+            "Oblique/Rounded",
+            "Oblique/Off Center",
+            "Oblique/Square",
+        ],
+    ),
+    (
+        "midline",
+        &[
+            "Any",
+            "No Fit",
+            "Standard/Trimmed",
+            "Standard/Pointed",
+            "Standard/Serifed",
+            "High/Trimmed",
+            "High/Pointed",
+            "High/Serifed",
+            "Constant/Trimmed",
+            "Constant/Pointed",
+            "Constant/Serifed",
+            "Low/Trimmed",
+            "Low/Pointed",
+            "Low/Serifed",
+        ],
+    ),
+    (
+        "x-height",
+        &[
+            "Any",
+            "No Fit",
+            "Constant/Small",
+            "Constant/Standard",
+            "Constant/Large",
+            "Ducking/Small",
+            "Ducking/Standard",
+            "Ducking/Large",
+        ],
+    ),
+];
+
+const LATIN_HAND_WRITTEN: [(&str, &[&str]); 9] = [
+    (
+        "tool kind",
+        &[
+            "Any",
+            "No Fit",
+            "Flat Nib",
+            "Pressure Point",
+            "Engraved",
+            "Ball (Round Cap)",
+            "Brush",
+            "Rough",
+            "Felt Pen/Brush Tip",
+            "Wild Brush",
+        ],
+    ),
+    ("weight", WEIGHT),
+    (
+        "spacing",
+        &["Any", "No Fit", "Proportional Spaced", "Monospaced"],
+    ),
+    (
+        "aspect ratio",
+        &[
+            "Any",
+            "No Fit",
+            "Very Condensed",
+            "Condensed",
+            "Normal",
+            "Expanded",
+            "Very Expanded",
+        ],
+    ),
+    ("contrast", CONTRAST),
+    (
+        "topology",
+        &[
+            "Any",
+            "No Fit",
+            "Roman Disconnected",
+            "Roman Trailing",
+            "Roman Connected",
+            "Cursive Disconnected",
+            "Cursive Trailing",
+            "Cursive Connected",
+            "Blackletter Disconnected",
+            "Blackletter Trailing",
+            "Blackletter Connected",
+        ],
+    ),
+    (
+        "form",
+        &[
+            "Any",
+            "No Fit",
+            "Upright/No Wrapping",
+            "Upright/Some Wrapping",
+            "Upright/More Wrapping",
+            "Upright/Extreme Wrapping",
+            "Oblique/No Wrapping",
+            "Oblique/Some Wrapping",
+            "Oblique/More Wrapping",
+            "Oblique/Extreme Wrapping",
+            "Exaggerated/No Wrapping",
+            "Exaggerated/Some Wrapping",
+            "Exaggerated/More Wrapping",
+            "Exaggerated/Extreme Wrapping",
+        ],
+    ),
+    (
+        "finials",
+        &[
+            "Any",
+            "No Fit",
+            "None/No Loops",
+            "None/Closed Loops",
+            "None/Open Loops",
+            "Sharp/No Loops",
+            "Sharp/Closed Loops",
+            "Sharp/Open Loops",
+            "Tapered/No Loops",
+            "Tapered/Closed Loops",
+            "Tapered/Open Loops",
+            "Round/No Loops",
+            "Round/Closed Loops",
+            "Round/Open Loops",
+        ],
+    ),
+    (
+        "x-ascent",
+        &[
+            "Any",
+            "No Fit",
+            "Very Low",
+            "Low",
+            "Medium",
+            "High",
+            "Very High",
+        ],
+    ),
+];
+
+// Beyond Any and No Fit, only the digits these families share with the others are labelled
+const LATIN_DECORATIVE: [(&str, &[&str]); 9] = [
+    ("class", &ANY_NO_FIT),
+    ("weight", WEIGHT),
+    ("aspect", &ANY_NO_FIT),
+    ("contrast", &ANY_NO_FIT),
+    ("serif variant", &ANY_NO_FIT),
+    ("treatment", &ANY_NO_FIT),
+    ("lining", &ANY_NO_FIT),
+    ("topology", &ANY_NO_FIT),
+    ("range of characters", &ANY_NO_FIT),
+];
+
+const LATIN_SYMBOL: [(&str, &[&str]); 9] = [
+    ("kind", &ANY_NO_FIT),
+    ("weight", WEIGHT),
+    (
+        "spacing",
+        &["Any", "No Fit", "Proportional Spaced", "Monospaced"],
+    ),
+    ("aspect ratio and contrast", &ANY_NO_FIT),
+    ("aspect ratio of character 94", &ANY_NO_FIT),
+    ("aspect ratio of character 119", &ANY_NO_FIT),
+    ("aspect ratio of character 157", &ANY_NO_FIT),
+    ("aspect ratio of character 163", &ANY_NO_FIT),
+    ("aspect ratio of character 211", &ANY_NO_FIT),
+];
+
+/// The name and label of each of the digits of `panose`.
+///
+/// The meaning of the digits after the first depends on the family kind.
+pub(super) fn describe(panose: &[u8; 10]) -> Vec<(&'static str, String)> {
+    let mut digits = vec![("family kind", label(FAMILY_KIND, panose[0]))];
+    let family = match panose[0] {
+        2 => Some(&LATIN_TEXT),
+        3 => Some(&LATIN_HAND_WRITTEN),
+        4 => Some(&LATIN_DECORATIVE),
+        5 => Some(&LATIN_SYMBOL),
+        _ => None,
+    };
+    match family {
+        Some(family) => digits.extend(
+            family
+                .iter()
+                .zip(&panose[1..])
+                .map(|((name, labels), &value)| (*name, label(labels, value))),
+        ),
+        None => digits.extend(
+            panose[1..]
+                .iter()
+                .map(|&value| ("digit", label(&ANY_NO_FIT, value))),
+        ),
+    }
+    digits
+}
+
+fn label(labels: &[&str], value: u8) -> String {
+    match labels.get(usize::from(value)) {
+        Some(label) => format!("{} ({})", label, value),
+        None if labels.len() == ANY_NO_FIT.len() => value.to_string(),
+        None => format!("unknown ({})", value),
+    }
+}
@@ -0,0 +1,206 @@
+//! Clues to the tools and vendor that produced a font
+//!
+//! None of these are conclusive: tables and name strings survive being passed through other
+//! tools, so the guesses are listed with the evidence for each and the most supported first.
+
+use allsorts::binary::read::ReadScope;
+use allsorts::tables::os2::Os2;
+use allsorts::tables::{FontTableProvider, HeadTable, NameTable};
+use allsorts::tag::{self, DisplayTag};
+
+use super::{decode_name, Tag};
+use crate::BoxError;
+
+const DSIG: Tag = allsorts::tag!(b"DSIG");
+const GASP: Tag = allsorts::tag!(b"gasp");
+
+/// Tables only written by specific tools
+const TOOL_TABLES: [(Tag, &str); 13] = [
+    (allsorts::tag!(b"FFTM"), "FontForge"),
+    (allsorts::tag!(b"PfEd"), "FontForge"),
+    (allsorts::tag!(b"TTFA"), "ttfautohint"),
+    (allsorts::tag!(b"prop"), "Apple font tools"),
+    (allsorts::tag!(b"Zapf"), "Apple font tools"),
+    (allsorts::tag!(b"TSI0"), "Visual TrueType (VTT)"),
+    (allsorts::tag!(b"TSI1"), "Visual TrueType (VTT)"),
+    (allsorts::tag!(b"TSI2"), "Visual TrueType (VTT)"),
+    (allsorts::tag!(b"TSI3"), "Visual TrueType (VTT)"),
+    (allsorts::tag!(b"TSIV"), "Microsoft VOLT"),
+    (allsorts::tag!(b"MERG"), "Microsoft font tools"),
+    (allsorts::tag!(b"Debg"), "fontmake (ufo2ft)"),
+    (allsorts::tag!(b"webf"), "web font converter"),
+];
+
+/// Substrings of the name ID 5 version string added by specific tools
+const VERSION_PATTERNS: [(&str, &str); 6] = [
+    ("ttfautohint", "ttfautohint"),
+    ("hotconv", "Adobe FDK (makeotf)"),
+    ("makeotf", "Adobe FDK (makeotf)"),
+    ("FontForge", "FontForge"),
+    ("FontLab", "FontLab"),
+    ("Fontographer", "Fontographer"),
+];
+
+/// OS/2 achVendID values that are the defaults of specific tools rather than a vendor
+const TOOL_VENDOR_IDS: [(&[u8; 4], &str); 3] = [
+    (b"PfEd", "FontForge"),
+    (b"UKWN", "fontmake (ufo2ft) or Glyphs"),
+    (b"NONE", "fontmake (ufo2ft)"),
+];
+
+/// The head flags fontmake writes when the UFO doesn't set them: baseline at y=0 and left
+/// sidebearing at x=0
+const UFO2FT_HEAD_FLAGS: u16 = 0x0003;
+
+/// The gasp behaviour flags that ask for gridfitting and smoothing at every size
+const GASP_SMOOTH_EVERYWHERE: u16 = 0x000f;
+
+/// The guesses about what produced a font, each with the evidence for it
+#[derive(Default)]
+struct Provenance {
+    guesses: Vec<(String, Vec<String>)>,
+}
+
+impl Provenance {
+    fn add(&mut self, guess: &str, evidence: String) {
+        match self.guesses.iter_mut().find(|(name, _)| name == guess) {
+            Some((_, found)) => found.push(evidence),
+            None => self.guesses.push((guess.to_string(), vec![evidence])),
+        }
+    }
+}
+
+/// Print a best-guess list of the tools and vendor that produced the font, with the evidence for
+/// each
+pub(super) fn dump_provenance(provider: &impl FontTableProvider) -> Result<(), BoxError> {
+    let mut provenance = Provenance::default();
+
+    for &(table, tool) in TOOL_TABLES.iter() {
+        if provider.has_table(table) {
+            provenance.add(tool, format!("{} table", DisplayTag(table)));
+        }
+    }
+    if let Some(data) = provider.table_data(DSIG)? {
+        let mut ctxt = ReadScope::new(&data).ctxt();
+        let _version = ctxt.read_u32be()?;
+        let num_signatures = ctxt.read_u16be()?;
+        if num_signatures == 0 {
+            provenance.add(
+                "font converter or build tool",
+                String::from("placeholder DSIG table without signatures"),
+            );
+        }
+    }
+
+    let name_data = provider.table_data(tag::NAME)?;
+    let name = name_data
+        .as_ref()
+        .map(|data| ReadScope::new(data).read::<NameTable<'_>>())
+        .transpose()?;
+    if let Some(version) = name.as_ref().and_then(|name| find_name(name, 5)) {
+        for &(pattern, tool) in VERSION_PATTERNS.iter() {
+            if version.contains(pattern) {
+                provenance.add(tool, format!("name 5 version {:?}", version));
+            }
+        }
+    }
+
+    let os2_data = provider.table_data(tag::OS_2)?;
+    let os2 = os2_data
+        .as_ref()
+        .map(|data| ReadScope::new(data).read_dep::<Os2>(data.len()))
+        .transpose()?;
+    let vendor_id = os2.map(|os2| os2.ach_vend_id.to_be_bytes());
+    let tool_vendor = vendor_id.and_then(|vendor_id| {
+        TOOL_VENDOR_IDS
+            .iter()
+            .find(|(id, _)| **id == vendor_id)
+            .map(|&(_, tool)| tool)
+    });
+    if let (Some(vendor_id), Some(tool)) = (vendor_id, tool_vendor) {
+        provenance.add(
+            tool,
+            format!("OS/2 achVendID '{}'", String::from_utf8_lossy(&vendor_id)),
+        );
+    }
+
+    if let Some(data) = provider.table_data(GASP)? {
+        let mut ctxt = ReadScope::new(&data).ctxt();
+        let version = ctxt.read_u16be()?;
+        let num_ranges = ctxt.read_u16be()?;
+        let ranges = (0..num_ranges)
+            .map(|_| Ok((ctxt.read_u16be()?, ctxt.read_u16be()?)))
+            .collect::<Result<Vec<_>, BoxError>>()?;
+        if version == 1 && ranges == [(0xffff, GASP_SMOOTH_EVERYWHERE)] {
+            provenance.add(
+                "ttfautohint",
+                String::from("gasp smoothing at all sizes with a single range"),
+            );
+        }
+    }
+    if let Some(data) = provider.table_data(tag::HEAD)? {
+        let head = ReadScope::new(&data).read::<HeadTable>()?;
+        if head.flags == UFO2FT_HEAD_FLAGS {
+            provenance.add(
+                "fontmake (ufo2ft)",
+                format!("head flags 0x{:04x}, the ufo2ft default", head.flags),
+            );
+        }
+    }
+
+    // The vendor comes last as it says who made the font rather than how
+    let mut vendor = Vec::new();
+    if let Some(name) = &name {
+        for &(name_id, meaning) in [(8, "manufacturer"), (11, "vendor URL")].iter() {
+            if let Some(value) = find_name(name, name_id) {
+                vendor.push((meaning, value));
+            }
+        }
+    }
+    if let (Some(vendor_id), None) = (vendor_id, tool_vendor) {
+        if vendor_id.iter().any(|&byte| byte != b' ' && byte != 0) {
+            vendor.push(("OS/2 achVendID", String::from_utf8_lossy(&vendor_id).into()));
+        }
+    }
+
+    provenance
+        .guesses
+        .sort_by_key(|(_, evidence)| std::cmp::Reverse(evidence.len()));
+    if provenance.guesses.is_empty() {
+        println!("no tool fingerprints found");
+    }
+    for (guess, evidence) in &provenance.guesses {
+        println!("{}", guess);
+        for evidence in evidence {
+            println!(" - {}", evidence);
+        }
+    }
+    if let Some((_, name)) = vendor.first() {
+        println!("vendor: {}", name.trim());
+        for (meaning, value) in &vendor {
+            println!(" - {} {:?}", meaning, value);
+        }
+    }
+
+    Ok(())
+}
+
+/// The string for `name_id`, preferring the Windows platform
+fn find_name(name: &NameTable<'_>, name_id: u16) -> Option<String> {
+    let record = name
+        .name_records
+        .iter()
+        .filter(|record| record.name_id == name_id)
+        .max_by_key(|record| record.platform_id == 3)?;
+    let data = name
+        .string_storage
+        .offset_length(usize::from(record.offset), usize::from(record.length))
+        .ok()?
+        .data();
+    Some(decode_name(
+        record.platform_id,
+        record.encoding_id,
+        record.language_id,
+        data,
+    ))
+}
@@ -0,0 +1,329 @@
+//! The summary printed by `dump`, collected into a structure so that it can be output as JSON
+
+use std::convert::TryFrom;
+
+use allsorts::binary::read::ReadScope;
+use allsorts::cff::{CFFVariant, CFF};
+use allsorts::error::ParseError;
+use allsorts::font_data::FontData;
+use allsorts::tables::cmap::CmapSubtable;
+use allsorts::tables::{FontTableProvider, MaxpTable, NameTable, OffsetTable, OpenTypeData};
+use allsorts::tag::{self, DisplayTag};
+use allsorts::woff::WoffFont;
+use allsorts::woff2::Woff2Font;
+use serde::Serialize;
+
+use super::{
+    cff_variant_name, charset_name, cmap_subtable_format, cmap_subtable_language, decode_name,
+    get_name_meaning, Flags, NameFilter,
+};
+use crate::{scope_at_offset, BoxError};
+
+#[derive(Serialize)]
+pub(super) struct DumpReport {
+    /// TTF, TTC, WOFF, or WOFF2
+    format: &'static str,
+    /// The collection header version, for TTC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    fonts: Vec<FontReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extended_metadata: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cmap_encodings: Option<Vec<CmapEncodingReport>>,
+}
+
+#[derive(Serialize)]
+struct FontReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sfnt_version: Option<String>,
+    tables: Vec<TableReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_glyphs: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cff: Option<CffReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    names: Option<Vec<NameReport>>,
+}
+
+#[derive(Serialize)]
+struct TableReport {
+    tag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checksum: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<usize>,
+    length: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compressed_length: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct CffReport {
+    version: String,
+    names: Vec<String>,
+    num_glyphs: usize,
+    charset: &'static str,
+    variant: &'static str,
+    global_subrs: usize,
+}
+
+#[derive(Serialize)]
+struct NameReport {
+    name_id: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meaning: Option<&'static str>,
+    platform_id: u16,
+    encoding_id: u16,
+    language_id: u16,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct CmapEncodingReport {
+    platform_id: u16,
+    encoding_id: u16,
+    /// `None` if the sub-table could not be read
+    format: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<u32>,
+}
+
+impl DumpReport {
+    pub fn new(
+        font_file: &FontData<'_>,
+        provider: &impl FontTableProvider,
+        index: usize,
+        flags: Flags,
+    ) -> Result<DumpReport, BoxError> {
+        let mut report = match font_file {
+            FontData::OpenType(font_file) => match &font_file.data {
+                OpenTypeData::Single(ttf) => DumpReport {
+                    format: "TTF",
+                    version: None,
+                    fonts: vec![ttf_report(&font_file.scope, ttf, flags)?],
+                    extended_metadata: None,
+                    cmap_encodings: None,
+                },
+                OpenTypeData::Collection(ttc) => {
+                    let mut fonts = Vec::with_capacity(ttc.offset_tables.len());
+                    for offset_table_offset in &ttc.offset_tables {
+                        let offset_table_offset =
+                            usize::try_from(offset_table_offset).map_err(ParseError::from)?;
+                        let offset_table = font_file
+                            .scope
+                            .offset(offset_table_offset)
+                            .read::<OffsetTable>()?;
+                        fonts.push(ttf_report(&font_file.scope, &offset_table, flags)?);
+                    }
+                    DumpReport {
+                        format: "TTC",
+                        version: Some(format!("{}.{}", ttc.major_version, ttc.minor_version)),
+                        fonts,
+                        extended_metadata: None,
+                        cmap_encodings: None,
+                    }
+                }
+            },
+            FontData::Woff(woff) => woff_report(woff, flags)?,
+            FontData::Woff2(woff) => woff2_report(woff, index, flags)?,
+        };
+        if flags.encodings {
+            report.cmap_encodings = Some(cmap_encodings(provider)?);
+        }
+        Ok(report)
+    }
+}
+
+fn ttf_report<'a>(
+    scope: &ReadScope<'a>,
+    ttf: &OffsetTable<'a>,
+    flags: Flags,
+) -> Result<FontReport, BoxError> {
+    let tables = ttf
+        .table_records
+        .iter()
+        .map(|table_record| TableReport {
+            tag: DisplayTag(table_record.table_tag).to_string(),
+            checksum: Some(table_record.checksum),
+            offset: Some(table_record.offset as usize),
+            length: table_record.length,
+            compressed_length: None,
+        })
+        .collect();
+    let num_glyphs = ttf
+        .read_table(scope, tag::MAXP)?
+        .map(|table| table.read::<MaxpTable>())
+        .transpose()?
+        .map(|maxp| maxp.num_glyphs);
+    let cff = ttf
+        .read_table(scope, tag::CFF)?
+        .map(|table| cff_report(&table))
+        .transpose()?;
+    let names = match ttf.read_table(scope, tag::NAME)? {
+        Some(table) if flags.name => Some(name_reports(
+            &table.read::<NameTable>()?,
+            flags.name_filter,
+        )?),
+        _ => None,
+    };
+    Ok(FontReport {
+        sfnt_version: Some(format!("0x{:08x}", ttf.sfnt_version)),
+        tables,
+        num_glyphs,
+        cff,
+        names,
+    })
+}
+
+fn woff_report(woff: &WoffFont<'_>, flags: Flags) -> Result<DumpReport, BoxError> {
+    let tables = woff
+        .table_directory
+        .iter()
+        .map(|entry| TableReport {
+            tag: DisplayTag(entry.tag).to_string(),
+            checksum: Some(entry.orig_checksum),
+            offset: None,
+            length: entry.orig_length,
+            compressed_length: Some(entry.comp_length),
+        })
+        .collect();
+    let names = match woff
+        .table_directory
+        .iter()
+        .find(|entry| entry.tag == tag::NAME)
+    {
+        Some(entry) if flags.name => {
+            let table = entry.read_table(&woff.scope)?;
+            Some(name_reports(
+                &table.scope().read::<NameTable>()?,
+                flags.name_filter,
+            )?)
+        }
+        _ => None,
+    };
+    Ok(DumpReport {
+        format: "WOFF",
+        version: None,
+        fonts: vec![FontReport {
+            sfnt_version: None,
+            tables,
+            num_glyphs: None,
+            cff: None,
+            names,
+        }],
+        extended_metadata: woff.extended_metadata()?,
+        cmap_encodings: None,
+    })
+}
+
+fn woff2_report(woff: &Woff2Font<'_>, index: usize, flags: Flags) -> Result<DumpReport, BoxError> {
+    let tables = woff
+        .table_directory
+        .iter()
+        .map(|entry| TableReport {
+            tag: DisplayTag(entry.tag).to_string(),
+            checksum: None,
+            offset: Some(entry.offset),
+            length: entry.length(),
+            compressed_length: None,
+        })
+        .collect();
+    let names = match woff.read_table(tag::NAME, index)? {
+        Some(table) if flags.name => Some(name_reports(
+            &table.scope().read::<NameTable>()?,
+            flags.name_filter,
+        )?),
+        _ => None,
+    };
+    Ok(DumpReport {
+        format: "WOFF2",
+        version: None,
+        fonts: vec![FontReport {
+            sfnt_version: None,
+            tables,
+            num_glyphs: None,
+            cff: None,
+            names,
+        }],
+        extended_metadata: woff.extended_metadata()?,
+        cmap_encodings: None,
+    })
+}
+
+fn cff_report(scope: &ReadScope<'_>) -> Result<CffReport, ParseError> {
+    let cff = scope.read::<CFF>()?;
+    let font = cff.fonts.get(0).ok_or(ParseError::MissingValue)?;
+    Ok(CffReport {
+        version: format!("{}.{}", cff.header.major, cff.header.minor),
+        names: cff
+            .name_index
+            .iter()
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect(),
+        num_glyphs: font.char_strings_index.len(),
+        charset: charset_name(&font.charset),
+        variant: cff_variant_name(&font.data),
+        global_subrs: cff.global_subr_index.len(),
+    })
+}
+
+fn name_reports(name_table: &NameTable, filter: NameFilter) -> Result<Vec<NameReport>, ParseError> {
+    let mut names = Vec::new();
+    for name_record in &name_table.name_records {
+        if !filter.matches(name_record.platform_id, name_record.language_id) {
+            continue;
+        }
+        let name_data = name_table
+            .string_storage
+            .offset_length(
+                usize::from(name_record.offset),
+                usize::from(name_record.length),
+            )?
+            .data();
+        names.push(NameReport {
+            name_id: name_record.name_id,
+            meaning: get_name_meaning(name_record.name_id),
+            platform_id: name_record.platform_id,
+            encoding_id: name_record.encoding_id,
+            language_id: name_record.language_id,
+            value: decode_name(
+                name_record.platform_id,
+                name_record.encoding_id,
+                name_record.language_id,
+                name_data,
+            ),
+        });
+    }
+    Ok(names)
+}
+
+fn cmap_encodings(
+    provider: &impl FontTableProvider,
+) -> Result<Vec<CmapEncodingReport>, ParseError> {
+    let cmap_data = provider.read_table_data(tag::CMAP)?;
+    let scope = ReadScope::new(&cmap_data);
+    let mut ctxt = scope.ctxt();
+    let _version = ctxt.read_u16be()?;
+    let num_tables = ctxt.read_u16be()?;
+    let mut encodings = Vec::with_capacity(usize::from(num_tables));
+    for _ in 0..num_tables {
+        let platform_id = ctxt.read_u16be()?;
+        let encoding_id = ctxt.read_u16be()?;
+        let offset = usize::try_from(ctxt.read_u32be()?)?;
+        let subtable_scope = scope_at_offset(&scope, offset).ok();
+        let format = subtable_scope
+            .and_then(|subtable| subtable.read::<CmapSubtable<'_>>().ok())
+            .map(|subtable| cmap_subtable_format(&subtable));
+        let language = subtable_scope
+            .filter(|_| format.is_some())
+            .and_then(|subtable| cmap_subtable_language(&subtable));
+        encodings.push(CmapEncodingReport {
+            platform_id,
+            encoding_id,
+            format,
+            language,
+        });
+    }
+    Ok(encodings)
+}
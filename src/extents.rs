@@ -0,0 +1,228 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+use allsorts::binary::read::ReadScope;
+use allsorts::cff::cff2::CFF2;
+use allsorts::cff::outline::CFF2Outlines;
+use allsorts::cff::CFF;
+use allsorts::outline::{OutlineBuilder, OutlineSink};
+use allsorts::pathfinder_geometry::line_segment::LineSegment2F;
+use allsorts::pathfinder_geometry::vector::Vector2F;
+use allsorts::tables::glyf::GlyfTable;
+use allsorts::tables::loca::LocaTable;
+use allsorts::tables::{FontTableProvider, HeadTable, MaxpTable};
+use allsorts::tag;
+
+use crate::{BoxError, ErrorMessage};
+
+/// The bounding box of a glyph's outline, in font units
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Extents {
+    pub x_min: f32,
+    pub y_min: f32,
+    pub x_max: f32,
+    pub y_max: f32,
+}
+
+/// An `OutlineSink` that computes the bounding box of the outline it is fed
+struct ExtentsSink {
+    extents: Option<Extents>,
+    current: Vector2F,
+}
+
+/// Calculate the extents of each of the glyphs in `glyph_ids`.
+///
+/// Glyphs without an outline have no extents.
+pub(crate) fn glyph_extents(
+    provider: &impl FontTableProvider,
+    glyph_ids: &[u16],
+) -> Result<Vec<Option<Extents>>, BoxError> {
+    if provider.has_table(tag::GLYF) {
+        let table = provider.read_table_data(tag::HEAD)?;
+        let head = ReadScope::new(table.borrow()).read::<HeadTable>()?;
+        let table = provider.read_table_data(tag::MAXP)?;
+        let maxp = ReadScope::new(table.borrow()).read::<MaxpTable>()?;
+        let loca_data = provider.read_table_data(tag::LOCA)?;
+        let loca = ReadScope::new(&loca_data)
+            .read_dep::<LocaTable>((usize::from(maxp.num_glyphs), head.index_to_loc_format))?;
+        let glyf_data = provider.read_table_data(tag::GLYF)?;
+        let mut glyf = ReadScope::new(&glyf_data).read_dep::<GlyfTable>(&loca)?;
+        outline_extents(&mut glyf, glyph_ids)
+    } else if provider.has_table(tag::CFF) {
+        let cff_data = provider.read_table_data(tag::CFF)?;
+        let mut cff = ReadScope::new(&cff_data).read::<CFF>()?;
+        outline_extents(&mut cff, glyph_ids)
+    } else if provider.has_table(tag::CFF2) {
+        let cff_data = provider.read_table_data(tag::CFF2)?;
+        let cff = ReadScope::new(&cff_data).read::<CFF2>()?;
+        let mut cff2_outlines = CFF2Outlines {
+            table: &cff,
+            tuple: None,
+        };
+        outline_extents(&mut cff2_outlines, glyph_ids)
+    } else {
+        Err(ErrorMessage("no glyf or CFF table").into())
+    }
+}
+
+fn outline_extents<T>(builder: &mut T, glyph_ids: &[u16]) -> Result<Vec<Option<Extents>>, BoxError>
+where
+    T: OutlineBuilder,
+    T::Error: std::error::Error + 'static,
+{
+    let mut all_extents = Vec::with_capacity(glyph_ids.len());
+    for &glyph_id in glyph_ids {
+        let mut sink = ExtentsSink::new();
+        builder.visit(glyph_id, &mut sink)?;
+        all_extents.push(sink.extents);
+    }
+    Ok(all_extents)
+}
+
+impl Extents {
+    pub fn translate(self, x: f32, y: f32) -> Extents {
+        Extents {
+            x_min: self.x_min + x,
+            y_min: self.y_min + y,
+            x_max: self.x_max + x,
+            y_max: self.y_max + y,
+        }
+    }
+
+    pub fn union(self, other: Extents) -> Extents {
+        Extents {
+            x_min: self.x_min.min(other.x_min),
+            y_min: self.y_min.min(other.y_min),
+            x_max: self.x_max.max(other.x_max),
+            y_max: self.y_max.max(other.y_max),
+        }
+    }
+
+    fn include(self, x: f32, y: f32) -> Extents {
+        self.union(Extents {
+            x_min: x,
+            y_min: y,
+            x_max: x,
+            y_max: y,
+        })
+    }
+}
+
+impl fmt::Display for Extents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}, {}, {})",
+            self.x_min, self.y_min, self.x_max, self.y_max
+        )
+    }
+}
+
+impl ExtentsSink {
+    fn new() -> Self {
+        ExtentsSink {
+            extents: None,
+            current: Vector2F::zero(),
+        }
+    }
+
+    fn include(&mut self, point: Vector2F) {
+        let (x, y) = (point.x(), point.y());
+        self.extents = Some(match self.extents {
+            Some(extents) => extents.include(x, y),
+            None => Extents {
+                x_min: x,
+                y_min: y,
+                x_max: x,
+                y_max: y,
+            },
+        });
+    }
+
+    /// Include the points on the curve where it changes direction on either axis.
+    ///
+    /// `roots` are the values of `t` where the derivative is zero on either axis and `eval`
+    /// returns the point on the curve at `t`.
+    fn include_extrema(&mut self, roots: [Option<f32>; 4], eval: impl Fn(f32) -> Vector2F) {
+        for t in roots.iter().flatten() {
+            if *t > 0. && *t < 1. {
+                self.include(eval(*t));
+            }
+        }
+    }
+}
+
+/// Solve a t² + b t + c = 0
+fn quadratic_roots(a: f32, b: f32, c: f32) -> [Option<f32>; 2] {
+    if a.abs() < f32::EPSILON {
+        if b.abs() < f32::EPSILON {
+            [None, None]
+        } else {
+            [Some(-c / b), None]
+        }
+    } else {
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. {
+            [None, None]
+        } else {
+            let sqrt = discriminant.sqrt();
+            [Some((-b + sqrt) / (2. * a)), Some((-b - sqrt) / (2. * a))]
+        }
+    }
+}
+
+impl OutlineSink for ExtentsSink {
+    fn move_to(&mut self, point: Vector2F) {
+        self.include(point);
+        self.current = point;
+    }
+
+    fn line_to(&mut self, point: Vector2F) {
+        self.include(point);
+        self.current = point;
+    }
+
+    fn quadratic_curve_to(&mut self, control: Vector2F, point: Vector2F) {
+        let from = self.current;
+        // The derivative is zero where t = (p0 - c) / (p0 - 2c + p1)
+        let root = |p0: f32, c: f32, p1: f32| {
+            let denominator = p0 - 2. * c + p1;
+            (denominator.abs() >= f32::EPSILON).then(|| (p0 - c) / denominator)
+        };
+        let roots = [
+            root(from.x(), control.x(), point.x()),
+            root(from.y(), control.y(), point.y()),
+            None,
+            None,
+        ];
+        self.include_extrema(roots, |t| {
+            let mt = 1. - t;
+            from * (mt * mt) + control * (2. * mt * t) + point * (t * t)
+        });
+        self.include(point);
+        self.current = point;
+    }
+
+    fn cubic_curve_to(&mut self, ctrl: LineSegment2F, to: Vector2F) {
+        let from = self.current;
+        let (c1, c2) = (ctrl.from(), ctrl.to());
+        // The derivative is a quadratic in t with these coefficients
+        let roots = |p0: f32, c1: f32, c2: f32, p1: f32| {
+            let (a, b, c) = (c1 - p0, c2 - c1, p1 - c2);
+            quadratic_roots(a - 2. * b + c, 2. * (b - a), a)
+        };
+        let [x0, x1] = roots(from.x(), c1.x(), c2.x(), to.x());
+        let [y0, y1] = roots(from.y(), c1.y(), c2.y(), to.y());
+        self.include_extrema([x0, x1, y0, y1], |t| {
+            let mt = 1. - t;
+            from * (mt * mt * mt)
+                + c1 * (3. * mt * mt * t)
+                + c2 * (3. * mt * t * t)
+                + to * (t * t * t)
+        });
+        self.include(to);
+        self.current = to;
+    }
+
+    fn close(&mut self) {}
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+
+use allsorts::binary::read::{ReadCtxt, ReadScope};
+use allsorts::error::ParseError;
+use allsorts::tag::DisplayTag;
+
+use crate::scope_at_offset;
+
+/// The FeatureVariations of a GSUB or GPOS table
+pub(crate) struct FeatureVariations {
+    pub records: Vec<FeatureVariationRecord>,
+}
+
+/// A condition set and the features that are substituted when it is active
+pub(crate) struct FeatureVariationRecord {
+    /// Conditions that must all be met for the record to be active, `None` for condition
+    /// formats other than 1, which never match
+    pub conditions: Vec<Option<Condition>>,
+    /// The tags of the features whose lookups are substituted
+    pub feature_tags: Vec<u32>,
+}
+
+/// A range of normalised values on a variation axis
+#[derive(Copy, Clone)]
+pub(crate) struct Condition {
+    pub axis_index: u16,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl FeatureVariations {
+    /// Read the FeatureVariations from the data of a GSUB or GPOS table.
+    ///
+    /// Returns `None` if the table has no FeatureVariations.
+    pub fn read_layout_table(data: &[u8]) -> Result<Option<FeatureVariations>, ParseError> {
+        let scope = ReadScope::new(data);
+        let mut ctxt = scope.ctxt();
+        let _major_version = ctxt.read_u16be()?;
+        let minor_version = ctxt.read_u16be()?;
+        let _script_list_offset = ctxt.read_u16be()?;
+        let feature_list_offset = ctxt.read_u16be()?;
+        let _lookup_list_offset = ctxt.read_u16be()?;
+        if minor_version < 1 {
+            return Ok(None);
+        }
+        let feature_variations_offset = ctxt.read_u32be()? as usize;
+        if feature_variations_offset == 0 {
+            return Ok(None);
+        }
+
+        let mut ctxt = scope_at_offset(&scope, usize::from(feature_list_offset))?.ctxt();
+        let feature_count = ctxt.read_u16be()?;
+        let mut feature_list_tags = Vec::with_capacity(usize::from(feature_count));
+        for _ in 0..feature_count {
+            feature_list_tags.push(ctxt.read_u32be()?);
+            let _feature_offset = ctxt.read_u16be()?;
+        }
+
+        let variations_scope = scope_at_offset(&scope, feature_variations_offset)?;
+        let mut ctxt = variations_scope.ctxt();
+        let _major_version = ctxt.read_u16be()?;
+        let _minor_version = ctxt.read_u16be()?;
+        let record_count = ctxt.read_u32be()?;
+        let mut records = Vec::new();
+        for _ in 0..record_count {
+            let condition_set_offset = ctxt.read_u32be()? as usize;
+            let substitution_offset = ctxt.read_u32be()? as usize;
+            let conditions = match condition_set_offset {
+                // A missing condition set matches all locations
+                0 => Vec::new(),
+                _ => {
+                    read_condition_set(&scope_at_offset(&variations_scope, condition_set_offset)?)?
+                }
+            };
+            let feature_tags = match substitution_offset {
+                0 => Vec::new(),
+                _ => read_substituted_features(
+                    &mut scope_at_offset(&variations_scope, substitution_offset)?.ctxt(),
+                    &feature_list_tags,
+                )?,
+            };
+            records.push(FeatureVariationRecord {
+                conditions,
+                feature_tags,
+            });
+        }
+
+        Ok(Some(FeatureVariations { records }))
+    }
+
+    /// The index of the record that is active at the normalised location `coords`.
+    ///
+    /// Only the first record whose conditions are all met is applied.
+    pub fn active_record(&self, coords: &[f32]) -> Option<usize> {
+        self.records
+            .iter()
+            .position(|record| record.is_active(coords))
+    }
+}
+
+impl FeatureVariationRecord {
+    fn is_active(&self, coords: &[f32]) -> bool {
+        self.conditions.iter().all(|condition| match condition {
+            Some(condition) => {
+                // Axes not present in the location are at their default, 0
+                let value = coords
+                    .get(usize::from(condition.axis_index))
+                    .copied()
+                    .unwrap_or(0.);
+                condition.min <= value && value <= condition.max
+            }
+            None => false,
+        })
+    }
+}
+
+impl Condition {
+    /// Display the condition using the tags of the axes, in fvar order
+    pub fn display<'a>(&'a self, axis_tags: &'a [u32]) -> impl fmt::Display + 'a {
+        DisplayCondition {
+            condition: self,
+            axis_tags,
+        }
+    }
+}
+
+struct DisplayCondition<'a> {
+    condition: &'a Condition,
+    axis_tags: &'a [u32],
+}
+
+impl fmt::Display for DisplayCondition<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.axis_tags.get(usize::from(self.condition.axis_index)) {
+            Some(&tag) => write!(f, "{}", DisplayTag(tag))?,
+            None => write!(f, "axis {}", self.condition.axis_index)?,
+        }
+        write!(f, " {}..{}", self.condition.min, self.condition.max)
+    }
+}
+
+fn read_condition_set(scope: &ReadScope<'_>) -> Result<Vec<Option<Condition>>, ParseError> {
+    let mut ctxt = scope.ctxt();
+    let condition_count = ctxt.read_u16be()?;
+    let mut conditions = Vec::with_capacity(usize::from(condition_count));
+    for _ in 0..condition_count {
+        let condition_offset = ctxt.read_u32be()? as usize;
+        let mut ctxt = scope_at_offset(scope, condition_offset)?.ctxt();
+        let format = ctxt.read_u16be()?;
+        let condition = match format {
+            1 => Some(Condition {
+                axis_index: ctxt.read_u16be()?,
+                min: f2dot14(ctxt.read_i16be()?),
+                max: f2dot14(ctxt.read_i16be()?),
+            }),
+            _ => None,
+        };
+        conditions.push(condition);
+    }
+    Ok(conditions)
+}
+
+fn read_substituted_features(
+    ctxt: &mut ReadCtxt<'_>,
+    feature_list_tags: &[u32],
+) -> Result<Vec<u32>, ParseError> {
+    let _major_version = ctxt.read_u16be()?;
+    let _minor_version = ctxt.read_u16be()?;
+    let substitution_count = ctxt.read_u16be()?;
+    let mut feature_tags = Vec::with_capacity(usize::from(substitution_count));
+    for _ in 0..substitution_count {
+        let feature_index = ctxt.read_u16be()?;
+        let _alternate_feature_offset = ctxt.read_u32be()?;
+        let tag = feature_list_tags
+            .get(usize::from(feature_index))
+            .ok_or(ParseError::BadIndex)?;
+        feature_tags.push(*tag);
+    }
+    Ok(feature_tags)
+}
+
+fn f2dot14(value: i16) -> f32 {
+    f32::from(value) / 16384.
+}
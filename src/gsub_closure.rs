@@ -0,0 +1,215 @@
+//! The glyphs GSUB can substitute into a set of glyphs, for finding the glyphs a subset needs
+//! that aren't reachable from the `cmap`
+//!
+//! allsorts only applies GSUB to shape text, so the substitutions are read from the table
+//! directly. Contextual lookups aren't followed as the lookups they apply are read on their own,
+//! which is an over-approximation: a substitution is taken to happen whenever its input glyphs
+//! are kept, whatever the context.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use allsorts::binary::read::ReadScope;
+use allsorts::error::ParseError;
+
+use crate::{read_coverage, scope_at_offset};
+
+/// GSUB lookup types that substitute glyphs themselves, rather than through other lookups
+const SINGLE: u16 = 1;
+const MULTIPLE: u16 = 2;
+const ALTERNATE: u16 = 3;
+const LIGATURE: u16 = 4;
+const EXTENSION_SUBSTITUTION: u16 = 7;
+const REVERSE_CHAINING_SINGLE: u16 = 8;
+
+/// A substitution that can produce any of `outputs` when all of `inputs` are present
+struct Substitution {
+    lookup_index: u16,
+    inputs: Vec<u16>,
+    outputs: Vec<u16>,
+}
+
+/// The substitutions of the lookups of a GSUB table, with the features that use each lookup
+pub(crate) struct GsubClosure {
+    substitutions: Vec<Substitution>,
+    lookup_features: BTreeMap<u16, BTreeSet<u32>>,
+}
+
+impl GsubClosure {
+    pub(crate) fn read(data: &[u8]) -> Result<Self, ParseError> {
+        let scope = ReadScope::new(data);
+        let mut ctxt = scope.ctxt();
+        let _major_version = ctxt.read_u16be()?;
+        let _minor_version = ctxt.read_u16be()?;
+        let _script_list_offset = ctxt.read_u16be()?;
+        let feature_list_offset = ctxt.read_u16be()?;
+        let lookup_list_offset = ctxt.read_u16be()?;
+
+        let feature_list = scope_at_offset(&scope, usize::from(feature_list_offset))?;
+        let mut ctxt = feature_list.ctxt();
+        let feature_count = ctxt.read_u16be()?;
+        let mut lookup_features = BTreeMap::<u16, BTreeSet<u32>>::new();
+        for _ in 0..feature_count {
+            let feature_tag = ctxt.read_u32be()?;
+            let feature_offset = ctxt.read_u16be()?;
+            let mut feature = scope_at_offset(&feature_list, usize::from(feature_offset))?.ctxt();
+            let _feature_params_offset = feature.read_u16be()?;
+            let lookup_index_count = feature.read_u16be()?;
+            for _ in 0..lookup_index_count {
+                let lookup_index = feature.read_u16be()?;
+                lookup_features
+                    .entry(lookup_index)
+                    .or_default()
+                    .insert(feature_tag);
+            }
+        }
+
+        let lookup_list = scope_at_offset(&scope, usize::from(lookup_list_offset))?;
+        let mut ctxt = lookup_list.ctxt();
+        let lookup_count = ctxt.read_u16be()?;
+        let mut substitutions = Vec::new();
+        for lookup_index in 0..lookup_count {
+            let lookup = scope_at_offset(&lookup_list, usize::from(ctxt.read_u16be()?))?;
+            let mut lookup_ctxt = lookup.ctxt();
+            let lookup_type = lookup_ctxt.read_u16be()?;
+            let _lookup_flag = lookup_ctxt.read_u16be()?;
+            let subtable_count = lookup_ctxt.read_u16be()?;
+            for _ in 0..subtable_count {
+                let mut subtable =
+                    scope_at_offset(&lookup, usize::from(lookup_ctxt.read_u16be()?))?;
+                let mut subtable_type = lookup_type;
+                if lookup_type == EXTENSION_SUBSTITUTION {
+                    let mut extension = subtable.ctxt();
+                    let _format = extension.read_u16be()?;
+                    subtable_type = extension.read_u16be()?;
+                    let extension_offset = extension.read_u32be()? as usize;
+                    subtable = scope_at_offset(&subtable, extension_offset)?;
+                }
+                for (inputs, outputs) in read_substitutions(&subtable, subtable_type)? {
+                    substitutions.push(Substitution {
+                        lookup_index,
+                        inputs,
+                        outputs,
+                    });
+                }
+            }
+        }
+
+        Ok(GsubClosure {
+            substitutions,
+            lookup_features,
+        })
+    }
+
+    /// The glyphs not in `glyph_ids` that substitutions can reach from them, directly or through
+    /// other reachable glyphs, with the features of the lookups that reach each one.
+    ///
+    /// Lookups that no feature uses directly, such as those only applied by contextual lookups,
+    /// contribute no features.
+    pub(crate) fn missing(&self, glyph_ids: &BTreeSet<u16>) -> BTreeMap<u16, BTreeSet<u32>> {
+        let mut reachable = glyph_ids.clone();
+        let mut missing = BTreeMap::<u16, BTreeSet<u32>>::new();
+        loop {
+            let mut added = Vec::new();
+            for substitution in &self.substitutions {
+                if !substitution
+                    .inputs
+                    .iter()
+                    .all(|glyph_id| reachable.contains(glyph_id))
+                {
+                    continue;
+                }
+                for &output in &substitution.outputs {
+                    if glyph_ids.contains(&output) {
+                        continue;
+                    }
+                    let features = self
+                        .lookup_features
+                        .get(&substitution.lookup_index)
+                        .cloned()
+                        .unwrap_or_default();
+                    missing.entry(output).or_default().extend(features);
+                    if !reachable.contains(&output) {
+                        added.push(output);
+                    }
+                }
+            }
+            if added.is_empty() {
+                return missing;
+            }
+            reachable.extend(added);
+        }
+    }
+}
+
+/// The inputs and outputs of each substitution in a subtable of `lookup_type`, empty for lookup
+/// types that only apply other lookups
+fn read_substitutions(
+    scope: &ReadScope<'_>,
+    lookup_type: u16,
+) -> Result<Vec<(Vec<u16>, Vec<u16>)>, ParseError> {
+    let mut substitutions = Vec::new();
+    if !matches!(
+        lookup_type,
+        SINGLE | MULTIPLE | ALTERNATE | LIGATURE | REVERSE_CHAINING_SINGLE
+    ) {
+        return Ok(substitutions);
+    }
+    let mut ctxt = scope.ctxt();
+    let format = ctxt.read_u16be()?;
+    // Every format of these lookup types starts with the coverage
+    let coverage = read_coverage(&scope_at_offset(scope, usize::from(ctxt.read_u16be()?))?)?;
+    match (lookup_type, format) {
+        (SINGLE, 1) => {
+            let delta = ctxt.read_u16be()?;
+            for &glyph_id in &coverage {
+                substitutions.push((vec![glyph_id], vec![glyph_id.wrapping_add(delta)]));
+            }
+        }
+        (SINGLE, 2) | (REVERSE_CHAINING_SINGLE, 1) => {
+            if lookup_type == REVERSE_CHAINING_SINGLE {
+                // Skip the backtrack and lookahead coverages, the context isn't followed
+                let backtrack_count = ctxt.read_u16be()?;
+                ctxt.read_slice(usize::from(backtrack_count) * 2)?;
+                let lookahead_count = ctxt.read_u16be()?;
+                ctxt.read_slice(usize::from(lookahead_count) * 2)?;
+            }
+            let glyph_count = ctxt.read_u16be()?;
+            for &glyph_id in coverage.iter().take(usize::from(glyph_count)) {
+                substitutions.push((vec![glyph_id], vec![ctxt.read_u16be()?]));
+            }
+        }
+        (MULTIPLE, 1) | (ALTERNATE, 1) => {
+            // Sequence and AlternateSet tables have the same layout
+            let set_count = ctxt.read_u16be()?;
+            for &glyph_id in coverage.iter().take(usize::from(set_count)) {
+                let mut set = scope_at_offset(scope, usize::from(ctxt.read_u16be()?))?.ctxt();
+                let glyph_count = set.read_u16be()?;
+                let outputs = (0..glyph_count)
+                    .map(|_| set.read_u16be())
+                    .collect::<Result<Vec<_>, _>>()?;
+                substitutions.push((vec![glyph_id], outputs));
+            }
+        }
+        (LIGATURE, 1) => {
+            let set_count = ctxt.read_u16be()?;
+            for &first in coverage.iter().take(usize::from(set_count)) {
+                let ligature_set = scope_at_offset(scope, usize::from(ctxt.read_u16be()?))?;
+                let mut set = ligature_set.ctxt();
+                let ligature_count = set.read_u16be()?;
+                for _ in 0..ligature_count {
+                    let mut ligature =
+                        scope_at_offset(&ligature_set, usize::from(set.read_u16be()?))?.ctxt();
+                    let ligature_glyph = ligature.read_u16be()?;
+                    let component_count = ligature.read_u16be()?;
+                    let mut inputs = vec![first];
+                    for _ in 1..component_count {
+                        inputs.push(ligature.read_u16be()?);
+                    }
+                    substitutions.push((inputs, vec![ligature_glyph]));
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(substitutions)
+}
@@ -1,14 +1,19 @@
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, IsTerminal, Write};
 
 use allsorts::binary::read::ReadScope;
 use allsorts::font_data::FontData;
 
 use crate::cli::InstanceOpts;
-use crate::{parse_tuple, BoxError};
+use crate::{parse_tuple, read_font_file, BoxError, ErrorMessage};
 
 pub fn main(opts: InstanceOpts) -> Result<i32, BoxError> {
-    let buffer = std::fs::read(&opts.font)?;
+    let to_stdout = opts.output == "-";
+    if to_stdout && io::stdout().is_terminal() {
+        return Err(ErrorMessage("Not printing binary data to tty.").into());
+    }
+
+    let buffer = read_font_file(&opts.font)?;
     let scope = ReadScope::new(&buffer);
     let font_file = scope.read::<FontData>()?;
     let provider = font_file.table_provider(opts.index)?;
@@ -17,8 +22,12 @@ pub fn main(opts: InstanceOpts) -> Result<i32, BoxError> {
     let (new_font, _tuple) = allsorts::variations::instance(&provider, &user_instance)?;
 
     // Write out the new font
-    let mut output = File::create(&opts.output)?;
-    output.write_all(&new_font)?;
+    if to_stdout {
+        io::stdout().write_all(&new_font)?;
+    } else {
+        let mut output = File::create(&opts.output)?;
+        output.write_all(&new_font)?;
+    }
 
     Ok(0)
 }
@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, HashSet};
+
+use allsorts::binary::read::{ReadCtxt, ReadScope};
+use allsorts::error::ParseError;
+use allsorts::tag;
+
+use crate::{read_class_def, read_coverage, scope_at_offset};
+
+/// Kerning values keyed by left and right glyph index
+pub(crate) type KerningPairs = BTreeMap<(u16, u16), i32>;
+
+/// GPOS lookup types used for pair kerning
+const PAIR_ADJUSTMENT: u16 = 2;
+const EXTENSION_POSITIONING: u16 = 9;
+
+/// The `XAdvance` bit of a ValueFormat
+const X_ADVANCE: u16 = 0x0004;
+
+/// The pairs of horizontal format 0 subtables in a `kern` table.
+///
+/// Values for the same pair in different subtables are added together. The formats of any
+/// subtables that were skipped, because only format 0 is supported, are returned alongside the
+/// pairs.
+pub(crate) fn read_kern_table(data: &[u8]) -> Result<(KerningPairs, Vec<u16>), ParseError> {
+    let scope = ReadScope::new(data);
+    let mut ctxt = scope.ctxt();
+    let mut pairs = KerningPairs::new();
+    let mut skipped = Vec::new();
+    match ctxt.read_u16be()? {
+        // Microsoft kern table
+        0 => {
+            let num_tables = ctxt.read_u16be()?;
+            let mut subtable_offset = 4;
+            for _ in 0..num_tables {
+                let mut ctxt = scope_at_offset(&scope, subtable_offset)?.ctxt();
+                let _version = ctxt.read_u16be()?;
+                let length = ctxt.read_u16be()?;
+                let coverage = ctxt.read_u16be()?;
+                let format = coverage >> 8;
+                let horizontal = coverage & 0x1 != 0 && coverage & 0x4 == 0;
+                if format == 0 {
+                    // The length of large format 0 subtables overflows so use the number of pairs
+                    let num_pairs = read_kern_format0(&mut ctxt, &mut pairs, horizontal)?;
+                    subtable_offset += 14 + usize::from(num_pairs) * 6;
+                } else {
+                    skipped.push(format);
+                    subtable_offset += usize::from(length);
+                }
+            }
+        }
+        // Apple kern table, version 1.0 as a 32-bit fixed
+        1 => {
+            let _version_fraction = ctxt.read_u16be()?;
+            let num_tables = ctxt.read_u32be()?;
+            for _ in 0..num_tables {
+                let length = ctxt.read_u32be()? as usize;
+                let coverage = ctxt.read_u16be()?;
+                let _tuple_index = ctxt.read_u16be()?;
+                let format = coverage & 0xFF;
+                let subtable = ctxt.read_slice(length.saturating_sub(8))?;
+                let horizontal = coverage & 0xE000 == 0;
+                if format == 0 {
+                    read_kern_format0(
+                        &mut ReadScope::new(subtable).ctxt(),
+                        &mut pairs,
+                        horizontal,
+                    )?;
+                } else {
+                    skipped.push(format);
+                }
+            }
+        }
+        _ => return Err(ParseError::BadVersion),
+    }
+    Ok((pairs, skipped))
+}
+
+/// Read the pairs of a format 0 subtable, adding them to `pairs` if `horizontal`, and return the
+/// number of pairs
+fn read_kern_format0(
+    ctxt: &mut ReadCtxt<'_>,
+    pairs: &mut KerningPairs,
+    horizontal: bool,
+) -> Result<u16, ParseError> {
+    let num_pairs = ctxt.read_u16be()?;
+    let _search_range = ctxt.read_u16be()?;
+    let _entry_selector = ctxt.read_u16be()?;
+    let _range_shift = ctxt.read_u16be()?;
+    for _ in 0..num_pairs {
+        let left = ctxt.read_u16be()?;
+        let right = ctxt.read_u16be()?;
+        let value = ctxt.read_i16be()?;
+        if horizontal {
+            *pairs.entry((left, right)).or_insert(0) += i32::from(value);
+        }
+    }
+    Ok(num_pairs)
+}
+
+/// The x-advance adjustments of the first glyph made by the pair adjustment lookups of the
+/// GPOS `kern` feature, in any script.
+///
+/// Values from different lookups are added together. Within a lookup only the first subtable
+/// that applies to a pair is used. For class based subtables only second glyphs explicitly
+/// assigned a class are listed, glyphs in class 0 are treated as having no kerning.
+pub(crate) fn read_gpos_kerning(data: &[u8]) -> Result<KerningPairs, ParseError> {
+    let scope = ReadScope::new(data);
+    let mut ctxt = scope.ctxt();
+    let _major_version = ctxt.read_u16be()?;
+    let _minor_version = ctxt.read_u16be()?;
+    let _script_list_offset = ctxt.read_u16be()?;
+    let feature_list_offset = ctxt.read_u16be()?;
+    let lookup_list_offset = ctxt.read_u16be()?;
+
+    // Collect the lookups of every kern feature
+    let feature_list = scope_at_offset(&scope, usize::from(feature_list_offset))?;
+    let mut ctxt = feature_list.ctxt();
+    let feature_count = ctxt.read_u16be()?;
+    let mut lookup_indices = Vec::new();
+    for _ in 0..feature_count {
+        let feature_tag = ctxt.read_u32be()?;
+        let feature_offset = ctxt.read_u16be()?;
+        if feature_tag != tag!(b"kern") {
+            continue;
+        }
+        let mut feature = scope_at_offset(&feature_list, usize::from(feature_offset))?.ctxt();
+        let _feature_params_offset = feature.read_u16be()?;
+        let lookup_index_count = feature.read_u16be()?;
+        for _ in 0..lookup_index_count {
+            lookup_indices.push(feature.read_u16be()?);
+        }
+    }
+    lookup_indices.sort_unstable();
+    lookup_indices.dedup();
+
+    let lookup_list = scope_at_offset(&scope, usize::from(lookup_list_offset))?;
+    let mut ctxt = lookup_list.ctxt();
+    let lookup_count = ctxt.read_u16be()?;
+    let mut lookup_offsets = Vec::with_capacity(usize::from(lookup_count));
+    for _ in 0..lookup_count {
+        lookup_offsets.push(ctxt.read_u16be()?);
+    }
+
+    let mut pairs = KerningPairs::new();
+    for lookup_index in lookup_indices {
+        let lookup_offset = *lookup_offsets
+            .get(usize::from(lookup_index))
+            .ok_or(ParseError::BadIndex)?;
+        let lookup = scope_at_offset(&lookup_list, usize::from(lookup_offset))?;
+        let mut ctxt = lookup.ctxt();
+        let lookup_type = ctxt.read_u16be()?;
+        let _lookup_flag = ctxt.read_u16be()?;
+        let subtable_count = ctxt.read_u16be()?;
+        let mut lookup_pairs = LookupPairs::default();
+        for _ in 0..subtable_count {
+            let mut subtable = scope_at_offset(&lookup, usize::from(ctxt.read_u16be()?))?;
+            let mut subtable_type = lookup_type;
+            if lookup_type == EXTENSION_POSITIONING {
+                let mut extension = subtable.ctxt();
+                let _format = extension.read_u16be()?;
+                subtable_type = extension.read_u16be()?;
+                let extension_offset = extension.read_u32be()? as usize;
+                subtable = scope_at_offset(&subtable, extension_offset)?;
+            }
+            if subtable_type == PAIR_ADJUSTMENT {
+                read_pair_pos(&subtable, &mut lookup_pairs)?;
+            }
+        }
+        for (pair, value) in lookup_pairs.pairs {
+            *pairs.entry(pair).or_insert(0) += value;
+        }
+    }
+    pairs.retain(|_, value| *value != 0);
+    Ok(pairs)
+}
+
+/// The pairs found in the subtables of one lookup so far
+#[derive(Default)]
+struct LookupPairs {
+    pairs: BTreeMap<(u16, u16), i32>,
+    /// First glyphs covered by a class based subtable, which applies to every second glyph
+    class_covered: HashSet<u16>,
+}
+
+impl LookupPairs {
+    fn insert(&mut self, first: u16, second: u16, value: i32) {
+        if !self.class_covered.contains(&first) {
+            self.pairs.entry((first, second)).or_insert(value);
+        }
+    }
+}
+
+fn read_pair_pos(scope: &ReadScope<'_>, lookup_pairs: &mut LookupPairs) -> Result<(), ParseError> {
+    let mut ctxt = scope.ctxt();
+    let format = ctxt.read_u16be()?;
+    let coverage = read_coverage(&scope_at_offset(scope, usize::from(ctxt.read_u16be()?))?)?;
+    let value_format1 = ctxt.read_u16be()?;
+    let value_format2 = ctxt.read_u16be()?;
+    let value_size1 = value_record_size(value_format1);
+    let value_size2 = value_record_size(value_format2);
+    match format {
+        1 => {
+            let pair_set_count = ctxt.read_u16be()?;
+            for &first in coverage.iter().take(usize::from(pair_set_count)) {
+                let pair_set = scope_at_offset(scope, usize::from(ctxt.read_u16be()?))?;
+                let mut pair_set = pair_set.ctxt();
+                let pair_value_count = pair_set.read_u16be()?;
+                for _ in 0..pair_value_count {
+                    let second = pair_set.read_u16be()?;
+                    let value1 = pair_set.read_slice(value_size1)?;
+                    let _value2 = pair_set.read_slice(value_size2)?;
+                    lookup_pairs.insert(first, second, x_advance(value_format1, value1)?);
+                }
+            }
+        }
+        2 => {
+            let class_def1 =
+                read_class_def(&scope_at_offset(scope, usize::from(ctxt.read_u16be()?))?)?;
+            let class_def2 =
+                read_class_def(&scope_at_offset(scope, usize::from(ctxt.read_u16be()?))?)?;
+            let class1_count = usize::from(ctxt.read_u16be()?);
+            let class2_count = usize::from(ctxt.read_u16be()?);
+            let record_size = value_size1 + value_size2;
+            let records = ctxt.read_slice(class1_count * class2_count * record_size)?;
+            for &first in &coverage {
+                let class1 = usize::from(class_def1.get(&first).copied().unwrap_or(0));
+                for (&second, &class2) in &class_def2 {
+                    let class2 = usize::from(class2);
+                    if class1 >= class1_count || class2 >= class2_count {
+                        continue;
+                    }
+                    let start = (class1 * class2_count + class2) * record_size;
+                    let value1 = &records[start..start + value_size1];
+                    lookup_pairs.insert(first, second, x_advance(value_format1, value1)?);
+                }
+            }
+            lookup_pairs.class_covered.extend(coverage);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn value_record_size(value_format: u16) -> usize {
+    value_format.count_ones() as usize * 2
+}
+
+/// Read the `XAdvance` field of a ValueRecord, 0 if the format doesn't include it
+fn x_advance(value_format: u16, value: &[u8]) -> Result<i32, ParseError> {
+    if value_format & X_ADVANCE == 0 {
+        return Ok(0);
+    }
+    // Skip the XPlacement and YPlacement fields that precede XAdvance
+    let skip = value_record_size(value_format & (X_ADVANCE - 1));
+    let mut ctxt = ReadScope::new(value).ctxt();
+    let _placement = ctxt.read_slice(skip)?;
+    Ok(i32::from(ctxt.read_i16be()?))
+}
@@ -2,7 +2,8 @@ use allsorts::binary::read::ReadScope;
 use allsorts::font::Font;
 use allsorts::font_data::FontData;
 use allsorts::layout::{LangSys, LayoutTable};
-use allsorts::tag::DisplayTag;
+use allsorts::tables::FontTableProvider;
+use allsorts::tag::{self, DisplayTag};
 
 use crate::cli::LayoutFeaturesOpts;
 use crate::BoxError;
@@ -12,21 +13,48 @@ pub fn main(opts: LayoutFeaturesOpts) -> Result<i32, BoxError> {
     let scope = ReadScope::new(&buffer);
     let font_file = scope.read::<FontData>()?;
     let provider = font_file.table_provider(opts.index)?;
+    let gsub_data = provider.table_data(tag::GSUB)?;
+    let gpos_data = provider.table_data(tag::GPOS)?;
     let mut font = Font::new(provider)?;
 
-    if let Some(gsub_cache) = font.gsub_cache()? {
+    if let (Some(gsub_cache), Some(data)) = (font.gsub_cache()?, &gsub_data) {
         println!("Table: GSUB");
+        print_version(data)?;
         print_layout_features(&gsub_cache.layout_table)?;
     }
 
-    if let Some(gpos_cache) = font.gpos_cache()? {
+    if let (Some(gpos_cache), Some(data)) = (font.gpos_cache()?, &gpos_data) {
         println!("Table: GPOS");
+        print_version(data)?;
         print_layout_features(&gpos_cache.layout_table)?;
     }
 
     Ok(0)
 }
 
+/// Print the version of a GSUB or GPOS table and whether it has a FeatureVariations table,
+/// which variable fonts use to switch features at some locations in the design space
+fn print_version(data: &[u8]) -> Result<(), BoxError> {
+    let mut ctxt = ReadScope::new(data).ctxt();
+    let major_version = ctxt.read_u16be()?;
+    let minor_version = ctxt.read_u16be()?;
+    let has_feature_variations = if minor_version >= 1 {
+        let _script_list_offset = ctxt.read_u16be()?;
+        let _feature_list_offset = ctxt.read_u16be()?;
+        let _lookup_list_offset = ctxt.read_u16be()?;
+        ctxt.read_u32be()? != 0
+    } else {
+        false
+    };
+    println!("  Version: {}.{}", major_version, minor_version);
+    println!(
+        "  FeatureVariations: {}",
+        if has_feature_variations { "yes" } else { "no" }
+    );
+
+    Ok(())
+}
+
 fn print_layout_features<T>(layout_table: &LayoutTable<T>) -> Result<(), BoxError> {
     if let Some(script_list) = &layout_table.opt_script_list {
         for script_record in script_list.script_records() {
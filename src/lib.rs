@@ -1,33 +1,53 @@
+mod baseline;
 pub mod bitmaps;
 pub mod cli;
 pub mod cmap;
 pub mod dump;
+mod extents;
+mod feature_variations;
 mod glyph;
+mod gsub_closure;
 pub mod has_table;
 pub mod instance;
+mod kerning;
 pub mod layout_features;
 mod script;
 pub mod shape;
 pub mod specimen;
+pub mod stats;
 pub mod subset;
 pub mod svg;
+mod svg_table;
 pub mod validate;
+pub mod validation;
+mod variation_sequences;
 pub mod variations;
+mod vertical_metrics;
 pub mod view;
 mod writer;
 
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::convert;
 use std::error::Error;
 use std::fmt;
+use std::io::{self, Read, Write};
 use std::num::ParseFloatError;
 
 use allsorts::binary::read::ReadScope;
 use allsorts::error::ParseError;
+use allsorts::font::{read_cmap_subtable, Font, MatchingPresentation};
+use allsorts::glyph_info::GlyphNames;
+use allsorts::gsub::{FeatureInfo, FeatureMask, Features, RawGlyph};
+use allsorts::tables::cmap::Cmap;
 use allsorts::tables::variable_fonts::avar::AvarTable;
 use allsorts::tables::variable_fonts::fvar::FvarTable;
 use allsorts::tables::variable_fonts::OwnedTuple;
 use allsorts::tables::{Fixed, FontTableProvider};
-use allsorts::tag;
+use allsorts::tag::{self, DisplayTag};
 use encoding_rs::Encoding;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 
 pub type BoxError = Box<dyn Error>;
 
@@ -77,3 +97,316 @@ fn normalise_tuple(
 
     fvar.normalize(tuple.iter().copied(), avar.as_ref())
 }
+
+/// A scope starting `offset` bytes into `scope`, checking that it is in bounds
+fn scope_at_offset<'a>(scope: &ReadScope<'a>, offset: usize) -> Result<ReadScope<'a>, ParseError> {
+    if offset > scope.data().len() {
+        return Err(ParseError::BadOffset);
+    }
+    Ok(scope.offset(offset))
+}
+
+/// The name of each of `glyph_ids`, from the `post` table or made up from the `cmap` mapping if
+/// the font doesn't name it
+fn glyph_names(
+    provider: &impl FontTableProvider,
+    glyph_ids: &[u16],
+) -> Result<Vec<String>, ParseError> {
+    let post_data = provider
+        .table_data(tag::POST)
+        .ok()
+        .and_then(convert::identity)
+        .map(|data| Box::from(&*data));
+
+    let table = provider.table_data(tag::CMAP)?;
+    let scope = table.as_ref().map(|data| ReadScope::new(data.borrow()));
+    let cmap = scope.map(|scope| scope.read::<Cmap<'_>>()).transpose()?;
+
+    let cmap_subtable = cmap
+        .as_ref()
+        .and_then(|cmap| read_cmap_subtable(cmap).ok())
+        .and_then(convert::identity);
+
+    let names = GlyphNames::new(&cmap_subtable, post_data);
+    Ok(glyph_ids
+        .iter()
+        .map(|&glyph_id| names.glyph_name(glyph_id).into_owned())
+        .collect())
+}
+
+/// The glyphs of the Coverage table in `scope`, in coverage index order
+fn read_coverage(scope: &ReadScope<'_>) -> Result<Vec<u16>, ParseError> {
+    let mut ctxt = scope.ctxt();
+    let mut glyphs = Vec::new();
+    match ctxt.read_u16be()? {
+        1 => {
+            let glyph_count = ctxt.read_u16be()?;
+            for _ in 0..glyph_count {
+                glyphs.push(ctxt.read_u16be()?);
+            }
+        }
+        2 => {
+            let range_count = ctxt.read_u16be()?;
+            for _ in 0..range_count {
+                let start = ctxt.read_u16be()?;
+                let end = ctxt.read_u16be()?;
+                let _start_coverage_index = ctxt.read_u16be()?;
+                glyphs.extend(start..=end);
+            }
+        }
+        _ => return Err(ParseError::BadValue),
+    }
+    Ok(glyphs)
+}
+
+/// The glyphs assigned a non-zero class by the ClassDef table in `scope`, with their class
+fn read_class_def(scope: &ReadScope<'_>) -> Result<BTreeMap<u16, u16>, ParseError> {
+    let mut ctxt = scope.ctxt();
+    let mut classes = BTreeMap::new();
+    match ctxt.read_u16be()? {
+        1 => {
+            let start_glyph = ctxt.read_u16be()?;
+            let glyph_count = ctxt.read_u16be()?;
+            for i in 0..glyph_count {
+                classes.insert(start_glyph.wrapping_add(i), ctxt.read_u16be()?);
+            }
+        }
+        2 => {
+            let range_count = ctxt.read_u16be()?;
+            for _ in 0..range_count {
+                let start = ctxt.read_u16be()?;
+                let end = ctxt.read_u16be()?;
+                let class = ctxt.read_u16be()?;
+                classes.extend((start..=end).map(|glyph| (glyph, class)));
+            }
+        }
+        _ => return Err(ParseError::BadValue),
+    }
+    classes.retain(|_, class| *class != 0);
+    Ok(classes)
+}
+
+/// Replace glyphs the font couldn't map with the glyph for `fallback`.
+///
+/// Returns the number of glyphs replaced, or `None` if the font can't map `fallback` either.
+fn substitute_unmapped<F: FontTableProvider>(
+    font: &mut Font<F>,
+    glyphs: &mut [RawGlyph<()>],
+    fallback: char,
+    script: u32,
+) -> Option<usize> {
+    let mut buf = [0; 4];
+    let fallback_index = font
+        .map_glyphs(
+            fallback.encode_utf8(&mut buf),
+            script,
+            MatchingPresentation::NotRequired,
+        )
+        .first()
+        .map(|glyph| glyph.glyph_index)
+        .filter(|&glyph_index| glyph_index != 0)?;
+    let mut replaced = 0;
+    for glyph in glyphs.iter_mut().filter(|glyph| glyph.glyph_index == 0) {
+        glyph.glyph_index = fallback_index;
+        replaced += 1;
+    }
+    Some(replaced)
+}
+
+/// Read the font file at `path`, or stdin if it is `-` so that fonts can be piped between commands.
+fn read_font_file(path: &str) -> io::Result<Vec<u8>> {
+    if path == "-" {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        std::fs::read(path)
+    }
+}
+
+/// Write `svg` followed by a new line to the file at `output`, or stdout if it is `None`.
+fn write_svg(svg: &str, output: Option<&str>) -> Result<(), BoxError> {
+    match output {
+        Some(path) => std::fs::write(path, format!("{}\n", svg))?,
+        None => println!("{}", svg),
+    }
+    Ok(())
+}
+
+/// Write `svg` followed by a new line, gzip compressed (SVGZ), to the file at `output`, or stdout
+/// if it is `None`.
+fn write_svgz(svg: &str, output: Option<&str>) -> Result<(), BoxError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    writeln!(encoder, "{}", svg)?;
+    let data = encoder.finish()?;
+    match output {
+        Some(path) => std::fs::write(path, data)?,
+        None => io::stdout().write_all(&data)?,
+    }
+    Ok(())
+}
+
+/// The registered OpenType feature tags, to find the features of a `FeatureMask`
+const FEATURE_TAGS: [&[u8; 4]; 141] = [
+    b"aalt", b"abvf", b"abvm", b"abvs", b"afrc", b"akhn", b"blwf", b"blwm", b"blws", b"c2pc",
+    b"c2sc", b"calt", b"case", b"ccmp", b"cfar", b"chws", b"cjct", b"clig", b"cpct", b"cpsp",
+    b"cswh", b"curs", b"dist", b"dlig", b"dnom", b"dtls", b"expt", b"falt", b"fin2", b"fin3",
+    b"fina", b"flac", b"frac", b"fwid", b"half", b"haln", b"halt", b"hist", b"hkna", b"hlig",
+    b"hngl", b"hojo", b"hwid", b"init", b"isol", b"ital", b"jalt", b"jp04", b"jp78", b"jp83",
+    b"jp90", b"kern", b"lfbd", b"liga", b"ljmo", b"lnum", b"locl", b"ltra", b"ltrm", b"mark",
+    b"med2", b"medi", b"mgrk", b"mkmk", b"mset", b"nalt", b"nlck", b"nukt", b"numr", b"onum",
+    b"opbd", b"ordn", b"ornm", b"palt", b"pcap", b"pkna", b"pnum", b"pref", b"pres", b"pstf",
+    b"psts", b"pwid", b"qwid", b"rand", b"rclt", b"rkrf", b"rlig", b"rphf", b"rtbd", b"rtla",
+    b"rtlm", b"ruby", b"rvrn", b"salt", b"sinf", b"smcp", b"smpl", b"ss01", b"ss02", b"ss03",
+    b"ss04", b"ss05", b"ss06", b"ss07", b"ss08", b"ss09", b"ss10", b"ss11", b"ss12", b"ss13",
+    b"ss14", b"ss15", b"ss16", b"ss17", b"ss18", b"ss19", b"ss20", b"ssty", b"stch", b"subs",
+    b"sups", b"swsh", b"titl", b"tjmo", b"tnam", b"tnum", b"trad", b"twid", b"unic", b"valt",
+    b"vatu", b"vchw", b"vert", b"vhal", b"vjmo", b"vkna", b"vkrn", b"vpal", b"vrt2", b"vrtr",
+    b"zero",
+];
+
+/// The GPOS features allsorts applies along with the features of a `FeatureMask`
+const MASK_GPOS_FEATURES: [&[u8; 4]; 7] = [
+    b"abvm", b"blwm", b"curs", b"dist", b"kern", b"mark", b"mkmk",
+];
+
+/// `mask` with the features `added` enabled.
+///
+/// Features allsorts doesn't know can't be added to a mask, so if there are any the features of
+/// the mask, the GPOS features it implies, and the added features are listed instead.
+fn add_to_mask(mut mask: FeatureMask, added: &[u32]) -> Features {
+    let mut unknown = Vec::new();
+    for &feature_tag in added {
+        let feature = FeatureMask::from_tag(feature_tag);
+        if feature.is_empty() {
+            unknown.push(feature_tag);
+        } else {
+            mask |= feature;
+        }
+    }
+    if unknown.is_empty() {
+        return Features::Mask(mask);
+    }
+
+    let mask_tags = FEATURE_TAGS
+        .iter()
+        .map(|tag| u32::from_be_bytes(**tag))
+        .filter(|&tag| {
+            let feature = FeatureMask::from_tag(tag);
+            !feature.is_empty() && mask.contains(feature)
+        });
+    let gpos_tags = MASK_GPOS_FEATURES
+        .iter()
+        .map(|tag| u32::from_be_bytes(**tag));
+    let mut feature_tags = Vec::new();
+    for feature_tag in mask_tags.chain(gpos_tags).chain(unknown) {
+        if !feature_tags.contains(&feature_tag) {
+            feature_tags.push(feature_tag);
+        }
+    }
+    let feature_infos = feature_tags
+        .into_iter()
+        .map(|feature_tag| FeatureInfo {
+            feature_tag,
+            alternate: None,
+        })
+        .collect();
+    Features::Custom(feature_infos)
+}
+
+/// Parse a comma-separated list of features.
+///
+/// A plain list of tags gives just those features, like a features file. Tags prefixed with `+`
+/// or `-` instead modify the default features, adding or removing that feature. Features
+/// allsorts doesn't know can be added, but only enabled features can be removed. The two forms
+/// can't be mixed as it's unclear whether the defaults apply.
+fn parse_features(features: &str) -> Result<Features, BoxError> {
+    let mut plain = Vec::new();
+    let mut modified = Vec::new();
+    for feature in features.split(',').map(str::trim) {
+        if let Some(feature) = feature.strip_prefix('+') {
+            modified.push((true, tag::from_string(feature.trim())?));
+        } else if let Some(feature) = feature.strip_prefix('-') {
+            modified.push((false, tag::from_string(feature.trim())?));
+        } else {
+            plain.push(tag::from_string(feature)?);
+        }
+    }
+
+    if !plain.is_empty() && !modified.is_empty() {
+        return Err(format!(
+            "{} has features both with and without a + or - prefix, either list only the \
+             features to apply or only modify the defaults with +tag and -tag",
+            features
+        )
+        .into());
+    }
+
+    if plain.is_empty() {
+        let mut mask = FeatureMask::default();
+        let mut added = Vec::new();
+        for (enable, feature_tag) in modified {
+            let feature = FeatureMask::from_tag(feature_tag);
+            if enable {
+                if feature.is_empty() {
+                    added.push(feature_tag);
+                } else {
+                    mask |= feature;
+                }
+            } else if !feature.is_empty() && mask.contains(feature) {
+                mask -= feature;
+            } else {
+                return Err(format!(
+                    "{} isn't one of the default features so can't be disabled",
+                    DisplayTag(feature_tag)
+                )
+                .into());
+            }
+        }
+        return Ok(add_to_mask(mask, &added));
+    }
+
+    let feature_infos = plain
+        .into_iter()
+        .map(|feature_tag| FeatureInfo {
+            feature_tag,
+            alternate: None,
+        })
+        .collect();
+    Ok(Features::Custom(feature_infos))
+}
+
+/// Read a feature profile from a file.
+///
+/// Entries are separated by commas or new lines and are either a bare feature tag or `tag=value`.
+/// A value of 0 disables the feature, 1 enables it, and higher values select that (1-based)
+/// alternate. Lines starting with `#` are ignored.
+fn read_features_file(path: &str) -> Result<Features, BoxError> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut feature_infos = Vec::new();
+    for entry in contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| line.split(','))
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let (feature, value) = match entry.split_once('=') {
+            Some((feature, value)) => (feature.trim(), value.trim().parse::<usize>()?),
+            None => (entry, 1),
+        };
+        let feature_tag = tag::from_string(feature)?;
+        match value {
+            0 => {}
+            1 => feature_infos.push(FeatureInfo {
+                feature_tag,
+                alternate: None,
+            }),
+            n => feature_infos.push(FeatureInfo {
+                feature_tag,
+                alternate: Some(n - 1),
+            }),
+        }
+    }
+    Ok(Features::Custom(feature_infos))
+}
@@ -1,5 +1,35 @@
+use std::str::FromStr;
+
 use allsorts::glyph_position::TextDirection;
 
+/// A text direction given on the command line, to use instead of the direction of the script
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ltr" => Ok(Direction::Ltr),
+            "rtl" => Ok(Direction::Rtl),
+            _ => Err(format!("invalid direction '{}', expected ltr or rtl", s)),
+        }
+    }
+}
+
+impl From<Direction> for TextDirection {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Ltr => TextDirection::LeftToRight,
+            Direction::Rtl => TextDirection::RightToLeft,
+        }
+    }
+}
+
 mod rtl_tags {
     use allsorts::tag;
 
@@ -68,3 +98,8 @@ pub fn direction(script: u32) -> TextDirection {
         _ => TextDirection::LeftToRight,
     }
 }
+
+/// The direction of `script`, unless `direction` overrides it
+pub fn direction_or(script: u32, direction: Option<Direction>) -> TextDirection {
+    direction.map_or_else(|| self::direction(script), TextDirection::from)
+}
@@ -1,25 +1,231 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
 use allsorts::binary::read::ReadScope;
+use allsorts::error::ParseError;
 use allsorts::font::{Font, MatchingPresentation};
 use allsorts::font_data::FontData;
-use allsorts::glyph_position::{GlyphLayout, TextDirection};
-use allsorts::gsub::{FeatureMask, Features};
+use allsorts::glyph_position::{GlyphLayout, GlyphPosition, TextDirection};
+use allsorts::gpos::Info;
+use allsorts::gsub::{self, FeatureMask, Features, RawGlyph};
+use allsorts::layout::LayoutTable;
+use allsorts::pathfinder_geometry::transform2d::Matrix2x2F;
+use allsorts::pathfinder_geometry::vector::vec2f;
+use allsorts::tables::variable_fonts::fvar::FvarTable;
 use allsorts::tables::variable_fonts::OwnedTuple;
+use allsorts::tables::{FontTableProvider, SfntVersion};
 use allsorts::tag;
+use allsorts::tag::DisplayTag;
+use serde::{Deserialize, Serialize};
 
 use crate::cli::ShapeOpts;
-use crate::{normalise_tuple, parse_tuple, BoxError};
+use crate::extents::{self, Extents};
+use crate::feature_variations::FeatureVariations;
+use crate::glyph;
+use crate::variation_sequences::{is_variation_selector, VariationGlyph, VariationSequences};
+use crate::vertical_metrics::{self, VerticalMetrics};
+use crate::view;
+use crate::writer::{Margin, SVGMode, SVGWriter};
+use crate::{
+    glyph_names, normalise_tuple, parse_features, parse_tuple, read_features_file, script,
+    substitute_unmapped, BoxError, ErrorMessage,
+};
+
+/// An alternative output format for the shaped glyphs, given with `--format`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShapeFormat {
+    /// The glyph buffer as printed by `hb-shape --no-glyph-names`
+    Hb,
+}
+
+impl FromStr for ShapeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hb" => Ok(ShapeFormat::Hb),
+            _ => Err(format!("invalid format '{}', expected hb", s)),
+        }
+    }
+}
+
+/// The coordinate convention of a platform text API, for printing positions the way it reports
+/// them with `--convention`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Convention {
+    /// CoreText's `CTRunGetPositions` and `CTRunGetAdvances`: the absolute position of each
+    /// glyph from the start of the line and its advance vector
+    CoreText,
+    /// DirectWrite's `glyphAdvances` and `glyphOffsets`: the advance of each glyph and its
+    /// offset from the pen as an advance offset and an ascender offset
+    DirectWrite,
+}
+
+impl FromStr for Convention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "coretext" => Ok(Convention::CoreText),
+            "directwrite" => Ok(Convention::DirectWrite),
+            _ => Err(format!(
+                "invalid convention '{}', expected coretext or directwrite",
+                s
+            )),
+        }
+    }
+}
+
+/// A shaping scenario read with `--config`, where each field given overrides the option of the
+/// same name
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShapeConfig {
+    script: Option<String>,
+    lang: Option<String>,
+    /// In the same form as `--features`
+    features: Option<String>,
+    /// In the same form as `--tuple`
+    tuple: Option<String>,
+    text: Option<String>,
+}
+
+impl ShapeConfig {
+    fn apply(self, opts: &mut ShapeOpts) {
+        let ShapeConfig {
+            script,
+            lang,
+            features,
+            tuple,
+            text,
+        } = self;
+        opts.script = script.or(opts.script.take());
+        opts.lang = lang.or(opts.lang.take());
+        opts.tuple = tuple.or(opts.tuple.take());
+        // The features and text replace the options they conflict with too
+        if features.is_some() {
+            opts.features = features;
+            opts.features_file = None;
+        }
+        if text.is_some() {
+            opts.text = text;
+            opts.tests = None;
+            opts.text_file = None;
+        }
+    }
+}
+
+pub fn main(mut opts: ShapeOpts) -> Result<i32, BoxError> {
+    if let Some(path) = &opts.config {
+        let config = serde_json::from_str::<ShapeConfig>(&std::fs::read_to_string(path)?)
+            .map_err(|err| format!("unable to read {}: {}", path, err))?;
+        config.apply(&mut opts);
+    }
+    let (script, lang) = match (&opts.script, &opts.lang) {
+        (Some(script), Some(lang)) => (tag::from_string(script)?, tag::from_string(lang)?),
+        _ => {
+            eprintln!("required: --script and --lang, or a --config that gives them");
+            return Ok(1);
+        }
+    };
+    let inputs = match (&opts.text, &opts.tests, &opts.text_file) {
+        (Some(text), None, None) => vec![text.clone()],
+        (None, Some(path), None) => std::fs::read_to_string(path)?
+            .lines()
+            .map(String::from)
+            .collect(),
+        (None, None, Some(path)) => read_text_file(path)?.lines().map(String::from).collect(),
+        (_, _, _) => {
+            eprintln!("required: one of TEXT, --tests FILE, or --text-file PATH");
+            return Ok(1);
+        }
+    };
+    if opts.porcelain
+        && (opts.bench.is_some() || opts.stage_svgs.is_some() || opts.extents || opts.feature_stats)
+    {
+        return Err(ErrorMessage(
+            "--porcelain can't be combined with --bench, --stage-svgs, --extents, or \
+             --feature-stats",
+        )
+        .into());
+    }
+    // The options that replace the output with one line of glyphs per input
+    let plain_options = [
+        ("--json", opts.json),
+        ("--format", opts.format.is_some()),
+        ("--convention", opts.convention.is_some()),
+    ]
+    .iter()
+    .filter(|(_, given)| *given)
+    .map(|(option, _)| *option)
+    .collect::<Vec<_>>();
+    if plain_options.len() > 1 {
+        return Err(
+            ErrorMessage("only one of --json, --format, and --convention can be given").into(),
+        );
+    }
+    let plain_option = plain_options.first().copied();
+    if opts.convention.is_some() && opts.vertical {
+        return Err(ErrorMessage("--convention can't be combined with --vertical").into());
+    }
+    if let Some(option) = plain_option {
+        if opts.porcelain
+            || opts.bench.is_some()
+            || opts.extents
+            || opts.both_advances
+            || opts.feature_stats
+            || opts.context_before.is_some()
+            || opts.context_after.is_some()
+        {
+            return Err(format!(
+                "{} can't be combined with --porcelain, --bench, --extents, --both-advances, \
+                 --feature-stats, --context-before, or --context-after",
+                option
+            )
+            .into());
+        }
+    }
+    if opts.trace && (opts.porcelain || plain_option.is_some() || opts.bench.is_some()) {
+        return Err(ErrorMessage(
+            "--trace can't be combined with --porcelain, --json, --format, --convention, or --bench",
+        )
+        .into());
+    }
+    if opts.expect_glyphs.is_some()
+        && (opts.porcelain
+            || plain_option.is_some()
+            || opts.bench.is_some()
+            || opts.stage_svgs.is_some()
+            || opts.extents
+            || opts.feature_stats)
+    {
+        return Err(ErrorMessage(
+            "--expect-glyphs can't be combined with --porcelain, --json, --format, --convention, \
+             --bench, --stage-svgs, --extents, or --feature-stats",
+        )
+        .into());
+    }
+    if opts.tests.is_some() && (opts.bench.is_some() || opts.stage_svgs.is_some()) {
+        return Err(ErrorMessage("--tests can't be combined with --bench or --stage-svgs").into());
+    }
+    if opts.text_file.is_some() && (opts.bench.is_some() || opts.stage_svgs.is_some()) {
+        return Err(
+            ErrorMessage("--text-file can't be combined with --bench or --stage-svgs").into(),
+        );
+    }
 
-pub fn main(opts: ShapeOpts) -> Result<i32, BoxError> {
-    let script = tag::from_string(&opts.script)?;
-    let lang = tag::from_string(&opts.lang)?;
     let buffer = std::fs::read(&opts.font)?;
     let scope = ReadScope::new(&buffer);
     let font_file = scope.read::<FontData<'_>>()?;
     let provider = font_file.table_provider(opts.index)?;
 
     let user_tuple = opts.tuple.as_deref().map(parse_tuple).transpose()?;
-    let tuple = match user_tuple {
-        Some(user_tuple) => match normalise_tuple(&provider, &user_tuple) {
+    let tuple = match &user_tuple {
+        Some(user_tuple) => match normalise_tuple(&provider, user_tuple) {
             Ok(tuple) => Some(tuple),
             Err(err) => {
                 eprintln!("unable to normalise variation tuple: {err}");
@@ -29,31 +235,1311 @@ pub fn main(opts: ShapeOpts) -> Result<i32, BoxError> {
         None => None,
     };
 
+    if let (Some(tuple), false) = (
+        &tuple,
+        opts.porcelain || plain_option.is_some() || opts.expect_glyphs.is_some(),
+    ) {
+        print_active_feature_variations(&provider, tuple)?;
+    }
+
+    let features = match (&opts.features, &opts.features_file) {
+        (Some(features), None) => parse_features(features)?,
+        (None, Some(path)) => read_features_file(path)?,
+        (None, None) => Features::Mask(FeatureMask::default()),
+        (Some(_), Some(_)) => {
+            eprintln!("only one of --features OR --features-file may be supplied");
+            return Ok(1);
+        }
+    };
+
+    // Variable fonts are instanced at the tuple so that the extents reflect that location
+    let instance = match &user_tuple {
+        Some(user_tuple) if opts.extents => {
+            Some(allsorts::variations::instance(&provider, user_tuple)?.0)
+        }
+        _ => None,
+    };
+
+    if opts.both_advances && !(provider.has_table(tag::VHEA) && provider.has_table(tag::VMTX)) {
+        return Err(ErrorMessage("--both-advances requires vhea and vmtx tables").into());
+    }
+    if opts.vertical
+        && !opts.porcelain
+        && plain_option.is_none()
+        && vertical_metrics::synthesized(&provider)
+    {
+        eprintln!("note: no vhea and vmtx tables, vertical metrics are synthesized from hhea");
+    }
+
+    let sequences = provider
+        .table_data(tag::CMAP)?
+        .map(|data| VariationSequences::read_cmap(&data))
+        .transpose()?
+        .flatten();
     let mut font = Font::new(Box::new(provider))?;
-    let glyphs = font.map_glyphs(&opts.text, script, MatchingPresentation::NotRequired);
+    // Porcelain output has no diagnostics so it starts out as if the warning was given
+    let mut warned = opts.porcelain;
+    let mut map_text = |font: &mut Font<_>, text: &str| {
+        let mut glyphs = map_glyphs(font, text, script, sequences.as_ref());
+        if let Some(fallback) = opts.fallback_codepoint {
+            if substitute_unmapped(font, &mut glyphs, fallback, script).is_none() && !warned {
+                warned = true;
+                eprintln!(
+                    "warning: U+{:04X} is not mapped by the font, leaving .notdef in place",
+                    fallback as u32
+                );
+            }
+        }
+        glyphs
+    };
+
+    let context_before = opts.context_before.as_deref().unwrap_or("");
+    let context_after = opts.context_after.as_deref().unwrap_or("");
+    let has_context = !context_before.is_empty() || !context_after.is_empty();
+    let mut map_context = |font: &mut Font<_>, text: &str| ContextGlyphs {
+        before: map_text(font, context_before),
+        text: map_text(font, text),
+        after: map_text(font, context_after),
+    };
+
+    if opts.porcelain {
+        for text in &inputs {
+            let glyphs = map_context(&mut font, text);
+            let fields = porcelain_fields(
+                &mut font,
+                glyphs.run(),
+                script,
+                lang,
+                &features,
+                tuple.as_ref(),
+                opts.vertical,
+            );
+            match fields {
+                Ok((infos, fields)) => {
+                    let shown = if has_context {
+                        glyph_range(&infos, &glyphs.text_chars())
+                    } else {
+                        0..fields.len()
+                    };
+                    println!("{}", fields[shown].join("\t"))
+                }
+                Err(err) => println!(
+                    "ERROR {}",
+                    err.to_string().replace(|c: char| c.is_control(), " ")
+                ),
+            }
+        }
+        return Ok(0);
+    }
+
+    if let Some(expected) = opts.expect_glyphs {
+        let mut all_match = true;
+        for text in &inputs {
+            let glyphs = map_context(&mut font, text);
+            let infos = font
+                .shape(
+                    glyphs.run(),
+                    script,
+                    Some(lang),
+                    &features,
+                    tuple.as_ref().map(OwnedTuple::as_tuple),
+                    true,
+                )
+                .map_err(|(err, _infos)| err)?;
+            let count = if has_context {
+                glyph_range(&infos, &glyphs.text_chars()).len()
+            } else {
+                infos.len()
+            };
+            if count != expected {
+                eprintln!(
+                    "expected {} glyphs but shaping '{}' gave {}",
+                    expected, text, count
+                );
+                all_match = false;
+            }
+        }
+        return Ok(if all_match { 0 } else { 1 });
+    }
+
+    if let Some(iterations) = opts.bench {
+        let glyphs = map_context(&mut font, &inputs[0]).run();
+        bench(
+            &mut font,
+            &glyphs,
+            script,
+            lang,
+            &features,
+            tuple.as_ref(),
+            iterations,
+        )?;
+        return Ok(0);
+    }
+
+    for (i, text) in inputs.iter().enumerate() {
+        if plain_option.is_none() {
+            if (opts.tests.is_some() || opts.text_file.is_some()) && i > 0 {
+                println!();
+            }
+            if opts.tests.is_some() {
+                println!("text: {}", text);
+            } else if opts.text_file.is_some() {
+                println!("line {}: {}", i + 1, text);
+            }
+        }
+        let glyphs = map_context(&mut font, text);
+        let params = ShapingParams {
+            script,
+            lang,
+            features: &features,
+            tuple: tuple.as_ref(),
+            instance: instance.as_deref(),
+        };
+        if opts.feature_stats {
+            print_feature_stats(&mut font, glyphs.run(), &params, opts.vertical)?;
+        }
+        let shown = has_context.then(|| glyphs.text_chars());
+        print_shaped(&opts, &font_file, &mut font, glyphs.run(), shown, &params)?;
+        if has_context {
+            print_break_safety(&mut font, &glyphs, &params, opts.vertical)?;
+        }
+    }
+
+    Ok(0)
+}
+
+/// Read the text of `--text-file`, from stdin if `path` is `-`
+fn read_text_file(path: &str) -> Result<String, BoxError> {
+    if path == "-" {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        std::fs::read_to_string(path)
+            .map_err(|err| format!("unable to read {}: {}", path, err).into())
+    }
+}
+
+/// Map `text` to glyphs, resolving characters followed by a variation selector (VS1 to VS256)
+/// through the format 14 `cmap` subtable.
+///
+/// Sequences that use the default glyph are mapped without the selector, and the rest of the
+/// text, including sequences the font doesn't have, is mapped by allsorts.
+fn map_glyphs<T: FontTableProvider>(
+    font: &mut Font<T>,
+    text: &str,
+    script: u32,
+    sequences: Option<&VariationSequences>,
+) -> Vec<RawGlyph<()>> {
+    let Some(sequences) = sequences else {
+        return font.map_glyphs(text, script, MatchingPresentation::NotRequired);
+    };
+    let mut glyphs = Vec::new();
+    // Start of the text that hasn't been mapped yet
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, ch)) = chars.next() {
+        let Some(&(selector_index, selector)) = chars.peek() else {
+            break;
+        };
+        if !is_variation_selector(selector) {
+            continue;
+        }
+        let glyph = match sequences.lookup(ch, selector) {
+            Some(VariationGlyph::Glyph(glyph_id)) => glyph::make(ch, glyph_id, None),
+            Some(VariationGlyph::Default) => {
+                let mut buf = [0; 4];
+                let mut base = font.map_glyphs(
+                    ch.encode_utf8(&mut buf),
+                    script,
+                    MatchingPresentation::NotRequired,
+                );
+                match base.pop() {
+                    Some(glyph) => glyph,
+                    None => continue,
+                }
+            }
+            None => continue,
+        };
+        glyphs.extend(font.map_glyphs(
+            &text[start..index],
+            script,
+            MatchingPresentation::NotRequired,
+        ));
+        glyphs.push(glyph);
+        chars.next();
+        start = selector_index + selector.len_utf8();
+    }
+    glyphs.extend(font.map_glyphs(&text[start..], script, MatchingPresentation::NotRequired));
+    glyphs
+}
+
+/// The glyphs of a text and of the context it's shaped in, mapped separately
+struct ContextGlyphs {
+    before: Vec<RawGlyph<()>>,
+    text: Vec<RawGlyph<()>>,
+    after: Vec<RawGlyph<()>>,
+}
+
+impl ContextGlyphs {
+    /// The glyphs of the context and text, in order
+    fn run(&self) -> Vec<RawGlyph<()>> {
+        [&self.before[..], &self.text, &self.after].concat()
+    }
+
+    /// The characters of the text within the run
+    fn text_chars(&self) -> Range<usize> {
+        let start = char_count(&self.before);
+        start..start + char_count(&self.text)
+    }
+}
+
+/// The number of characters `glyphs` were mapped from
+fn char_count(glyphs: &[RawGlyph<()>]) -> usize {
+    glyphs.iter().map(|glyph| glyph.unicodes.len()).sum()
+}
+
+/// The range of `infos` shaped from the characters `chars` of the run.
+///
+/// Characters are attributed to glyphs through the unicodes of each glyph, so glyphs that span
+/// the start or end of `chars`, such as a ligature formed with the context, are included.
+fn glyph_range(infos: &[Info], chars: &Range<usize>) -> Range<usize> {
+    let mut range: Option<Range<usize>> = None;
+    for (i, glyph_chars) in glyph_chars(infos).into_iter().enumerate() {
+        let in_chars = if glyph_chars.is_empty() {
+            chars.contains(&glyph_chars.start)
+        } else {
+            glyph_chars.start < chars.end && chars.start < glyph_chars.end
+        };
+        if in_chars {
+            range = Some(range.map_or(i..i + 1, |range| range.start..i + 1));
+        }
+    }
+    range.unwrap_or(infos.len()..infos.len())
+}
+
+/// The range of characters of the run that each of `infos` was shaped from
+fn glyph_chars(infos: &[Info]) -> Vec<Range<usize>> {
+    let mut next_char = 0;
+    let mut glyph_chars = 0..0;
+    infos
+        .iter()
+        .map(|info| {
+            // The copies made by multiple substitution share the characters of the first glyph
+            if !info.glyph.multi_subst_dup() {
+                glyph_chars = next_char..next_char + info.glyph.unicodes.len();
+                next_char = glyph_chars.end;
+            }
+            glyph_chars.clone()
+        })
+        .collect()
+}
+
+/// Print whether the run could be broken at the start and end of the text without changing how
+/// the text is shaped.
+///
+/// The text shaped alone is compared with it shaped after only the context before it, and
+/// before only the context after it.
+fn print_break_safety<F: FontTableProvider>(
+    font: &mut Font<F>,
+    glyphs: &ContextGlyphs,
+    params: &ShapingParams<'_>,
+    vertical: bool,
+) -> Result<(), BoxError> {
+    let ShapingParams {
+        script,
+        lang,
+        features,
+        tuple,
+        ..
+    } = *params;
+    let mut shape_text = |run: Vec<RawGlyph<()>>, chars: Range<usize>| {
+        porcelain_fields(font, run, script, lang, features, tuple, vertical)
+            .map(|(infos, fields)| fields[glyph_range(&infos, &chars)].to_vec())
+    };
+    let text_chars = 0..char_count(&glyphs.text);
+    let alone = shape_text(glyphs.text.clone(), text_chars.clone())?;
+    let boundaries = [
+        (
+            "start",
+            &glyphs.before,
+            [&glyphs.before[..], &glyphs.text].concat(),
+        ),
+        (
+            "end",
+            &glyphs.after,
+            [&glyphs.text[..], &glyphs.after].concat(),
+        ),
+    ];
+    for (boundary, context, run) in boundaries {
+        if context.is_empty() {
+            continue;
+        }
+        let chars = if boundary == "start" {
+            let start = char_count(&glyphs.before);
+            start..start + text_chars.end
+        } else {
+            text_chars.clone()
+        };
+        let changed = count_changed(&alone, &shape_text(run, chars)?);
+        if changed == 0 {
+            println!("break at {} of text: safe", boundary);
+        } else {
+            println!(
+                "break at {} of text: unsafe, the context changes {} glyph(s)",
+                boundary, changed
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// The number of fields that differ between `a` and `b`, counting any extra fields as changed
+fn count_changed(a: &[String], b: &[String]) -> usize {
+    a.iter().zip(b).filter(|(a, b)| a != b).count() + a.len().abs_diff(b.len())
+}
+
+/// The settings shared by each text that is shaped
+struct ShapingParams<'a> {
+    script: u32,
+    lang: u32,
+    features: &'a Features,
+    tuple: Option<&'a OwnedTuple>,
+    /// The font instanced at the tuple, for calculating extents
+    instance: Option<&'a [u8]>,
+}
+
+/// Shape `glyphs` and print the glyphs with their positions.
+///
+/// When `shown` is given only the glyphs of those characters of the run are printed, with
+/// positions and extents relative to the first of them.
+fn print_shaped<F: FontTableProvider>(
+    opts: &ShapeOpts,
+    font_file: &FontData<'_>,
+    font: &mut Font<F>,
+    glyphs: Vec<RawGlyph<()>>,
+    shown: Option<Range<usize>>,
+    params: &ShapingParams<'_>,
+) -> Result<(), BoxError> {
+    let ShapingParams {
+        script,
+        lang,
+        features,
+        tuple,
+        instance,
+    } = *params;
+    let mapped_glyphs = (opts.stage_svgs.is_some() || opts.trace).then(|| glyphs.clone());
     let infos = font
         .shape(
             glyphs,
             script,
             Some(lang),
-            &Features::Mask(FeatureMask::default()),
-            tuple.as_ref().map(OwnedTuple::as_tuple),
+            features,
+            tuple.map(OwnedTuple::as_tuple),
             true,
         )
         .map_err(|(err, _infos)| err)?;
-    let mut layout = GlyphLayout::new(&mut font, &infos, TextDirection::LeftToRight, opts.vertical);
+    if let (Some(dir), Some(mapped_glyphs)) = (&opts.stage_svgs, &mapped_glyphs) {
+        let provider = font_file.table_provider(opts.index)?;
+        let direction = script::direction_or(script, opts.direction);
+        write_stage_svgs(
+            dir,
+            font,
+            &provider,
+            mapped_glyphs.clone(),
+            &infos,
+            direction,
+            params,
+        )?;
+    }
+
+    // The advances from hmtx and vmtx, whichever direction the layout is in
+    let advances = if opts.both_advances {
+        infos
+            .iter()
+            .map(|info| {
+                let glyph_index = info.glyph.glyph_index;
+                (
+                    font.horizontal_advance(glyph_index),
+                    font.vertical_advance(glyph_index),
+                )
+            })
+            .collect::<Vec<_>>()
+    } else {
+        Vec::new()
+    };
+
+    let mut layout = GlyphLayout::new(font, &infos, TextDirection::LeftToRight, opts.vertical);
     let positions = layout.glyph_positions()?;
 
-    for (glyph, position) in infos.iter().zip(&positions) {
+    if opts.json {
+        let provider = font_file.table_provider(opts.index)?;
+        let glyph_ids = infos
+            .iter()
+            .map(|info| info.glyph.glyph_index)
+            .collect::<Vec<_>>();
+        let names = glyph_names(&provider, &glyph_ids)?;
+        let glyphs = infos
+            .iter()
+            .zip(&positions)
+            .zip(names.into_iter().zip(glyph_chars(&infos)))
+            .map(|((info, position), (glyph_name, chars))| JsonGlyph {
+                glyph_index: info.glyph.glyph_index,
+                glyph_name,
+                cluster: chars.start,
+                unicodes: info
+                    .glyph
+                    .unicodes
+                    .iter()
+                    .map(|&ch| format!("U+{:04X}", u32::from(ch)))
+                    .collect(),
+                hori_advance: position.hori_advance,
+                vert_advance: position.vert_advance,
+                x_offset: position.x_offset,
+                y_offset: position.y_offset,
+                small_caps: info.glyph.small_caps(),
+                multi_subst_dup: info.glyph.multi_subst_dup(),
+                is_vert_alt: info.glyph.is_vert_alt(),
+                fake_bold: info.glyph.fake_bold(),
+                fake_italic: info.glyph.fake_italic(),
+            })
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string(&glyphs)?);
+        return Ok(());
+    }
+
+    if opts.format == Some(ShapeFormat::Hb) {
+        println!("{}", hb_line(&infos, &positions));
+        return Ok(());
+    }
+
+    if let Some(convention) = opts.convention {
+        // Offsets of right-to-left glyphs, like marks, depend on the direction of the layout
+        let direction = script::direction_or(script, opts.direction);
+        let positions = GlyphLayout::new(font, &infos, direction, false).glyph_positions()?;
+        println!(
+            "{}",
+            convention_line(convention, direction, &infos, &positions)
+        );
+        return Ok(());
+    }
+
+    let glyph_extents = if opts.extents {
+        let glyph_ids = infos
+            .iter()
+            .map(|info| info.glyph.glyph_index)
+            .collect::<Vec<_>>();
+        match instance {
+            Some(instance) => {
+                let instance_file = ReadScope::new(instance).read::<FontData<'_>>()?;
+                extents::glyph_extents(&instance_file.table_provider(0)?, &glyph_ids)?
+            }
+            None => extents::glyph_extents(&font_file.table_provider(opts.index)?, &glyph_ids)?,
+        }
+    } else {
+        Vec::new()
+    };
+
+    let vertical_metrics = if opts.vertical {
+        let glyph_ids = infos
+            .iter()
+            .map(|info| info.glyph.glyph_index)
+            .collect::<Vec<_>>();
+        vertical_metrics::vertical_metrics(&font_file.table_provider(opts.index)?, &glyph_ids)?
+    } else {
+        Vec::new()
+    };
+
+    let shown = match shown {
+        Some(chars) => glyph_range(&infos, &chars),
+        None => 0..infos.len(),
+    };
+    let (mut x, mut y) = (0, 0);
+    let mut run_extents: Option<Extents> = None;
+    for (i, (glyph, position)) in infos.iter().zip(&positions).enumerate() {
+        if !shown.contains(&i) {
+            continue;
+        }
+        let glyph_extents = glyph_extents.get(i).copied().flatten().map(|extents| {
+            extents.translate(
+                (x + position.x_offset) as f32,
+                (y + position.y_offset) as f32,
+            )
+        });
+        let extents_str = match glyph_extents {
+            Some(extents) => format!(" extents: {}", extents),
+            None if opts.extents => String::from(" extents: none"),
+            None => String::new(),
+        };
+        let advances_str = match advances.get(i) {
+            Some((hori_advance, vert_advance)) => format!(
+                " advances: {},{}",
+                display_advance(*hori_advance),
+                display_advance(*vert_advance)
+            ),
+            None => String::new(),
+        };
+        let vertical_str = match vertical_metrics.get(i) {
+            Some(VerticalMetrics {
+                advance,
+                top_side_bearing,
+                origin_y,
+            }) => format!(
+                " vertical: advance {}, origin {}, tsb {}",
+                advance, origin_y, top_side_bearing
+            ),
+            None => String::new(),
+        };
         println!(
-            "{},{} ({}, {}) {:#?}",
+            "{},{} ({}, {}){}{}{}{} {:#?}",
             position.hori_advance,
             position.vert_advance,
             position.x_offset,
             position.y_offset,
+            synthesis_flags(glyph),
+            advances_str,
+            vertical_str,
+            extents_str,
             glyph
         );
+
+        if let Some(extents) = glyph_extents {
+            run_extents = Some(run_extents.map_or(extents, |run| run.union(extents)));
+        }
+        x += position.hori_advance;
+        y += position.vert_advance;
     }
 
-    Ok(0)
+    if opts.extents {
+        match run_extents {
+            Some(extents) => println!("run extents: {}", extents),
+            None => println!("run extents: none"),
+        }
+    }
+
+    if let (true, Some(mapped_glyphs)) = (opts.trace, &mapped_glyphs) {
+        print_trace(font, mapped_glyphs, &infos, &positions, opts.vertical);
+    }
+
+    Ok(())
+}
+
+/// Print how shaping changed the glyphs mapped from the characters of the run.
+///
+/// allsorts doesn't report the lookups it applies, so the glyphs before and after shaping are
+/// compared instead. Each cluster of characters whose glyphs were substituted is listed with the
+/// glyphs before and after, then each glyph that was moved from its origin or given an advance
+/// other than its hmtx advance, as GPOS does.
+fn print_trace<F: FontTableProvider>(
+    font: &mut Font<F>,
+    mapped_glyphs: &[RawGlyph<()>],
+    infos: &[Info],
+    positions: &[GlyphPosition],
+    vertical: bool,
+) {
+    let chars = mapped_glyphs
+        .iter()
+        .flat_map(|glyph| glyph.unicodes.iter().copied())
+        .collect::<Vec<char>>();
+    // The first character each mapped glyph came from, characters followed by a variation
+    // selector map to one glyph
+    let mut mapped = Vec::new();
+    let mut next_char = 0;
+    for glyph in mapped_glyphs {
+        mapped.push((next_char, glyph.glyph_index));
+        next_char += glyph.unicodes.len();
+    }
+    let mapped_in = |range: &Range<usize>| {
+        mapped
+            .iter()
+            .filter(|(first_char, _)| range.contains(first_char))
+            .map(|&(_, glyph_index)| glyph_index)
+            .collect::<Vec<_>>()
+    };
+    let format_ids = |glyph_ids: &[u16]| {
+        if glyph_ids.is_empty() {
+            return String::from("-");
+        }
+        glyph_ids
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    println!("substitutions:");
+    let glyph_chars = traced_glyph_chars(&chars, infos);
+    let mut covered = vec![false; chars.len()];
+    let mut changes = 0;
+    let mut i = 0;
+    while i < infos.len() {
+        // Copies made by multiple substitution share the characters of the first glyph
+        let range = glyph_chars[i].clone();
+        let mut end = i + 1;
+        while end < infos.len() && infos[end].glyph.multi_subst_dup() {
+            end += 1;
+        }
+        let before = mapped_in(&range);
+        let after = infos[i..end]
+            .iter()
+            .map(|info| info.glyph.glyph_index)
+            .collect::<Vec<_>>();
+        for covered in covered.iter_mut().take(range.end).skip(range.start) {
+            *covered = true;
+        }
+        i = end;
+        if before == after {
+            continue;
+        }
+        let kind = match (before.len(), after.len()) {
+            (0, _) => "inserted",
+            (1, 1) => "single",
+            (_, 1) => "ligature",
+            (1, _) => "multiple",
+            _ => "contextual",
+        };
+        let codepoints = chars[range]
+            .iter()
+            .map(|&ch| format!("U+{:04X}", u32::from(ch)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!(
+            " - {}: {} -> {} ({})",
+            if codepoints.is_empty() {
+                "-"
+            } else {
+                codepoints.as_str()
+            },
+            format_ids(&before),
+            format_ids(&after),
+            kind
+        );
+        changes += 1;
+    }
+    for (first_char, glyph_index) in &mapped {
+        if !covered.get(*first_char).copied().unwrap_or(true) {
+            println!(
+                " - U+{:04X}: {} -> (deleted)",
+                u32::from(chars[*first_char]),
+                glyph_index
+            );
+            changes += 1;
+        }
+    }
+    if changes == 0 {
+        println!(" none");
+    }
+
+    println!("positioning:");
+    let mut changes = 0;
+    for (i, (info, position)) in infos.iter().zip(positions).enumerate() {
+        let glyph_index = info.glyph.glyph_index;
+        // Vertical advances are laid out from vmtx, only horizontal ones are compared
+        let hmtx_advance = font.horizontal_advance(glyph_index).map_or(0, i32::from);
+        let advance_changed = !vertical && position.hori_advance != hmtx_advance;
+        if position.x_offset == 0 && position.y_offset == 0 && !advance_changed {
+            continue;
+        }
+        let advance = if advance_changed {
+            format!(
+                ", advance {} (hmtx {})",
+                position.hori_advance, hmtx_advance
+            )
+        } else {
+            String::new()
+        };
+        println!(
+            " - glyph {} ({}): offset {},{}{}",
+            i, glyph_index, position.x_offset, position.y_offset, advance
+        );
+        changes += 1;
+    }
+    if changes == 0 {
+        println!(" none");
+    }
+}
+
+/// The range of `chars` that each of `infos` was shaped from, found by matching the characters of
+/// each glyph against those not yet taken by an earlier glyph.
+///
+/// Unlike `glyph_chars` this copes with glyphs that shaping inserted, like dotted circles, whose
+/// characters aren't in `chars` and get an empty range, with deleted glyphs, whose characters
+/// aren't taken by any glyph, and with reordered glyphs.
+fn traced_glyph_chars(chars: &[char], infos: &[Info]) -> Vec<Range<usize>> {
+    let mut taken = vec![false; chars.len()];
+    let mut next_char = 0;
+    let mut glyph_chars = 0..0;
+    infos
+        .iter()
+        .map(|info| {
+            // The copies made by multiple substitution share the characters of the first glyph
+            if info.glyph.multi_subst_dup() {
+                return glyph_chars.clone();
+            }
+            let unicodes = &info.glyph.unicodes;
+            let len = unicodes.len();
+            let start = match len {
+                0 => None,
+                _ => chars
+                    .windows(len)
+                    .zip(taken.windows(len))
+                    .position(|(window, taken)| window == &unicodes[..] && !taken.contains(&true)),
+            };
+            glyph_chars = match start {
+                Some(start) => {
+                    taken[start..start + len].fill(true);
+                    next_char = start + len;
+                    start..start + len
+                }
+                None => next_char..next_char,
+            };
+            glyph_chars.clone()
+        })
+        .collect()
+}
+
+/// A shaped glyph as printed by `--json`
+#[derive(Serialize)]
+struct JsonGlyph {
+    glyph_index: u16,
+    glyph_name: String,
+    /// Index of the first character of the run the glyph was shaped from
+    cluster: usize,
+    unicodes: Vec<String>,
+    hori_advance: i32,
+    vert_advance: i32,
+    x_offset: i32,
+    y_offset: i32,
+    small_caps: bool,
+    multi_subst_dup: bool,
+    is_vert_alt: bool,
+    fake_bold: bool,
+    fake_italic: bool,
+}
+
+/// Format the glyphs like `hb-shape --no-glyph-names`, as `[gid=cluster@x_offset,y_offset+advance|...]`.
+///
+/// The offsets are left out when both are zero and the vertical advance is only given when it's
+/// non-zero, as hb-shape does. The cluster is the index of the first character the glyph came
+/// from, which matches the byte offset hb-shape prints only for ASCII text.
+fn hb_line(infos: &[Info], positions: &[GlyphPosition]) -> String {
+    let glyphs = infos
+        .iter()
+        .zip(positions)
+        .zip(glyph_chars(infos))
+        .map(|((info, position), chars)| {
+            let mut glyph = format!("{}={}", info.glyph.glyph_index, chars.start);
+            if position.x_offset != 0 || position.y_offset != 0 {
+                glyph.push_str(&format!("@{},{}", position.x_offset, position.y_offset));
+            }
+            glyph.push_str(&format!("+{}", position.hori_advance));
+            if position.vert_advance != 0 {
+                glyph.push_str(&format!(",{}", position.vert_advance));
+            }
+            glyph
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", glyphs.join("|"))
+}
+
+/// Format the glyphs as tab-separated fields in the coordinate convention of a platform API.
+///
+/// Both APIs use font units here with y up, as allsorts does. CoreText fields are
+/// `glyph_index@x,y+advance_width,advance_height`, where `x,y` is the pen position plus the offset
+/// of the glyph, and right-to-left glyphs are listed in visual order as CoreText stores them.
+/// DirectWrite fields are `glyph_index@advance_offset,ascender_offset+advance`, the offset
+/// relative to the pen, in logical order with the advance offset in the reading direction.
+///
+/// `positions` are laid out in `direction`.
+fn convention_line(
+    convention: Convention,
+    direction: TextDirection,
+    infos: &[Info],
+    positions: &[GlyphPosition],
+) -> String {
+    let rtl = matches!(direction, TextDirection::RightToLeft);
+    let glyphs = infos
+        .iter()
+        .map(|info| info.glyph.glyph_index)
+        .zip(positions.iter().copied());
+    let fields = match convention {
+        Convention::CoreText => {
+            // Right-to-left positions are laid out from the left edge in visual order
+            let glyphs: Vec<_> = if rtl {
+                glyphs.rev().collect()
+            } else {
+                glyphs.collect()
+            };
+            let (mut x, mut y) = (0, 0);
+            let mut fields = Vec::new();
+            for (glyph_index, position) in glyphs {
+                fields.push(format!(
+                    "{}@{},{}+{},{}",
+                    glyph_index,
+                    x + position.x_offset,
+                    y + position.y_offset,
+                    position.hori_advance,
+                    position.vert_advance
+                ));
+                x += position.hori_advance;
+                y += position.vert_advance;
+            }
+            fields
+        }
+        Convention::DirectWrite => glyphs
+            .map(|(glyph_index, position)| {
+                let advance_offset = if rtl {
+                    -position.x_offset
+                } else {
+                    position.x_offset
+                };
+                format!(
+                    "{}@{},{}+{}",
+                    glyph_index, advance_offset, position.y_offset, position.hori_advance
+                )
+            })
+            .collect(),
+    };
+    fields.join("\t")
+}
+
+/// Shape `glyphs`, returning the shaped glyphs and the porcelain field of each
+fn porcelain_fields<F: FontTableProvider>(
+    font: &mut Font<F>,
+    glyphs: Vec<RawGlyph<()>>,
+    script: u32,
+    lang: u32,
+    features: &Features,
+    tuple: Option<&OwnedTuple>,
+    vertical: bool,
+) -> Result<(Vec<Info>, Vec<String>), BoxError> {
+    let tuple = tuple.map(OwnedTuple::as_tuple);
+    let infos = font
+        .shape(glyphs, script, Some(lang), features, tuple, true)
+        .map_err(|(err, _infos)| err)?;
+    let mut layout = GlyphLayout::new(font, &infos, TextDirection::LeftToRight, vertical);
+    let positions = layout.glyph_positions()?;
+    let fields = infos
+        .iter()
+        .zip(&positions)
+        .map(|(info, position)| {
+            format!(
+                "{}@{},{}+{},{}",
+                info.glyph.glyph_index,
+                position.x_offset,
+                position.y_offset,
+                position.hori_advance,
+                position.vert_advance
+            )
+        })
+        .collect::<Vec<_>>();
+    Ok((infos, fields))
+}
+
+/// Print the number of clusters that each enabled feature of the script and language changes,
+/// followed by the number that each of its GSUB lookups changes.
+///
+/// allsorts doesn't report which lookups were applied, so the text is shaped again without each
+/// feature and the glyph indices and positions of each cluster are compared with the text shaped
+/// with all of them. Each GSUB lookup is applied on its own to the glyphs mapped from the text.
+/// Features the shaper always applies for the script count as changing nothing.
+fn print_feature_stats<F: FontTableProvider>(
+    font: &mut Font<F>,
+    glyphs: Vec<RawGlyph<()>>,
+    params: &ShapingParams<'_>,
+    vertical: bool,
+) -> Result<(), BoxError> {
+    let ShapingParams {
+        script,
+        lang,
+        features,
+        tuple,
+        ..
+    } = *params;
+    let gsub_cache = font.gsub_cache()?;
+    let gdef_table = font.gdef_table()?;
+    let gsub_features = match &gsub_cache {
+        Some(gsub_cache) => langsys_features(&gsub_cache.layout_table, script, lang)?,
+        None => BTreeMap::new(),
+    };
+    let gpos_features = match font.gpos_cache()? {
+        Some(gpos_cache) => langsys_features(&gpos_cache.layout_table, script, lang)?,
+        None => BTreeMap::new(),
+    };
+    let feature_tags = gsub_features
+        .keys()
+        .chain(gpos_features.keys())
+        .copied()
+        .collect::<BTreeSet<_>>();
+
+    let (infos, fields) = porcelain_fields(
+        font,
+        glyphs.clone(),
+        script,
+        lang,
+        features,
+        tuple,
+        vertical,
+    )?;
+    let shaped = cluster_fields(&infos, fields);
+    let mapped_infos = Info::init_from_glyphs(gdef_table.as_deref(), glyphs.clone());
+    let mapped = cluster_fields(&mapped_infos, glyph_index_fields(&mapped_infos));
+    println!("feature stats (clusters changed by disabling the feature):");
+    for feature_tag in feature_tags {
+        let without = match features {
+            Features::Mask(mask) => {
+                let feature = FeatureMask::from_tag(feature_tag);
+                if feature.is_empty() || !mask.contains(feature) {
+                    continue;
+                }
+                Features::Mask(*mask - feature)
+            }
+            Features::Custom(feature_infos) => {
+                if !feature_infos
+                    .iter()
+                    .any(|info| info.feature_tag == feature_tag)
+                {
+                    continue;
+                }
+                Features::Custom(
+                    feature_infos
+                        .iter()
+                        .filter(|info| info.feature_tag != feature_tag)
+                        .cloned()
+                        .collect(),
+                )
+            }
+        };
+        let (infos, fields) = porcelain_fields(
+            font,
+            glyphs.clone(),
+            script,
+            lang,
+            &without,
+            tuple,
+            vertical,
+        )?;
+        let changed = changed_clusters(&shaped, &cluster_fields(&infos, fields));
+        println!(" {}: {}", DisplayTag(feature_tag), changed);
+
+        let (gsub_cache, lookup_indices) = match (&gsub_cache, gsub_features.get(&feature_tag)) {
+            (Some(gsub_cache), Some(lookup_indices)) => (gsub_cache, lookup_indices),
+            _ => continue,
+        };
+        for &lookup_index in lookup_indices {
+            let mut lookup_glyphs = glyphs.clone();
+            let length = lookup_glyphs.len();
+            gsub::gsub_apply_lookup(
+                gsub_cache,
+                &gsub_cache.layout_table,
+                gdef_table.as_deref(),
+                usize::from(lookup_index),
+                feature_tag,
+                None,
+                &mut lookup_glyphs,
+                0,
+                length,
+                |_| true,
+            )?;
+            let infos = Info::init_from_glyphs(gdef_table.as_deref(), lookup_glyphs);
+            let changed =
+                changed_clusters(&mapped, &cluster_fields(&infos, glyph_index_fields(&infos)));
+            println!("  - GSUB lookup {}: {}", lookup_index, changed);
+        }
+    }
+    println!();
+
+    Ok(())
+}
+
+/// The glyph index of each of `infos`, to compare substitutions with `changed_clusters`
+fn glyph_index_fields(infos: &[Info]) -> Vec<String> {
+    infos
+        .iter()
+        .map(|info| info.glyph.glyph_index.to_string())
+        .collect()
+}
+
+/// The fields of the glyphs of each cluster, keyed by the first character of the cluster
+fn cluster_fields(infos: &[Info], fields: Vec<String>) -> BTreeMap<usize, Vec<String>> {
+    let mut clusters = BTreeMap::new();
+    for (chars, field) in glyph_chars(infos).into_iter().zip(fields) {
+        clusters
+            .entry(chars.start)
+            .or_insert_with(Vec::new)
+            .push(field);
+    }
+    clusters
+}
+
+/// The number of clusters whose fields differ between `a` and `b`, including the clusters that
+/// only one of them has, such as the characters joined by a ligature
+fn changed_clusters(a: &BTreeMap<usize, Vec<String>>, b: &BTreeMap<usize, Vec<String>>) -> usize {
+    a.keys()
+        .chain(b.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|cluster| a.get(cluster) != b.get(cluster))
+        .count()
+}
+
+/// The tag and lookup indices of each of the features of the language system for `script` and
+/// `lang`, falling back on the default script and language system
+fn langsys_features<T>(
+    layout_table: &LayoutTable<T>,
+    script: u32,
+    lang: u32,
+) -> Result<BTreeMap<u32, Vec<u16>>, BoxError> {
+    let mut features = BTreeMap::new();
+    let script_list = match &layout_table.opt_script_list {
+        Some(script_list) => script_list,
+        None => return Ok(features),
+    };
+    let script_table = match script_list
+        .script_records()
+        .find(|record| record.script_tag == script)
+        .or_else(|| {
+            script_list
+                .script_records()
+                .find(|record| record.script_tag == tag::DFLT)
+        }) {
+        Some(script_record) => script_record.script_table(),
+        None => return Ok(features),
+    };
+    let langsys = match script_table
+        .langsys_records()
+        .find(|record| record.langsys_tag == lang)
+    {
+        Some(langsys_record) => langsys_record.langsys_table(),
+        None => match script_table.default_langsys_record() {
+            Some(default_langsys) => default_langsys,
+            None => return Ok(features),
+        },
+    };
+    for feature_index in langsys.feature_indices_iter() {
+        let feature_record = layout_table.feature_by_index(*feature_index)?;
+        features
+            .entry(feature_record.feature_tag)
+            .or_insert_with(Vec::new)
+            .extend(&feature_record.feature_table.lookup_indices);
+    }
+    Ok(features)
+}
+
+/// Shape `glyphs` `iterations` times and print timing statistics.
+///
+/// The glyphs are shaped once before timing starts so that the layout caches are populated.
+fn bench<F: FontTableProvider>(
+    font: &mut Font<F>,
+    glyphs: &[RawGlyph<()>],
+    script: u32,
+    lang: u32,
+    features: &Features,
+    tuple: Option<&OwnedTuple>,
+    iterations: u32,
+) -> Result<(), BoxError> {
+    if iterations == 0 {
+        return Err(ErrorMessage("--bench requires at least one iteration").into());
+    }
+    let mut shape = || {
+        let tuple = tuple.map(OwnedTuple::as_tuple);
+        font.shape(glyphs.to_vec(), script, Some(lang), features, tuple, true)
+            .map_err(|(err, _infos)| err)
+    };
+    let num_glyphs = shape()?.len();
+
+    let mut min = Duration::MAX;
+    let mut max = Duration::ZERO;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let iteration_start = Instant::now();
+        shape()?;
+        let elapsed = iteration_start.elapsed();
+        min = min.min(elapsed);
+        max = max.max(elapsed);
+    }
+    let total = start.elapsed();
+
+    let mean = total / iterations;
+    let glyphs_per_second = (num_glyphs as f64 * f64::from(iterations)) / total.as_secs_f64();
+    println!("shaped {} times in {:?}", iterations, total);
+    println!(
+        "per iteration: mean {:?}, min {:?}, max {:?}",
+        mean, min, max
+    );
+    println!(
+        "{} glyphs per iteration, {:.0} glyphs per second",
+        num_glyphs, glyphs_per_second
+    );
+
+    Ok(())
+}
+
+/// Print the GSUB and GPOS feature variation condition sets that are active at `tuple`
+fn print_active_feature_variations(
+    provider: &impl FontTableProvider,
+    tuple: &OwnedTuple,
+) -> Result<(), BoxError> {
+    let fvar_data = provider.read_table_data(tag::FVAR)?;
+    let fvar = ReadScope::new(&fvar_data).read::<FvarTable>()?;
+    let axis_tags = fvar.axes().map(|axis| axis.axis_tag).collect::<Vec<_>>();
+    let coords = tuple.as_tuple().iter().map(f32::from).collect::<Vec<_>>();
+    let location = axis_tags
+        .iter()
+        .zip(&coords)
+        .map(|(&axis_tag, value)| format!("{}={}", DisplayTag(axis_tag), value))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    for table_tag in [tag::GSUB, tag::GPOS] {
+        let Some(data) = provider.table_data(table_tag)? else {
+            continue;
+        };
+        let Some(variations) = FeatureVariations::read_layout_table(&data)? else {
+            continue;
+        };
+        match variations.active_record(&coords) {
+            Some(index) => {
+                let record = &variations.records[index];
+                let conditions = record
+                    .conditions
+                    .iter()
+                    .flatten()
+                    .map(|condition| condition.display(&axis_tags).to_string())
+                    .collect::<Vec<_>>();
+                let features = record
+                    .feature_tags
+                    .iter()
+                    .map(|&feature_tag| DisplayTag(feature_tag).to_string())
+                    .collect::<Vec<_>>();
+                println!(
+                    "{} feature variations at {}: condition set {} active ({}), substituting {}",
+                    DisplayTag(table_tag),
+                    location,
+                    index,
+                    if conditions.is_empty() {
+                        String::from("no conditions")
+                    } else {
+                        conditions.join(", ")
+                    },
+                    features.join(", ")
+                );
+            }
+            None => println!(
+                "{} feature variations at {}: no condition set active",
+                DisplayTag(table_tag),
+                location
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Write an SVG of the glyphs after each shaping stage: cmap mapping, GSUB, and GPOS
+fn write_stage_svgs<F: FontTableProvider>(
+    dir: &str,
+    font: &mut Font<F>,
+    provider: &(impl FontTableProvider + SfntVersion),
+    mapped_glyphs: Vec<RawGlyph<()>>,
+    infos: &[Info],
+    direction: TextDirection,
+    params: &ShapingParams<'_>,
+) -> Result<(), BoxError> {
+    let ShapingParams {
+        script,
+        lang,
+        features,
+        tuple,
+        ..
+    } = *params;
+    // The mapped glyphs are substituted again without positioning them, the same way
+    // `Font::shape` applies GSUB
+    let mut substituted_glyphs = mapped_glyphs.clone();
+    if let Some(gsub_cache) = font.gsub_cache()? {
+        let dotted_circle_index = font
+            .map_glyphs("\u{25CC}", script, MatchingPresentation::NotRequired)
+            .first()
+            .map_or(0, |glyph| glyph.glyph_index);
+        let gdef_table = font.gdef_table()?;
+        gsub::apply(
+            dotted_circle_index,
+            &gsub_cache,
+            gdef_table.as_deref(),
+            script,
+            Some(lang),
+            features,
+            tuple.map(OwnedTuple::as_tuple),
+            font.num_glyphs(),
+            &mut substituted_glyphs,
+        )?;
+    }
+    let mapped = Info::init_from_glyphs(None, mapped_glyphs);
+    let substituted = Info::init_from_glyphs(font.gdef_table()?.as_deref(), substituted_glyphs);
+    let stages = [
+        ("1-cmap", mapped.as_slice()),
+        ("2-gsub", substituted.as_slice()),
+        ("3-gpos", infos),
+    ];
+
+    let head = font.head_table()?.ok_or(ParseError::MissingValue)?;
+    let scale = view::FONT_SIZE / f32::from(head.units_per_em);
+    let transform = Matrix2x2F::from_scale(vec2f(scale, -scale));
+    std::fs::create_dir_all(dir)?;
+    for (stage, infos) in stages.iter() {
+        let mode = SVGMode::View {
+            mark_origin: false,
+            margin: Margin::default(),
+            trim_advance: false,
+            fg: None,
+            bg: None,
+            waterfall: None,
+            show_invisible: false,
+            tint_fallbacks: false,
+            legend: false,
+            show_cursive: false,
+            show_anchors: false,
+            show_points: false,
+            show_bboxes: false,
+            guides: false,
+            label_indices: false,
+            label_unicodes: false,
+            inline_paths: false,
+            precision: 2,
+            colour_by_cluster: false,
+            pixel_size: false,
+            canvas: None,
+        };
+        let mut writer = SVGWriter::new(mode, transform);
+        if !view::add_run(&mut writer, font, provider, infos, direction, 0, 1., tuple)? {
+            return Err(ErrorMessage("no glyf, CFF, or CFF2 table").into());
+        }
+        let path = Path::new(dir).join(format!("{}.svg", stage));
+        writer.warn_sanitised_ids();
+        std::fs::write(&path, writer.finish())?;
+        eprintln!("wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+fn display_advance(advance: Option<u16>) -> String {
+    advance.map_or_else(|| String::from("none"), |advance| advance.to_string())
+}
+
+/// Describe the styles the shaper synthesised for this glyph, if any
+fn synthesis_flags(info: &Info) -> String {
+    let mut flags = Vec::new();
+    if info.glyph.fake_bold() {
+        flags.push("fake-bold");
+    }
+    if info.glyph.fake_italic() {
+        flags.push("fake-italic");
+    }
+    if flags.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", flags.join(", "))
+    }
 }
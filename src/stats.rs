@@ -0,0 +1,194 @@
+use std::collections::{BTreeSet, HashSet};
+use std::convert::TryFrom;
+
+use allsorts::binary::read::ReadScope;
+use allsorts::font::{read_cmap_subtable, Font};
+use allsorts::font_data::FontData;
+use allsorts::layout::LayoutTable;
+use allsorts::tables::cmap::Cmap;
+use allsorts::tables::variable_fonts::fvar::FvarTable;
+use allsorts::tables::{FontTableProvider, HeadTable, MaxpTable, OffsetTable, OpenTypeData};
+use allsorts::tag::{self, DisplayTag};
+
+use crate::cli::StatsOpts;
+use crate::BoxError;
+
+const COLOUR_TABLES: [u32; 4] = [
+    allsorts::tag!(b"COLR"),
+    allsorts::tag!(b"sbix"),
+    allsorts::tag!(b"CBDT"),
+    allsorts::tag!(b"SVG "),
+];
+
+pub fn main(opts: StatsOpts) -> Result<i32, BoxError> {
+    let buffer = std::fs::read(&opts.font)?;
+    let scope = ReadScope::new(&buffer);
+    let font_file = scope.read::<FontData>()?;
+    let provider = font_file.table_provider(opts.index)?;
+
+    let head = ReadScope::new(&provider.read_table_data(tag::HEAD)?).read::<HeadTable>()?;
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+
+    println!("Format:          {}", format_name(&font_file));
+    println!("Outlines:        {}", outline_type(&provider));
+    println!("Glyphs:          {}", maxp.num_glyphs);
+    println!("Units per em:    {}", head.units_per_em);
+
+    if let Some(cmap_data) = provider.table_data(tag::CMAP)? {
+        let cmap = ReadScope::new(&cmap_data).read::<Cmap>()?;
+        let mut num_mappings = 0;
+        if let Some((_encoding, cmap_subtable)) = read_cmap_subtable(&cmap)? {
+            cmap_subtable.mappings_fn(|_ch, _gid| num_mappings += 1)?;
+        }
+        println!("cmap sub-tables: {}", cmap.encoding_records().count());
+        println!("Mapped chars:    {}", num_mappings);
+    } else {
+        println!("cmap sub-tables: 0");
+    }
+
+    match provider.table_data(tag::FVAR)? {
+        Some(fvar_data) => {
+            let fvar = ReadScope::new(&fvar_data).read::<FvarTable>()?;
+            let axes = fvar
+                .axes()
+                .map(|axis| DisplayTag(axis.axis_tag).to_string())
+                .collect::<Vec<_>>();
+            println!(
+                "Variable:        yes ({} axes: {})",
+                axes.len(),
+                axes.join(", ")
+            );
+        }
+        None => println!("Variable:        no"),
+    }
+
+    let colour = COLOUR_TABLES
+        .iter()
+        .filter(|&&table| provider.has_table(table))
+        .map(|&table| DisplayTag(table).to_string())
+        .collect::<Vec<_>>();
+    if colour.is_empty() {
+        println!("Colour:          no");
+    } else {
+        println!("Colour:          yes ({})", colour.join(", "));
+    }
+
+    let mut table_sizes = Vec::new();
+    for table in table_tags(&font_file, opts.index)? {
+        if let Some(data) = provider.table_data(table)? {
+            table_sizes.push((table, data.len()));
+        }
+    }
+    table_sizes.sort_by(|(_, a), (_, b)| b.cmp(a));
+    let largest = table_sizes
+        .iter()
+        .take(3)
+        .map(|(table, size)| format!("{} ({} bytes)", DisplayTag(*table), size))
+        .collect::<Vec<_>>();
+    println!("File size:       {} bytes", buffer.len());
+    println!("Largest tables:  {}", largest.join(", "));
+
+    let mut font = Font::new(provider)?;
+    if let Some(gsub_cache) = font.gsub_cache()? {
+        println!(
+            "GSUB features:   {}",
+            count_features(&gsub_cache.layout_table)?
+        );
+    }
+    if let Some(gpos_cache) = font.gpos_cache()? {
+        println!(
+            "GPOS features:   {}",
+            count_features(&gpos_cache.layout_table)?
+        );
+    }
+
+    Ok(0)
+}
+
+fn format_name(font_file: &FontData<'_>) -> String {
+    match font_file {
+        FontData::OpenType(font_file) => match &font_file.data {
+            OpenTypeData::Single(_) => String::from("OpenType"),
+            OpenTypeData::Collection(ttc) => {
+                format!("OpenType Collection ({} fonts)", ttc.offset_tables.len())
+            }
+        },
+        FontData::Woff(_) => String::from("WOFF"),
+        FontData::Woff2(_) => String::from("WOFF2"),
+    }
+}
+
+fn outline_type(provider: &impl FontTableProvider) -> &'static str {
+    if provider.has_table(tag::GLYF) {
+        "TrueType (glyf)"
+    } else if provider.has_table(tag::CFF) {
+        "PostScript (CFF)"
+    } else if provider.has_table(tag::CFF2) {
+        "PostScript (CFF2)"
+    } else {
+        "none"
+    }
+}
+
+/// The tags of the tables in the font at `index`
+pub(crate) fn table_tags(font_file: &FontData<'_>, index: usize) -> Result<Vec<u32>, BoxError> {
+    let tags = match font_file {
+        FontData::OpenType(font_file) => match &font_file.data {
+            OpenTypeData::Single(ttf) => record_tags(ttf),
+            OpenTypeData::Collection(ttc) => {
+                let offset = ttc
+                    .offset_tables
+                    .iter()
+                    .nth(index)
+                    .ok_or("font index out of range")?;
+                let offset_table = font_file
+                    .scope
+                    .offset(usize::try_from(offset)?)
+                    .read::<OffsetTable>()?;
+                record_tags(&offset_table)
+            }
+        },
+        FontData::Woff(woff_file) => woff_file
+            .table_directory
+            .iter()
+            .map(|entry| entry.tag)
+            .collect(),
+        FontData::Woff2(woff_file) => woff_file
+            .table_directory
+            .iter()
+            .map(|entry| entry.tag)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect(),
+    };
+    Ok(tags)
+}
+
+fn record_tags(offset_table: &OffsetTable<'_>) -> Vec<u32> {
+    offset_table
+        .table_records
+        .iter()
+        .map(|record| record.table_tag)
+        .collect()
+}
+
+fn count_features<T>(layout_table: &LayoutTable<T>) -> Result<usize, BoxError> {
+    let mut features = HashSet::new();
+    if let Some(script_list) = &layout_table.opt_script_list {
+        for script_record in script_list.script_records() {
+            let script_table = script_record.script_table();
+            let langsys_tables = script_table.default_langsys_record().into_iter().chain(
+                script_table
+                    .langsys_records()
+                    .map(|langsys| langsys.langsys_table()),
+            );
+            for langsys in langsys_tables {
+                for feature_index in langsys.feature_indices_iter() {
+                    let feature_record = layout_table.feature_by_index(*feature_index)?;
+                    features.insert(feature_record.feature_tag);
+                }
+            }
+        }
+    }
+    Ok(features.len())
+}
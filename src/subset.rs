@@ -1,40 +1,227 @@
 use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::Write;
-use std::str;
+use std::str::{self, FromStr};
 
 use allsorts::binary::read::ReadScope;
-use allsorts::font::read_cmap_subtable;
+use allsorts::font::{read_cmap_subtable, Font, MatchingPresentation};
 use allsorts::font_data::FontData;
-use allsorts::gsub::{GlyphOrigin, RawGlyph, RawGlyphFlags};
+use allsorts::gsub::{FeatureMask, Features, GlyphOrigin, RawGlyph, RawGlyphFlags};
+use allsorts::subset;
 use allsorts::tables::cmap::Cmap;
 use allsorts::tables::{FontTableProvider, MaxpTable};
+use allsorts::tag::{self, DisplayTag};
 use allsorts::tinyvec::tiny_vec;
-use allsorts::{subset, tag};
 
 use crate::cli::SubsetOpts;
-use crate::{glyph, BoxError, ErrorMessage};
+use crate::gsub_closure::GsubClosure;
+use crate::validation::{self, ValidationOptions};
+use crate::{glyph, read_font_file, validate, BoxError, ErrorMessage};
+
+/// The most glyphs GSUB can reach but the subset doesn't keep to list in the warning
+const MAX_MISSING_GLYPHS_LISTED: usize = 10;
+
+/// What to do when the subset font fails validation, given with `--check`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Check {
+    /// Report the problems and don't write the font
+    Fail,
+    /// Report the problems and write the font anyway
+    Warn,
+}
+
+impl FromStr for Check {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(Check::Fail),
+            "warn" => Ok(Check::Warn),
+            _ => Err(format!("invalid check mode '{}', expected fail or warn", s)),
+        }
+    }
+}
 
 pub fn main(opts: SubsetOpts) -> Result<i32, BoxError> {
-    let buffer = std::fs::read(&opts.input)?;
+    let buffer = read_font_file(&opts.input)?;
     let font_file = ReadScope::new(&buffer).read::<FontData>()?;
     let provider = font_file.table_provider(opts.index)?;
 
-    if opts.text.is_none() && !opts.all {
-        eprintln!("One of --text or --all is required");
+    let has_chars =
+        !(opts.text.is_empty() && opts.text_file.is_empty() && opts.unicodes.is_empty());
+    if !has_chars && !opts.all {
+        eprintln!("One of --text, --text-file, --unicodes, or --all is required");
+        return Ok(1);
+    }
+    if opts.shape.is_some() && !has_chars {
+        eprintln!("--shape requires --text, --text-file, or --unicodes");
+        return Ok(1);
+    }
+    if opts.lang.is_some() && opts.shape.is_none() {
+        eprintln!("--lang requires --shape");
+        return Ok(1);
+    }
+
+    if opts.closure && !has_chars {
+        eprintln!("--closure requires --text, --text-file, or --unicodes");
         return Ok(1);
     }
 
-    if let Some(text) = opts.text {
-        subset_text(&provider, &text, &opts.output)?;
+    let new_font = if has_chars {
+        let mut glyph_ids = if let Some(script) = &opts.shape {
+            let script = tag::from_string(script)?;
+            let lang = opts.lang.as_deref().map(tag::from_string).transpose()?;
+            shaped_glyph_ids(&font_file, &opts, script, lang)?
+        } else {
+            let chars = collect_chars(&opts)?;
+            let text = chars.into_iter().collect::<String>();
+            text_glyph_ids(&provider, &text)?
+        };
+        gsub_closure(&provider, &mut glyph_ids, opts.closure)?;
+        subset_glyphs(&provider, &glyph_ids)?
     } else {
-        subset_all(&provider, &opts.output)?;
+        subset_all(&provider)?
+    };
+
+    if let Some(tables) = &opts.preserve_tables {
+        let new_font_file = ReadScope::new(&new_font).read::<FontData>()?;
+        let new_provider = new_font_file.table_provider(0)?;
+        let changed = changed_tables(&provider, &new_provider, tables)?;
+        if !changed.is_empty() {
+            for message in changed {
+                eprintln!("{}", message);
+            }
+            eprintln!("Subset font doesn't preserve the tables, not writing output");
+            return Ok(1);
+        }
     }
 
+    if let Some(check) = opts.check {
+        let new_font_file = ReadScope::new(&new_font).read::<FontData>()?;
+        let new_provider = new_font_file.table_provider(0)?;
+        let findings = validation::validate(&new_provider, ValidationOptions::default())?;
+        if !findings.is_empty() {
+            validate::print_findings(&opts.output, &findings);
+            if check == Check::Fail {
+                eprintln!("Subset font failed validation, not writing output");
+                return Ok(1);
+            }
+        }
+    }
+
+    // Write out the new font
+    let mut output = File::create(&opts.output)?;
+    output.write_all(&new_font)?;
+
     Ok(0)
 }
 
-fn subset_all<F: FontTableProvider>(font_provider: &F, output_path: &str) -> Result<(), BoxError> {
+/// Describe each of the comma-separated `tables` that is missing from `new_provider` or differs
+/// from the table in `provider`
+fn changed_tables(
+    provider: &impl FontTableProvider,
+    new_provider: &impl FontTableProvider,
+    tables: &str,
+) -> Result<Vec<String>, BoxError> {
+    let mut changed = Vec::new();
+    for name in tables.split(',').map(str::trim) {
+        let table = tag::from_string(name)?;
+        let original = provider
+            .table_data(table)?
+            .ok_or_else(|| format!("table '{}' is not in the source font", name))?;
+        match new_provider.table_data(table)? {
+            Some(data) if data == original => {}
+            Some(_) => changed.push(format!("table '{}' was modified", name)),
+            None => changed.push(format!("table '{}' was dropped", name)),
+        }
+    }
+    Ok(changed)
+}
+
+/// Union the characters from each of the text sources, reporting how many new characters each
+/// one contributed
+fn collect_chars(opts: &SubsetOpts) -> Result<BTreeSet<char>, BoxError> {
+    let mut chars = BTreeSet::new();
+    for (source, text) in text_sources(opts)? {
+        let before = chars.len();
+        chars.extend(text.chars());
+        eprintln!("{}: {} new characters", source, chars.len() - before);
+    }
+    Ok(chars)
+}
+
+/// Shape each of the text sources with `script` and `lang`, returning the glyphs they shape to
+/// along with `.notdef` and reporting how many new glyphs each source contributed.
+///
+/// Each line is shaped separately, as shaping doesn't apply across lines.
+fn shaped_glyph_ids(
+    font_file: &FontData<'_>,
+    opts: &SubsetOpts,
+    script: u32,
+    lang: Option<u32>,
+) -> Result<Vec<u16>, BoxError> {
+    let mut font = Font::new(Box::new(font_file.table_provider(opts.index)?))?;
+    let features = Features::Mask(FeatureMask::default());
+    let mut glyph_ids = BTreeSet::new();
+    glyph_ids.insert(0);
+    for (source, text) in text_sources(opts)? {
+        let before = glyph_ids.len();
+        for line in text.lines() {
+            let glyphs = font.map_glyphs(line, script, MatchingPresentation::NotRequired);
+            let infos = font
+                .shape(glyphs, script, lang, &features, None, true)
+                .map_err(|(err, _infos)| err)?;
+            glyph_ids.extend(infos.iter().map(|info| info.glyph.glyph_index));
+        }
+        eprintln!("{}: {} new glyphs", source, glyph_ids.len() - before);
+    }
+    Ok(glyph_ids.into_iter().collect())
+}
+
+/// The name and text of each of the text sources, in the order they're reported
+fn text_sources(opts: &SubsetOpts) -> Result<Vec<(String, String)>, BoxError> {
+    let mut sources = Vec::new();
+    for (i, text) in opts.text.iter().enumerate() {
+        sources.push((format!("--text #{}", i + 1), text.clone()));
+    }
+    for path in &opts.text_file {
+        sources.push((path.clone(), std::fs::read_to_string(path)?));
+    }
+    for unicodes in &opts.unicodes {
+        sources.push((
+            format!("--unicodes {}", unicodes),
+            parse_unicodes(unicodes)?,
+        ));
+    }
+    Ok(sources)
+}
+
+/// Parse a comma-separated list of hex codepoints and ranges, such as `U+0041,61-7A`
+fn parse_unicodes(unicodes: &str) -> Result<String, BoxError> {
+    let parse = |hex: &str| {
+        let hex = hex.trim();
+        let hex = hex
+            .strip_prefix("U+")
+            .or_else(|| hex.strip_prefix("u+"))
+            .unwrap_or(hex);
+        u32::from_str_radix(hex, 16).map_err(|_| format!("invalid codepoint '{}'", hex))
+    };
+    let mut text = String::new();
+    for entry in unicodes.split(',') {
+        let (start, end) = match entry.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => {
+                let codepoint = parse(entry)?;
+                (codepoint, codepoint)
+            }
+        };
+        text.extend((start..=end).filter_map(std::char::from_u32));
+    }
+    Ok(text)
+}
+
+fn subset_all<F: FontTableProvider>(font_provider: &F) -> Result<Vec<u8>, BoxError> {
     let table = font_provider.table_data(tag::MAXP)?.expect("no maxp table");
     let scope = ReadScope::new(table.borrow());
     let maxp = scope.read::<MaxpTable>()?;
@@ -42,18 +229,14 @@ fn subset_all<F: FontTableProvider>(font_provider: &F, output_path: &str) -> Res
     let glyph_ids = (0..maxp.num_glyphs).collect::<Vec<_>>();
     let new_font = subset::subset(font_provider, &glyph_ids)?;
 
-    // Write out the new font
-    let mut output = File::create(output_path)?;
-    output.write_all(&new_font)?;
-
-    Ok(())
+    Ok(new_font)
 }
 
-fn subset_text<F: FontTableProvider>(
+/// The sorted glyphs the characters of `text` map to, along with `.notdef`
+fn text_glyph_ids<F: FontTableProvider>(
     font_provider: &F,
     text: &str,
-    output_path: &str,
-) -> Result<(), BoxError> {
+) -> Result<Vec<u16>, BoxError> {
     // Work out the glyphs we want to keep from the text
     let mut glyphs = chars_to_glyphs(font_provider, text)?;
     let notdef = RawGlyph {
@@ -74,6 +257,59 @@ fn subset_text<F: FontTableProvider>(
         .map(|glyph| glyph.glyph_index)
         .collect::<Vec<_>>();
     glyph_ids.dedup();
+    Ok(glyph_ids)
+}
+
+/// Warn about the glyphs that GSUB can substitute into the sorted `glyph_ids` but aren't in
+/// them, so shaping with the subset font would differ, or with `closure` add them.
+fn gsub_closure<F: FontTableProvider>(
+    font_provider: &F,
+    glyph_ids: &mut Vec<u16>,
+    closure: bool,
+) -> Result<(), BoxError> {
+    let kept = glyph_ids.iter().copied().collect::<BTreeSet<_>>();
+    let missing = match font_provider.table_data(tag::GSUB)? {
+        Some(gsub_data) => GsubClosure::read(&gsub_data)?.missing(&kept),
+        None => BTreeMap::new(),
+    };
+    if closure {
+        eprintln!("--closure: {} new glyphs", missing.len());
+        glyph_ids.extend(missing.keys());
+        glyph_ids.sort_unstable();
+        return Ok(());
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!(
+        "warning: GSUB can substitute {} glyph(s) that aren't in the subset, shaping with the \
+         subset font may differ (use --closure to include them)",
+        missing.len()
+    );
+    for (glyph_id, features) in missing.iter().take(MAX_MISSING_GLYPHS_LISTED) {
+        let features = if features.is_empty() {
+            String::from("only applied by contextual lookups")
+        } else {
+            features
+                .iter()
+                .map(|&feature| DisplayTag(feature).to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        eprintln!(" - glyph {}: {}", glyph_id, features);
+    }
+    if missing.len() > MAX_MISSING_GLYPHS_LISTED {
+        eprintln!(" - and {} more", missing.len() - MAX_MISSING_GLYPHS_LISTED);
+    }
+    Ok(())
+}
+
+/// Subset to `glyph_ids`, which are sorted and include `.notdef`
+fn subset_glyphs<F: FontTableProvider>(
+    font_provider: &F,
+    glyph_ids: &[u16],
+) -> Result<Vec<u8>, BoxError> {
     if glyph_ids.is_empty() {
         return Err(ErrorMessage("no glyphs left in font").into());
     }
@@ -81,13 +317,9 @@ fn subset_text<F: FontTableProvider>(
     println!("Number of glyphs in new font: {}", glyph_ids.len());
 
     // Subset
-    let new_font = subset::subset(font_provider, &glyph_ids)?;
+    let new_font = subset::subset(font_provider, glyph_ids)?;
 
-    // Write out the new font
-    let mut output = File::create(output_path)?;
-    output.write_all(&new_font)?;
-
-    Ok(())
+    Ok(new_font)
 }
 
 fn chars_to_glyphs<F: FontTableProvider>(
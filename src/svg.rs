@@ -22,7 +22,7 @@ use allsorts::{tag, Font};
 use crate::cli::SvgOpts;
 use crate::script;
 use crate::writer::{NamedOutliner, SVGMode, SVGWriter};
-use crate::BoxError;
+use crate::{write_svg, BoxError};
 
 const FONT_SIZE: f32 = 1000.0;
 
@@ -78,6 +78,8 @@ pub fn main(opts: SvgOpts) -> Result<i32, BoxError> {
             .map(|data| ReadScope::new(data).read::<PostTable<'_>>())
             .transpose()?;
 
+        // The font has already been instanced at the variation settings, so applying the tuple
+        // to the outlines again would vary them twice
         let cff2_outlines = CFF2Outlines {
             table: &cff,
             tuple: None,
@@ -110,7 +112,7 @@ pub fn main(opts: SvgOpts) -> Result<i32, BoxError> {
         return Ok(1);
     };
 
-    println!("{}", svg);
+    write_svg(&svg, opts.output.as_deref())?;
 
     Ok(0)
 }
@@ -0,0 +1,161 @@
+//! The glyph documents of an OpenType `SVG ` table, for drawing colour glyphs in `view`
+//!
+//! allsorts doesn't parse the table, so the document list is read directly. Each document holds
+//! the glyphs of a range of glyph ids, as elements with the id `glyphN`, in font units with the
+//! y axis pointing down. Documents commonly reuse ids, for gradients and such, so the ids of each
+//! document are prefixed with its index before it's embedded alongside the others.
+
+use std::io::Read;
+
+use allsorts::binary::read::ReadScope;
+use flate2::read::GzDecoder;
+
+use crate::{scope_at_offset, BoxError};
+
+/// The first bytes of a gzip compressed document
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The attributes that give an element its id, and the references to ids within a document,
+/// each up to where the id starts
+const ID_ATTRIBUTES: [&str; 2] = ["id=\"", "id='"];
+const ID_REFERENCES: [&str; 5] = ["href=\"#", "href='#", "url(#", "url(\"#", "url('#"];
+
+/// The glyph ids covered by a document and the index of that document
+struct DocumentRecord {
+    start_glyph_id: u16,
+    end_glyph_id: u16,
+    document_index: usize,
+}
+
+pub(crate) struct SvgTable {
+    records: Vec<DocumentRecord>,
+    /// The documents, decompressed and without any XML declaration or doctype
+    documents: Vec<String>,
+}
+
+impl SvgTable {
+    pub(crate) fn read(data: &[u8]) -> Result<Self, BoxError> {
+        let scope = ReadScope::new(data);
+        let mut ctxt = scope.ctxt();
+        let _version = ctxt.read_u16be()?;
+        let document_list_offset = ctxt.read_u32be()? as usize;
+        let document_list = scope_at_offset(&scope, document_list_offset)?;
+        let mut ctxt = document_list.ctxt();
+        let num_entries = ctxt.read_u16be()?;
+
+        let mut records = Vec::new();
+        // Documents shared by several records, which is common, are kept once
+        let mut offsets = Vec::new();
+        let mut documents = Vec::new();
+        for _ in 0..num_entries {
+            let start_glyph_id = ctxt.read_u16be()?;
+            let end_glyph_id = ctxt.read_u16be()?;
+            let offset = ctxt.read_u32be()? as usize;
+            let length = ctxt.read_u32be()? as usize;
+            let document_index = match offsets.iter().position(|&other| other == offset) {
+                Some(index) => index,
+                None => {
+                    let data = scope_at_offset(&document_list, offset)?
+                        .ctxt()
+                        .read_slice(length)?;
+                    offsets.push(offset);
+                    let prefix = id_prefix(documents.len());
+                    documents.push(scope_ids(&read_document(data)?, &prefix));
+                    documents.len() - 1
+                }
+            };
+            records.push(DocumentRecord {
+                start_glyph_id,
+                end_glyph_id,
+                document_index,
+            });
+        }
+
+        Ok(SvgTable { records, documents })
+    }
+
+    /// The index of the document holding `glyph_id`, if it's in the table
+    pub(crate) fn document_index(&self, glyph_id: u16) -> Option<usize> {
+        self.records
+            .iter()
+            .find(|record| (record.start_glyph_id..=record.end_glyph_id).contains(&glyph_id))
+            .map(|record| record.document_index)
+    }
+
+    pub(crate) fn document_count(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub(crate) fn document(&self, index: usize) -> &str {
+        &self.documents[index]
+    }
+}
+
+/// The id of the element of glyph `glyph_id` in document `document_index`, once its ids are
+/// scoped
+pub(crate) fn glyph_element_id(document_index: usize, glyph_id: u16) -> String {
+    format!("{}glyph{}", id_prefix(document_index), glyph_id)
+}
+
+fn id_prefix(document_index: usize) -> String {
+    format!("svgdoc{}-", document_index)
+}
+
+/// Prefix the ids of the elements of `document`, and the references to them, with `prefix`.
+///
+/// Only attributes, not ids in CSS selectors, are rewritten.
+fn scope_ids(document: &str, prefix: &str) -> String {
+    let mut scoped = String::with_capacity(document.len());
+    let mut copied = 0;
+    for (index, _) in document.char_indices() {
+        let rest = &document[index..];
+        // Attribute names must start after whitespace, or a namespace as in `xlink:href`
+        let attribute_start = document[..index]
+            .chars()
+            .next_back()
+            .map_or(true, |ch| ch.is_whitespace() || ch == ':');
+        let pattern = ID_ATTRIBUTES
+            .iter()
+            .chain(&ID_REFERENCES)
+            .filter(|pattern| attribute_start || pattern.starts_with("url("))
+            .find(|pattern| rest.starts_with(*pattern));
+        if let Some(pattern) = pattern {
+            let end = index + pattern.len();
+            scoped.push_str(&document[copied..end]);
+            scoped.push_str(prefix);
+            copied = end;
+        }
+    }
+    scoped.push_str(&document[copied..]);
+    scoped
+}
+
+/// Decompress a document if needed and strip what can't be nested in another SVG
+fn read_document(data: &[u8]) -> Result<String, BoxError> {
+    let mut document = String::new();
+    if data.starts_with(&GZIP_MAGIC) {
+        GzDecoder::new(data).read_to_string(&mut document)?;
+    } else {
+        document = String::from_utf8(data.to_vec())?;
+    }
+
+    let mut document = document.trim_start_matches('\u{feff}').trim_start();
+    if document.starts_with("<?xml") {
+        document = skip_past(document, "?>");
+    }
+    if document.starts_with("<!DOCTYPE") {
+        // The doctype may have an internal subset in brackets
+        let internal_subset = document.find('[').map_or(false, |bracket| {
+            document.find('>').map_or(false, |end| bracket < end)
+        });
+        document = skip_past(document, if internal_subset { "]>" } else { ">" });
+    }
+    Ok(document.to_string())
+}
+
+/// The rest of `document` after the first `end`, or nothing if it doesn't contain `end`
+fn skip_past<'a>(document: &'a str, end: &str) -> &'a str {
+    document
+        .find(end)
+        .map_or("", |index| document[index + end.len()..].trim_start())
+}
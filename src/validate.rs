@@ -1,101 +1,117 @@
-use std::borrow::Borrow;
-use std::convert::TryFrom;
-
 use allsorts::binary::read::ReadScope;
-use allsorts::cff::Operator;
-use allsorts::error::ParseError;
+use allsorts::font::Font;
 use allsorts::font_data::FontData;
-use allsorts::tables::glyf::GlyfTable;
-use allsorts::tables::loca::LocaTable;
-use allsorts::tables::{FontTableProvider, HeadTable, MaxpTable};
-use allsorts::tag;
+use allsorts::tables::variable_fonts::fvar::FvarTable;
+use allsorts::tables::{Fixed, FontTableProvider};
+use allsorts::tag::{self, DisplayTag};
 
+use crate::baseline::Manifest;
 use crate::cli::ValidateOpts;
+use crate::validation::{self, Finding, ValidationOptions};
 use crate::BoxError;
 
 pub fn main(opts: ValidateOpts) -> Result<i32, BoxError> {
+    if opts.write_baseline && opts.baseline.is_none() {
+        eprintln!("--write-baseline requires --baseline");
+        return Ok(1);
+    }
+
     let buffer = std::fs::read(&opts.font)?;
     let scope = ReadScope::new(&buffer);
     let font_file = scope.read::<FontData>()?;
     let table_provider = font_file.table_provider(0)?; // TODO: Handle all fonts in collection
-    let failed = dump_glyphs(&opts.font, &table_provider)?;
-    if failed {
-        Ok(1)
-    } else {
-        Ok(0)
-    }
-}
-
-fn dump_glyphs(path: &str, provider: &impl FontTableProvider) -> Result<bool, ParseError> {
-    let table = provider.table_data(tag::HEAD)?.expect("no head table");
-    let scope = ReadScope::new(table.borrow());
-    let head = scope.read::<HeadTable>()?;
+    let mut findings = validation::validate(&table_provider, ValidationOptions::default())?;
+    print_findings(&opts.font, &findings);
 
-    let table = provider.table_data(tag::MAXP)?.expect("no maxp table");
-    let scope = ReadScope::new(table.borrow());
-    let maxp = scope.read::<MaxpTable>()?;
+    if let Some(fvar_data) = table_provider.table_data(tag::FVAR)? {
+        let fvar = ReadScope::new(&fvar_data).read::<FvarTable>()?;
+        let user_tuple = instance_tuple(&fvar, opts.at.as_deref())?;
+        let label = fvar
+            .axes()
+            .zip(&user_tuple)
+            .map(|(axis, &value)| format!("{}={}", DisplayTag(axis.axis_tag), f32::from(value)))
+            .collect::<Vec<_>>()
+            .join(",");
+        let path = format!("{} @ {}", opts.font, label);
 
-    let mut failed = false;
-    if provider.has_table(tag::CFF) {
-        let cff = provider
-            .table_data(tag::CFF)?
-            .expect("unable to read CFF table");
-        match check_cff_table(ReadScope::new(&cff)) {
-            Ok(()) => (),
+        // Deltas that are fine at the axis extremes can still produce broken glyphs elsewhere,
+        // so validate the glyphs of a static instance too
+        match allsorts::variations::instance(&table_provider, &user_tuple) {
+            Ok((instance, _tuple)) => {
+                let instance_file = ReadScope::new(&instance).read::<FontData>()?;
+                let instance_provider = instance_file.table_provider(0)?;
+                let options = ValidationOptions {
+                    cmap: false,
+                    ..ValidationOptions::default()
+                };
+                let instance_findings = validation::validate(&instance_provider, options)?;
+                print_findings(&path, &instance_findings);
+                findings.extend(instance_findings);
+            }
             Err(err) => {
-                failed = true;
-                println!("{}: CFF Error - {}", path, err)
+                println!("{}: unable to create instance - {}", path, err);
+                return Ok(1);
             }
         }
-    } else {
-        let table = provider.table_data(tag::LOCA)?.expect("no loca table");
-        let scope = ReadScope::new(table.borrow());
-        let loca = scope
-            .read_dep::<LocaTable>((usize::from(maxp.num_glyphs), head.index_to_loc_format))?;
-
-        let table = provider.table_data(tag::GLYF)?.expect("no glyf table");
-        let scope = ReadScope::new(table.borrow());
-        let mut glyf = scope.read_dep::<GlyfTable>(&loca)?;
+    } else if opts.at.is_some() {
+        eprintln!("--at requires a variable font");
+        return Ok(1);
+    }
 
-        for (index, glyph) in glyf.records_mut().iter_mut().enumerate() {
-            match glyph.parse() {
-                Ok(()) => (),
-                Err(err) => {
-                    failed = true;
-                    println!("{} [{}]: {}", path, index, err)
-                }
+    let mut drift = Vec::new();
+    if let Some(path) = &opts.baseline {
+        let manifest =
+            Manifest::from_font(&table_provider, Font::new(font_file.table_provider(0)?)?)?;
+        if opts.write_baseline {
+            std::fs::write(path, manifest.to_string())?;
+        } else {
+            let baseline = Manifest::parse(&std::fs::read_to_string(path)?)?;
+            drift = baseline.drift(&manifest);
+            for change in &drift {
+                println!("{}: baseline: {}", opts.font, change);
             }
         }
     }
 
-    Ok(failed)
+    if !findings.is_empty() || !drift.is_empty() {
+        Ok(1)
+    } else {
+        Ok(0)
+    }
 }
 
-fn check_cff_table<'a>(scope: ReadScope<'a>) -> Result<(), ParseError> {
-    use allsorts::cff::{self, CFFVariant, FontDict, CFF};
+/// The user coordinates of the instance to validate.
+///
+/// Axes not listed in `at`, a comma-separated list of `axis:value` entries, take their default.
+fn instance_tuple(fvar: &FvarTable<'_>, at: Option<&str>) -> Result<Vec<Fixed>, BoxError> {
+    let mut tuple = fvar
+        .axes()
+        .map(|axis| axis.default_value)
+        .collect::<Vec<_>>();
+    let Some(at) = at else {
+        return Ok(tuple);
+    };
 
-    let cff = scope.read::<CFF>()?;
-    if cff.name_index.len() != 1 {
-        return Err(ParseError::BadIndex);
+    for entry in at.split(',').map(str::trim) {
+        let (axis_tag, value) = entry
+            .split_once(':')
+            .ok_or_else(|| format!("expected axis:value, got '{}'", entry))?;
+        let axis_tag = tag::from_string(axis_tag)?;
+        let index = fvar
+            .axes()
+            .position(|axis| axis.axis_tag == axis_tag)
+            .ok_or_else(|| format!("font has no {} axis", DisplayTag(axis_tag)))?;
+        tuple[index] = Fixed::from(value.trim().parse::<f32>()?);
     }
-    let font = cff.fonts.get(0).ok_or(ParseError::MissingValue)?;
-    let char_strings_offset = font
-        .top_dict
-        .get_i32(Operator::CharStrings)
-        .ok_or(ParseError::MissingValue)??;
-    let _char_strings_index = scope
-        .offset(usize::try_from(char_strings_offset)?)
-        .read::<cff::IndexU16>()?;
-    match &font.data {
-        CFFVariant::Type1(ref _type1) => {}
-        CFFVariant::CID(cid) => {
-            for (_i, object) in cid.font_dict_index.iter().enumerate() {
-                let font_dict = ReadScope::new(object).read_dep::<FontDict>(cff::MAX_OPERANDS)?;
-                let (_private_dict, _private_dict_offset) =
-                    font_dict.read_private_dict::<cff::PrivateDict>(&scope, cff::MAX_OPERANDS)?;
-            }
+    Ok(tuple)
+}
+
+pub(crate) fn print_findings(path: &str, findings: &[Finding]) {
+    for finding in findings {
+        match finding {
+            Finding::Glyph(index, err) => println!("{} [{}]: {}", path, index, err),
+            Finding::Cff(err) => println!("{}: CFF Error - {}", path, err),
+            _ => println!("{}: {}", path, finding),
         }
     }
-
-    Ok(())
 }
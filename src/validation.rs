@@ -0,0 +1,217 @@
+use std::borrow::Borrow;
+use std::convert::TryFrom;
+use std::fmt;
+
+use allsorts::binary::read::ReadScope;
+use allsorts::cff::Operator;
+use allsorts::error::ParseError;
+use allsorts::font::read_cmap_subtable;
+use allsorts::tables::cmap::Cmap;
+use allsorts::tables::glyf::GlyfTable;
+use allsorts::tables::loca::LocaTable;
+use allsorts::tables::{FontTableProvider, HeadTable, MaxpTable};
+use allsorts::tag;
+
+/// A problem found while validating a font
+#[derive(Debug)]
+pub enum Finding {
+    /// The glyph at this index could not be parsed
+    Glyph(usize, ParseError),
+    /// The CFF table could not be parsed
+    Cff(ParseError),
+    /// The loca offset for this glyph is less than the one before it
+    LocaOrder(usize),
+    /// The loca offset for this glyph points past the end of the glyf table
+    LocaOutOfRange(usize),
+    /// The character maps to a glyph index that is not in the font
+    CmapOutOfRange(u32, u16),
+}
+
+/// Which validation passes to run
+#[derive(Debug, Copy, Clone)]
+pub struct ValidationOptions {
+    /// Parse every glyph in the `glyf` table, or the DICTs of the `CFF` table
+    pub glyphs: bool,
+    /// Check `loca` offsets are in order and within the `glyf` table
+    pub loca: bool,
+    /// Check the `cmap` only maps to glyphs in the font
+    pub cmap: bool,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        ValidationOptions {
+            glyphs: true,
+            loca: true,
+            cmap: true,
+        }
+    }
+}
+
+/// Run the selected validation passes over the font, returning any problems found.
+///
+/// An `Err` is only returned if the font is too broken to validate, such as when the `head` or
+/// `maxp` tables can't be read.
+pub fn validate(
+    provider: &impl FontTableProvider,
+    options: ValidationOptions,
+) -> Result<Vec<Finding>, ParseError> {
+    let mut findings = Vec::new();
+    if options.glyphs || options.loca {
+        findings.extend(check_glyphs(provider, options)?);
+    }
+    if options.cmap {
+        findings.extend(check_cmap(provider)?);
+    }
+    Ok(findings)
+}
+
+fn check_glyphs(
+    provider: &impl FontTableProvider,
+    options: ValidationOptions,
+) -> Result<Vec<Finding>, ParseError> {
+    let table = provider
+        .table_data(tag::HEAD)?
+        .ok_or(ParseError::MissingValue)?;
+    let scope = ReadScope::new(table.borrow());
+    let head = scope.read::<HeadTable>()?;
+
+    let table = provider
+        .table_data(tag::MAXP)?
+        .ok_or(ParseError::MissingValue)?;
+    let scope = ReadScope::new(table.borrow());
+    let maxp = scope.read::<MaxpTable>()?;
+
+    let mut findings = Vec::new();
+    if provider.has_table(tag::CFF) {
+        if !options.glyphs {
+            return Ok(findings);
+        }
+        let cff = provider
+            .table_data(tag::CFF)?
+            .expect("unable to read CFF table");
+        match check_cff_table(ReadScope::new(&cff)) {
+            Ok(()) => (),
+            Err(err) => findings.push(Finding::Cff(err)),
+        }
+    } else {
+        let table = provider
+            .table_data(tag::LOCA)?
+            .ok_or(ParseError::MissingValue)?;
+        let scope = ReadScope::new(table.borrow());
+        let loca = scope
+            .read_dep::<LocaTable>((usize::from(maxp.num_glyphs), head.index_to_loc_format))?;
+
+        let table = provider
+            .table_data(tag::GLYF)?
+            .ok_or(ParseError::MissingValue)?;
+        if options.loca {
+            findings.extend(check_loca_table(&loca, table.len()));
+        }
+        if !options.glyphs {
+            return Ok(findings);
+        }
+
+        let scope = ReadScope::new(table.borrow());
+        let mut glyf = scope.read_dep::<GlyfTable>(&loca)?;
+
+        for (index, glyph) in glyf.records_mut().iter_mut().enumerate() {
+            match glyph.parse() {
+                Ok(()) => (),
+                Err(err) => findings.push(Finding::Glyph(index, err)),
+            }
+        }
+    }
+
+    Ok(findings)
+}
+
+fn check_loca_table(loca: &LocaTable, glyf_len: usize) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut prev_offset = 0;
+    for (glyph_id, offset) in loca.offsets.iter().enumerate() {
+        if offset < prev_offset {
+            findings.push(Finding::LocaOrder(glyph_id));
+        }
+        if usize::try_from(offset).map_or(true, |offset| offset > glyf_len) {
+            findings.push(Finding::LocaOutOfRange(glyph_id));
+        }
+        prev_offset = offset;
+    }
+    findings
+}
+
+fn check_cmap(provider: &impl FontTableProvider) -> Result<Vec<Finding>, ParseError> {
+    let table = provider
+        .table_data(tag::MAXP)?
+        .ok_or(ParseError::MissingValue)?;
+    let scope = ReadScope::new(table.borrow());
+    let maxp = scope.read::<MaxpTable>()?;
+
+    let mut findings = Vec::new();
+    let Some(table) = provider.table_data(tag::CMAP)? else {
+        return Ok(findings);
+    };
+    let cmap = ReadScope::new(table.borrow()).read::<Cmap>()?;
+    if let Some((_encoding, cmap_subtable)) = read_cmap_subtable(&cmap)? {
+        cmap_subtable.mappings_fn(|ch, gid| {
+            if gid >= maxp.num_glyphs {
+                findings.push(Finding::CmapOutOfRange(ch, gid));
+            }
+        })?;
+    }
+    Ok(findings)
+}
+
+fn check_cff_table<'a>(scope: ReadScope<'a>) -> Result<(), ParseError> {
+    use allsorts::cff::{self, CFFVariant, FontDict, CFF};
+
+    let cff = scope.read::<CFF>()?;
+    if cff.name_index.len() != 1 {
+        return Err(ParseError::BadIndex);
+    }
+    let font = cff.fonts.get(0).ok_or(ParseError::MissingValue)?;
+    let char_strings_offset = font
+        .top_dict
+        .get_i32(Operator::CharStrings)
+        .ok_or(ParseError::MissingValue)??;
+    let _char_strings_index = scope
+        .offset(usize::try_from(char_strings_offset)?)
+        .read::<cff::IndexU16>()?;
+    match &font.data {
+        CFFVariant::Type1(ref _type1) => {}
+        CFFVariant::CID(cid) => {
+            for (_i, object) in cid.font_dict_index.iter().enumerate() {
+                let font_dict = ReadScope::new(object).read_dep::<FontDict>(cff::MAX_OPERANDS)?;
+                let (_private_dict, _private_dict_offset) =
+                    font_dict.read_private_dict::<cff::PrivateDict>(&scope, cff::MAX_OPERANDS)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::Glyph(index, err) => write!(f, "glyph {}: {}", index, err),
+            Finding::Cff(err) => write!(f, "CFF Error - {}", err),
+            Finding::LocaOrder(glyph_id) => write!(
+                f,
+                "loca offset for glyph {} is less than the previous offset",
+                glyph_id
+            ),
+            Finding::LocaOutOfRange(glyph_id) => write!(
+                f,
+                "loca offset for glyph {} is past the end of the glyf table",
+                glyph_id
+            ),
+            Finding::CmapOutOfRange(ch, gid) => write!(
+                f,
+                "cmap maps U+{:04X} to glyph {} which is not in the font",
+                ch, gid
+            ),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Unicode variation sequences from the format 14 `cmap` subtable, which allsorts doesn't read
+
+use std::collections::BTreeMap;
+
+use allsorts::binary::read::{ReadCtxt, ReadScope};
+use allsorts::error::ParseError;
+
+use crate::scope_at_offset;
+
+/// How a base character followed by a variation selector is mapped
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum VariationGlyph {
+    /// The sequence uses the glyph the rest of the `cmap` maps the base character to
+    Default,
+    /// The sequence maps to this glyph
+    Glyph(u16),
+}
+
+/// The variation sequences of a font, keyed by variation selector
+pub(crate) struct VariationSequences {
+    /// Inclusive ranges of base characters whose sequences use the default glyph
+    default: BTreeMap<u32, Vec<(u32, u32)>>,
+    /// The glyph of each base character that has its own glyph
+    non_default: BTreeMap<u32, BTreeMap<u32, u16>>,
+}
+
+impl VariationSequences {
+    /// Read the Unicode platform, encoding 5 subtable of `cmap_data`, if the font has one
+    pub(crate) fn read_cmap(cmap_data: &[u8]) -> Result<Option<Self>, ParseError> {
+        let scope = ReadScope::new(cmap_data);
+        let mut ctxt = scope.ctxt();
+        let _version = ctxt.read_u16be()?;
+        let num_tables = ctxt.read_u16be()?;
+        for _ in 0..num_tables {
+            let platform_id = ctxt.read_u16be()?;
+            let encoding_id = ctxt.read_u16be()?;
+            let offset = ctxt.read_u32be()? as usize;
+            if (platform_id, encoding_id) == (0, 5) {
+                return Self::read_subtable(&scope_at_offset(&scope, offset)?).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn read_subtable(scope: &ReadScope<'_>) -> Result<Self, ParseError> {
+        let mut ctxt = scope.ctxt();
+        if ctxt.read_u16be()? != 14 {
+            return Err(ParseError::BadVersion);
+        }
+        let _length = ctxt.read_u32be()?;
+        let num_records = ctxt.read_u32be()?;
+        let mut sequences = VariationSequences {
+            default: BTreeMap::new(),
+            non_default: BTreeMap::new(),
+        };
+        for _ in 0..num_records {
+            let selector = read_u24be(&mut ctxt)?;
+            let default_offset = ctxt.read_u32be()? as usize;
+            let non_default_offset = ctxt.read_u32be()? as usize;
+            if default_offset != 0 {
+                let mut ctxt = scope_at_offset(scope, default_offset)?.ctxt();
+                let num_ranges = ctxt.read_u32be()?;
+                let ranges = sequences.default.entry(selector).or_default();
+                for _ in 0..num_ranges {
+                    let start = read_u24be(&mut ctxt)?;
+                    let additional_count = ctxt.read_u8()?;
+                    ranges.push((start, start + u32::from(additional_count)));
+                }
+            }
+            if non_default_offset != 0 {
+                let mut ctxt = scope_at_offset(scope, non_default_offset)?.ctxt();
+                let num_mappings = ctxt.read_u32be()?;
+                let mappings = sequences.non_default.entry(selector).or_default();
+                for _ in 0..num_mappings {
+                    let base = read_u24be(&mut ctxt)?;
+                    let glyph_id = ctxt.read_u16be()?;
+                    mappings.insert(base, glyph_id);
+                }
+            }
+        }
+        Ok(sequences)
+    }
+
+    /// The glyph for `base` followed by `selector`, or `None` if the font has no such sequence
+    pub(crate) fn lookup(&self, base: char, selector: char) -> Option<VariationGlyph> {
+        let (base, selector) = (u32::from(base), u32::from(selector));
+        if let Some(&glyph_id) = self
+            .non_default
+            .get(&selector)
+            .and_then(|mappings| mappings.get(&base))
+        {
+            return Some(VariationGlyph::Glyph(glyph_id));
+        }
+        self.default
+            .get(&selector)?
+            .iter()
+            .any(|&(start, end)| (start..=end).contains(&base))
+            .then_some(VariationGlyph::Default)
+    }
+}
+
+/// Whether `ch` is one of the variation selectors VS1 to VS256
+pub(crate) fn is_variation_selector(ch: char) -> bool {
+    matches!(ch, '\u{FE00}'..='\u{FE0F}' | '\u{E0100}'..='\u{E01EF}')
+}
+
+fn read_u24be(ctxt: &mut ReadCtxt<'_>) -> Result<u32, ParseError> {
+    let high = ctxt.read_u8()?;
+    let low = ctxt.read_u16be()?;
+    Ok((u32::from(high) << 16) | u32::from(low))
+}
@@ -0,0 +1,115 @@
+//! The vertical metrics of glyphs from `vhea`, `vmtx`, and `VORG`, which vertical layout is
+//! based on
+//!
+//! Fonts without `vhea` and `vmtx` get metrics synthesized from `hhea`: every glyph advances by
+//! the line height and hangs from the ascender.
+
+use std::borrow::Borrow;
+
+use allsorts::binary::read::ReadScope;
+use allsorts::error::ParseError;
+use allsorts::tables::{FontTableProvider, HheaTable, HmtxTable, MaxpTable};
+use allsorts::tag;
+
+use crate::extents::glyph_extents;
+use crate::BoxError;
+
+const VORG: u32 = allsorts::tag!(b"VORG");
+
+/// The vertical metrics of a glyph, in font units
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct VerticalMetrics {
+    pub advance: i32,
+    /// The distance from the vertical origin down to the top of the glyph's outline
+    pub top_side_bearing: i32,
+    /// The y coordinate of the vertical origin
+    pub origin_y: i32,
+}
+
+/// Whether the font has no `vhea` and `vmtx`, so its vertical metrics are synthesized
+pub(crate) fn synthesized(provider: &impl FontTableProvider) -> bool {
+    !(provider.has_table(tag::VHEA) && provider.has_table(tag::VMTX))
+}
+
+/// The vertical metrics of each of the glyphs in `glyph_ids`.
+///
+/// The vertical origin comes from `VORG` if the font has one, otherwise it's the top side bearing
+/// above the top of the outline, or above the baseline for glyphs without an outline.
+pub(crate) fn vertical_metrics(
+    provider: &impl FontTableProvider,
+    glyph_ids: &[u16],
+) -> Result<Vec<VerticalMetrics>, BoxError> {
+    let extents = glyph_extents(provider, glyph_ids)?;
+    let y_maxes = extents
+        .iter()
+        .map(|extents| extents.map_or(0, |extents| extents.y_max.round() as i32));
+
+    if synthesized(provider) {
+        let table = provider.read_table_data(tag::HHEA)?;
+        let hhea = ReadScope::new(table.borrow()).read::<HheaTable>()?;
+        let ascender = i32::from(hhea.ascender);
+        let advance = ascender - i32::from(hhea.descender);
+        let metrics = y_maxes
+            .map(|y_max| VerticalMetrics {
+                advance,
+                top_side_bearing: ascender - y_max,
+                origin_y: ascender,
+            })
+            .collect();
+        return Ok(metrics);
+    }
+
+    let table = provider.read_table_data(tag::MAXP)?;
+    let maxp = ReadScope::new(table.borrow()).read::<MaxpTable>()?;
+    // vhea has the same layout as hhea
+    let table = provider.read_table_data(tag::VHEA)?;
+    let vhea = ReadScope::new(table.borrow()).read::<HheaTable>()?;
+    let num_glyphs = usize::from(maxp.num_glyphs);
+    let num_metrics = usize::from(vhea.num_h_metrics);
+    let vmtx_data = provider.read_table_data(tag::VMTX)?;
+    let vmtx = ReadScope::new(&vmtx_data).read_dep::<HmtxTable<'_>>((num_glyphs, num_metrics))?;
+    let vorg = provider
+        .table_data(VORG)?
+        .map(|data| read_vorg(&data))
+        .transpose()?;
+
+    let mut metrics = Vec::with_capacity(glyph_ids.len());
+    for (&glyph_id, y_max) in glyph_ids.iter().zip(y_maxes) {
+        let index = usize::from(glyph_id);
+        if index >= num_glyphs || num_metrics == 0 {
+            return Err(ParseError::BadIndex.into());
+        }
+        // Glyphs after the last long metric share its advance
+        let metric = vmtx.h_metrics.get_item(index.min(num_metrics - 1));
+        let top_side_bearing = match index.checked_sub(num_metrics) {
+            Some(index) => vmtx.left_side_bearings.get_item(index),
+            None => metric.lsb,
+        };
+        let origin_y = match &vorg {
+            Some((default, origins)) => origins
+                .iter()
+                .find(|&&(other, _)| other == glyph_id)
+                .map_or(*default, |&(_, origin_y)| origin_y),
+            None => i32::from(top_side_bearing) + y_max,
+        };
+        metrics.push(VerticalMetrics {
+            advance: i32::from(metric.advance_width),
+            top_side_bearing: i32::from(top_side_bearing),
+            origin_y,
+        });
+    }
+    Ok(metrics)
+}
+
+/// The default vertical origin of a `VORG` table and the glyphs that have their own
+fn read_vorg(data: &[u8]) -> Result<(i32, Vec<(u16, i32)>), ParseError> {
+    let mut ctxt = ReadScope::new(data).ctxt();
+    let _major_version = ctxt.read_u16be()?;
+    let _minor_version = ctxt.read_u16be()?;
+    let default_origin_y = ctxt.read_i16be()?;
+    let num_metrics = ctxt.read_u16be()?;
+    let origins = (0..num_metrics)
+        .map(|_| Ok((ctxt.read_u16be()?, i32::from(ctxt.read_i16be()?))))
+        .collect::<Result<Vec<_>, ParseError>>()?;
+    Ok((i32::from(default_origin_y), origins))
+}
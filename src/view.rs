@@ -1,8 +1,15 @@
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
 use allsorts::binary::read::ReadScope;
+use allsorts::cff::cff2::CFF2;
+use allsorts::cff::outline::CFF2Outlines;
 use allsorts::cff::CFF;
 use allsorts::error::ParseError;
 use allsorts::font::{Font, GlyphTableFlags, MatchingPresentation};
 use allsorts::font_data::FontData;
+use allsorts::glyph_position::TextDirection;
+use allsorts::gpos::Info;
 use allsorts::gsub::{FeatureInfo, FeatureMask, Features, GlyphOrigin, RawGlyph, RawGlyphFlags};
 use allsorts::pathfinder_geometry::transform2d::Matrix2x2F;
 use allsorts::pathfinder_geometry::vector::vec2f;
@@ -10,19 +17,78 @@ use allsorts::post::PostTable;
 use allsorts::tables::glyf::GlyfTable;
 use allsorts::tables::loca::LocaTable;
 use allsorts::tables::variable_fonts::OwnedTuple;
-use allsorts::tables::{FontTableProvider, SfntVersion};
+use allsorts::tables::{FontTableProvider, HheaTable, SfntVersion};
 use allsorts::tag;
 use allsorts::tinyvec::tiny_vec;
 
 use crate::cli::ViewOpts;
-use crate::writer::{NamedOutliner, SVGMode, SVGWriter};
+use crate::svg_table::SvgTable;
+use crate::writer::{Canvas, ImageFormat, NamedOutliner, SVGMode, SVGWriter};
 use crate::BoxError;
-use crate::{normalise_tuple, parse_tuple, script};
+use crate::{
+    add_to_mask, normalise_tuple, parse_features, parse_tuple, read_features_file, script,
+    substitute_unmapped, write_svg, write_svgz,
+};
+
+mod grid;
+
+pub(crate) const FONT_SIZE: f32 = 1000.0;
+
+const SVG: u32 = allsorts::tag!(b"SVG ");
 
-const FONT_SIZE: f32 = 1000.0;
+/// Number of columns in the `--all-glyphs` grid when `--columns` isn't supplied
+const DEFAULT_COLUMNS: u16 = 16;
 
 pub fn main(opts: ViewOpts) -> Result<i32, BoxError> {
-    let script = tag::from_string(&opts.script)?;
+    let format = opts.format.unwrap_or_default();
+    if format == ImageFormat::Png {
+        if opts.output.is_none() {
+            eprintln!("--format png requires --output, not writing binary data to stdout");
+            return Ok(1);
+        }
+        if opts.all_glyphs {
+            eprintln!("--format png can't be combined with --all-glyphs");
+            return Ok(1);
+        }
+    }
+    if gzip(&opts) {
+        if format == ImageFormat::Png {
+            eprintln!("--gzip can't be combined with --format png");
+            return Ok(1);
+        }
+        if opts.output.is_none() && io::stdout().is_terminal() {
+            eprintln!("Not printing gzip compressed data to tty.");
+            return Ok(1);
+        }
+    }
+
+    if opts.per_glyph_dir.is_some() && opts.all_glyphs {
+        eprintln!("--per-glyph-dir can't be combined with --all-glyphs");
+        return Ok(1);
+    }
+
+    if opts.all_glyphs {
+        return view_all_glyphs(&opts);
+    }
+
+    let labels = opts.label_indices || opts.label_unicodes;
+    if opts.vertical && (opts.waterfall.is_some() || opts.guides || labels) {
+        eprintln!("--vertical can't be combined with --waterfall, --guides, or glyph labels");
+        return Ok(1);
+    }
+
+    if opts.width == Some(0) || opts.height == Some(0) {
+        eprintln!("--width and --height must be greater than zero");
+        return Ok(1);
+    }
+
+    let script = match &opts.script {
+        Some(script) => tag::from_string(script)?,
+        None => {
+            eprintln!("required option: --script");
+            return Ok(1);
+        }
+    };
     let lang = opts
         .lang
         .as_deref()
@@ -36,15 +102,47 @@ pub fn main(opts: ViewOpts) -> Result<i32, BoxError> {
         }
     }
 
-    let features = match opts.features {
-        Some(ref features) => parse_features(&features),
-        None => Features::Mask(FeatureMask::default()),
+    let features = match (&opts.features, &opts.features_file, &opts.disable) {
+        (Some(features), None, None) => parse_features(features)?,
+        (None, Some(path), None) => read_features_file(path)?,
+        (None, None, Some(disabled)) => {
+            let disabled = disabled
+                .split(',')
+                .map(|feature| format!("-{}", feature.trim()))
+                .collect::<Vec<_>>();
+            parse_features(&disabled.join(","))?
+        }
+        (None, None, None) => Features::Mask(FeatureMask::default()),
+        (_, _, _) => {
+            eprintln!("only one of --features OR --features-file OR --disable may be supplied");
+            return Ok(1);
+        }
     };
+    let diff_features = opts
+        .diff_features
+        .as_deref()
+        .map(|added| add_features(&features, added))
+        .transpose()?;
+    if opts.diff_show_base && diff_features.is_none() {
+        eprintln!("--diff-show-base requires --diff-features");
+        return Ok(1);
+    }
 
     let buffer = std::fs::read(&opts.font)?;
     let scope = ReadScope::new(&buffer);
     let font_file = scope.read::<FontData<'_>>()?;
-    let provider = font_file.table_provider(0)?;
+
+    // The primary font is index 0, followed by the fallback fonts
+    let fallback_buffers = opts
+        .fallback
+        .iter()
+        .map(std::fs::read)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut font_files = vec![font_file];
+    for buffer in &fallback_buffers {
+        font_files.push(ReadScope::new(buffer).read::<FontData<'_>>()?);
+    }
+    let provider = font_files[0].table_provider(0)?;
 
     let user_tuple = opts.tuple.as_deref().map(parse_tuple).transpose()?;
     let tuple = match user_tuple {
@@ -58,47 +156,303 @@ pub fn main(opts: ViewOpts) -> Result<i32, BoxError> {
         None => None,
     };
 
-    let mut font = Font::new(provider)?;
+    let mut fonts = font_files
+        .iter()
+        .map(|font_file| font_file.table_provider(0).and_then(Font::new))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let glyphs = if let Some(ref text) = opts.text {
-        font.map_glyphs(&text, script, MatchingPresentation::NotRequired)
+    let text = if let Some(ref text) = opts.text {
+        Some(normalise_spaces(
+            text,
+            opts.trim_spaces,
+            opts.collapse_spaces,
+        ))
     } else if let Some(ref codepoints) = opts.codepoints {
         let text = parse_codepoints(&codepoints);
-        font.map_glyphs(&text, script, MatchingPresentation::NotRequired)
+        Some(normalise_spaces(
+            &text,
+            opts.trim_spaces,
+            opts.collapse_spaces,
+        ))
+    } else {
+        None
+    };
+    let runs = if let Some(text) = &text {
+        let mut warned = false;
+        font_runs(&mut fonts, text, script)
+            .into_iter()
+            .map(|(font_index, text)| {
+                let mut glyphs =
+                    fonts[font_index].map_glyphs(&text, script, MatchingPresentation::NotRequired);
+                // Characters no font maps end up in runs of the primary font
+                if let (0, Some(fallback)) = (font_index, opts.fallback_codepoint) {
+                    let substituted =
+                        substitute_unmapped(&mut fonts[0], &mut glyphs, fallback, script);
+                    if substituted.is_none() && !warned {
+                        warned = true;
+                        eprintln!(
+                            "warning: U+{:04X} is not mapped by the font, leaving .notdef in place",
+                            fallback as u32
+                        );
+                    }
+                }
+                (font_index, glyphs)
+            })
+            .collect::<Vec<_>>()
     } else if let Some(ref indices) = opts.indices {
-        parse_glyph_indices(&indices)
+        vec![(0, parse_glyph_indices(&indices))]
     } else {
         panic!("expected --text OR --codepoints OR --indices");
     };
 
-    let infos = font
-        .shape(
-            glyphs,
-            script,
-            lang,
-            &features,
-            tuple.as_ref().map(OwnedTuple::as_tuple),
-            true,
-        )
-        .map_err(|(err, _infos)| err)?;
-    let direction = script::direction(script);
-
-    // TODO: Can we avoid creating a new table provider?
-    let provider = font_file.table_provider(0)?;
+    let mut shape_runs = |features: &Features| -> Result<Vec<(usize, Vec<Info>)>, BoxError> {
+        let mut shaped_runs = Vec::with_capacity(runs.len());
+        for &(font_index, ref glyphs) in &runs {
+            // The variation tuple only applies to the primary font
+            let tuple = match font_index {
+                0 => tuple.as_ref().map(OwnedTuple::as_tuple),
+                _ => None,
+            };
+            let infos = fonts[font_index]
+                .shape(glyphs.clone(), script, lang, features, tuple, true)
+                .map_err(|(err, _infos)| err)?;
+            shaped_runs.push((font_index, infos));
+        }
+        Ok(shaped_runs)
+    };
+    let shaped_runs = shape_runs(&features)?;
+    // The text shaped with the added features is drawn over the base, highlighting the changes
+    let diff_runs = diff_features
+        .as_ref()
+        .map(|diff_features| shape_runs(diff_features))
+        .transpose()?;
+    let direction = script::direction_or(script, opts.direction);
 
     // Turn each glyph into an SVG...
-    let head = font.head_table()?.ok_or(ParseError::MissingValue)?;
-    let scale = FONT_SIZE / f32::from(head.units_per_em);
-    let transform = Matrix2x2F::from_scale(vec2f(scale, -scale));
+    let head = fonts[0].head_table()?.ok_or(ParseError::MissingValue)?;
+    let units_per_em = f32::from(head.units_per_em);
+    let scale = opts.font_size.unwrap_or(FONT_SIZE) / units_per_em;
+    let rotation = Matrix2x2F::from_rotation(opts.rotate.unwrap_or(0.).to_radians());
+    let transform = rotation * Matrix2x2F::from_scale(vec2f(scale, -scale));
     let mode = SVGMode::from(&opts);
-    let svg = if font.glyph_table_flags.contains(GlyphTableFlags::CFF)
-        && provider.sfnt_version() == tag::OTTO
+    let mut writer = SVGWriter::new(mode, transform);
+    if let Some(svg_data) = provider.table_data(SVG)? {
+        writer.set_svg_table(SvgTable::read(&svg_data)?);
+    }
+    if opts.vertical {
+        let (ascent, descent) = column_extents(&provider, &fonts[0])?;
+        writer.set_vertical(ascent, descent);
+    }
+    if !opts.no_embed_params {
+        writer.set_params(params(&opts, head.check_sum_adjustment, direction));
+    }
+    match &text {
+        Some(text) if opts.selectable_text => writer.set_selectable_text(text),
+        None if opts.selectable_text => {
+            eprintln!("--selectable-text requires --text OR --codepoints");
+            return Ok(1);
+        }
+        _ => {}
+    }
+    let mut layers = vec![&shaped_runs];
+    layers.extend(&diff_runs);
+    for (layer, shaped_runs) in layers.into_iter().enumerate() {
+        if layer > 0 {
+            writer.start_comparison(f32::from(opts.diff_tolerance), opts.diff_show_base);
+        }
+        let ordered_runs = match direction {
+            TextDirection::LeftToRight => shaped_runs.iter().collect::<Vec<_>>(),
+            TextDirection::RightToLeft => shaped_runs.iter().rev().collect(),
+        };
+        for (font_index, infos) in ordered_runs {
+            // TODO: Can we avoid creating a new table provider?
+            let provider = font_files[*font_index].table_provider(0)?;
+            let font = &mut fonts[*font_index];
+            let head = font.head_table()?.ok_or(ParseError::MissingValue)?;
+            let run_scale = units_per_em / f32::from(head.units_per_em);
+            // The variation tuple only applies to the primary font
+            let run_tuple = match font_index {
+                0 => tuple.as_ref(),
+                _ => None,
+            };
+            if !add_run(
+                &mut writer,
+                font,
+                &provider,
+                infos,
+                direction,
+                *font_index,
+                run_scale,
+                run_tuple,
+            )? {
+                eprintln!("no glyf, CFF, or CFF2 table");
+                return Ok(1);
+            }
+        }
+    }
+
+    writer.warn_sanitised_ids();
+    if let Some(dir) = &opts.per_glyph_dir {
+        std::fs::create_dir_all(dir)?;
+        for (file_name, contents) in writer.per_glyph_files()? {
+            std::fs::write(Path::new(dir).join(file_name), contents)?;
+        }
+    }
+
+    match (format, &opts.output) {
+        (ImageFormat::Png, Some(output)) => std::fs::write(output, writer.finish_png()?)?,
+        _ => write_view_svg(&writer.finish(), &opts)?,
+    }
+
+    Ok(0)
+}
+
+/// Whether to write the SVG gzip compressed, with `--gzip` or an `.svgz` output file
+fn gzip(opts: &ViewOpts) -> bool {
+    opts.gzip
+        || opts.output.as_deref().map_or(false, |output| {
+            output.to_ascii_lowercase().ends_with(".svgz")
+        })
+}
+
+fn write_view_svg(svg: &str, opts: &ViewOpts) -> Result<(), BoxError> {
+    if gzip(opts) {
+        write_svgz(svg, opts.output.as_deref())
+    } else {
+        write_svg(svg, opts.output.as_deref())
+    }
+}
+
+/// The extent either side of the centre line of a vertical column, from vhea if the font has one,
+/// otherwise the hhea ascender and descender split evenly
+fn column_extents<F: FontTableProvider>(
+    provider: &impl FontTableProvider,
+    font: &Font<F>,
+) -> Result<(f32, f32), BoxError> {
+    match provider.table_data(tag::VHEA)? {
+        Some(data) => {
+            // vhea has the same layout as hhea
+            let vhea = ReadScope::new(&data).read::<HheaTable>()?;
+            Ok((f32::from(vhea.ascender), f32::from(vhea.descender)))
+        }
+        None => {
+            let height = f32::from(font.hhea_table.ascender) - f32::from(font.hhea_table.descender);
+            Ok((height / 2., -height / 2.))
+        }
+    }
+}
+
+/// The options that determine the glyphs and their positions, to record in the SVG metadata
+fn params(
+    opts: &ViewOpts,
+    check_sum_adjustment: u32,
+    direction: TextDirection,
+) -> Vec<(&'static str, String)> {
+    let font_name = Path::new(&opts.font).file_name().map_or_else(
+        || opts.font.clone(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    let mut params = vec![
+        (
+            "tool",
+            format!("allsorts-tools {}", env!("CARGO_PKG_VERSION")),
+        ),
+        ("font", font_name),
+        ("font-checksum", format!("0x{:08x}", check_sum_adjustment)),
+    ];
+    let optional = [
+        ("script", &opts.script),
+        ("lang", &opts.lang),
+        ("features", &opts.features),
+        ("features-file", &opts.features_file),
+        ("disable", &opts.disable),
+        ("tuple", &opts.tuple),
+        ("text", &opts.text),
+        ("codepoints", &opts.codepoints),
+        ("indices", &opts.indices),
+    ];
+    params.extend(
+        optional
+            .iter()
+            .filter_map(|&(key, value)| value.clone().map(|value| (key, value))),
+    );
+    params.push((
+        "direction",
+        String::from(match direction {
+            TextDirection::LeftToRight => "ltr",
+            TextDirection::RightToLeft => "rtl",
+        }),
+    ));
+    if opts.vertical {
+        params.push(("vertical", String::from("true")));
+    }
+    params
+}
+
+fn view_all_glyphs(opts: &ViewOpts) -> Result<i32, BoxError> {
+    let columns = opts.columns.unwrap_or(DEFAULT_COLUMNS);
+    if columns == 0 {
+        eprintln!("--columns must be greater than zero");
+        return Ok(1);
+    }
+
+    let buffer = std::fs::read(&opts.font)?;
+    let scope = ReadScope::new(&buffer);
+    let font_file = scope.read::<FontData<'_>>()?;
+    let provider = font_file.table_provider(0)?;
+    let mut font = Font::new(font_file.table_provider(0)?)?;
+    match grid::render(&mut font, &provider, columns)? {
+        Some(svg) => {
+            write_view_svg(&svg, opts)?;
+            Ok(0)
+        }
+        None => {
+            eprintln!("no glyf, CFF, or CFF2 table");
+            Ok(1)
+        }
+    }
+}
+
+/// Add a run of glyphs shaped with `font` to `writer`, reading the outlines from `provider`.
+///
+/// CFF2 outlines are instanced at `tuple`. Returns `false` if the font has no glyf, CFF, or CFF2
+/// table.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn add_run<'info, F: FontTableProvider>(
+    writer: &mut SVGWriter<'info>,
+    font: &mut Font<F>,
+    provider: &(impl FontTableProvider + SfntVersion),
+    infos: &'info [Info],
+    direction: TextDirection,
+    font_index: usize,
+    scale: f32,
+    tuple: Option<&OwnedTuple>,
+) -> Result<bool, BoxError> {
+    if font.glyph_table_flags.contains(GlyphTableFlags::CFF) && provider.sfnt_version() == tag::OTTO
     {
         let cff_data = provider.read_table_data(tag::CFF)?;
         let mut cff = ReadScope::new(&cff_data).read::<CFF<'_>>()?;
-        let writer = SVGWriter::new(mode, transform);
-        writer.glyphs_to_svg(&mut cff, &mut font, &infos, direction)?
+        writer.add_run(&mut cff, font, infos, direction, font_index, scale)?;
+    } else if font.glyph_table_flags.contains(GlyphTableFlags::CFF2)
+        && provider.sfnt_version() == tag::OTTO
+    {
+        let cff_data = provider.read_table_data(tag::CFF2)?;
+        let cff = ReadScope::new(&cff_data).read::<CFF2<'_>>()?;
+        let post_data = provider.table_data(tag::POST)?;
+        let post = post_data
+            .as_ref()
+            .map(|data| ReadScope::new(data).read::<PostTable<'_>>())
+            .transpose()?;
+        let mut cff2_post = NamedOutliner {
+            table: CFF2Outlines {
+                table: &cff,
+                tuple: tuple.map(OwnedTuple::as_tuple),
+            },
+            post,
+        };
+        writer.add_run(&mut cff2_post, font, infos, direction, font_index, scale)?;
     } else if font.glyph_table_flags.contains(GlyphTableFlags::GLYF) {
+        let head = font.head_table()?.ok_or(ParseError::MissingValue)?;
         let loca_data = provider.read_table_data(tag::LOCA)?;
         let loca = ReadScope::new(&loca_data).read_dep::<LocaTable<'_>>((
             usize::from(font.maxp_table.num_glyphs),
@@ -112,16 +466,79 @@ pub fn main(opts: ViewOpts) -> Result<i32, BoxError> {
             .map(|data| ReadScope::new(data).read::<PostTable<'_>>())
             .transpose()?;
         let mut glyf_post = NamedOutliner { table: glyf, post };
-        let writer = SVGWriter::new(mode, transform);
-        writer.glyphs_to_svg(&mut glyf_post, &mut font, &infos, direction)?
+        writer.add_run(&mut glyf_post, font, infos, direction, font_index, scale)?;
     } else {
-        eprintln!("no glyf or CFF table");
-        return Ok(1);
-    };
+        return Ok(false);
+    }
+    Ok(true)
+}
 
-    println!("{}", svg);
+/// Split `text` into runs, each paired with the index of the first font in `fonts` that maps
+/// every character in it.
+///
+/// Characters that no font maps are left in the primary font.
+fn font_runs<F: FontTableProvider>(
+    fonts: &mut [Font<F>],
+    text: &str,
+    script: u32,
+) -> Vec<(usize, String)> {
+    if fonts.len() == 1 {
+        return vec![(0, text.to_string())];
+    }
 
-    Ok(0)
+    let mut runs: Vec<(usize, String)> = Vec::new();
+    for ch in text.chars() {
+        let font_index = match runs.last() {
+            // Keep joiners and variation selectors with the character before them
+            Some((font_index, _)) if is_joiner_or_selector(ch) => *font_index,
+            _ => fonts
+                .iter_mut()
+                .position(|font| maps_char(font, ch, script))
+                .unwrap_or(0),
+        };
+        match runs.last_mut() {
+            Some((index, run)) if *index == font_index => run.push(ch),
+            _ => runs.push((font_index, ch.to_string())),
+        }
+    }
+    runs
+}
+
+fn maps_char<F: FontTableProvider>(font: &mut Font<F>, ch: char, script: u32) -> bool {
+    let mut buf = [0; 4];
+    font.map_glyphs(
+        ch.encode_utf8(&mut buf),
+        script,
+        MatchingPresentation::NotRequired,
+    )
+    .iter()
+    .all(|glyph| glyph.glyph_index != 0)
+}
+
+fn is_joiner_or_selector(ch: char) -> bool {
+    matches!(ch, '\u{200D}' | '\u{FE00}'..='\u{FE0F}' | '\u{E0100}'..='\u{E01EF}')
+}
+
+fn normalise_spaces(text: &str, trim: bool, collapse: bool) -> String {
+    let text = if trim { text.trim() } else { text };
+    if collapse {
+        let mut collapsed = String::with_capacity(text.len());
+        let mut prev_space = false;
+        for ch in text.chars() {
+            if ch.is_whitespace() {
+                if !prev_space {
+                    collapsed.push(' ');
+                }
+                prev_space = true;
+            } else {
+                collapsed.push(ch);
+                prev_space = false;
+            }
+        }
+        collapsed
+    } else {
+        text.to_string()
+    }
 }
 
 fn parse_codepoints(codepoints: &str) -> String {
@@ -163,17 +580,24 @@ fn make_raw_glyph(glyph_index: u16) -> RawGlyph<()> {
     }
 }
 
-fn parse_features(features: &str) -> Features {
-    let feature_infos = features
+/// The features to shape with for `--diff-features`, `features` with each of the comma-separated
+/// `added` features enabled
+fn add_features(features: &Features, added: &str) -> Result<Features, BoxError> {
+    let tags = added
         .split(',')
-        .map(str::trim)
-        .map(|s| tag::from_string(s).expect(format!("invalid feature '{}'", s).as_str()))
-        .map(|f| FeatureInfo {
-            feature_tag: f,
-            alternate: None,
-        })
-        .collect();
-    Features::Custom(feature_infos)
+        .map(|s| tag::from_string(s.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match features {
+        Features::Custom(feature_infos) => {
+            let mut feature_infos = feature_infos.clone();
+            feature_infos.extend(tags.into_iter().map(|feature_tag| FeatureInfo {
+                feature_tag,
+                alternate: None,
+            }));
+            Ok(Features::Custom(feature_infos))
+        }
+        Features::Mask(mask) => Ok(add_to_mask(*mask, &tags)),
+    }
 }
 
 impl From<&ViewOpts> for SVGMode {
@@ -181,8 +605,35 @@ impl From<&ViewOpts> for SVGMode {
         SVGMode::View {
             mark_origin: opts.mark_origin,
             margin: opts.margin.unwrap_or_default(),
+            trim_advance: opts.trim_advance,
             fg: opts.fg_colour.or(opts.fg_color),
             bg: opts.bg_colour.or(opts.bg_color),
+            waterfall: opts
+                .waterfall
+                .as_ref()
+                .map(|waterfall| waterfall.scales(opts.font_size.unwrap_or(FONT_SIZE))),
+            show_invisible: opts.show_invisible,
+            tint_fallbacks: opts.tint_fallbacks,
+            legend: opts.legend,
+            show_cursive: opts.show_cursive,
+            show_anchors: opts.show_anchors,
+            show_points: opts.show_points,
+            show_bboxes: opts.show_bboxes,
+            guides: opts.guides,
+            label_indices: opts.label_indices,
+            label_unicodes: opts.label_unicodes,
+            inline_paths: opts.inline_paths,
+            precision: opts.precision,
+            colour_by_cluster: opts.colour_by_cluster,
+            pixel_size: opts.font_size.is_some(),
+            canvas: match (opts.width, opts.height) {
+                (None, None) => None,
+                (width, height) => Some(Canvas {
+                    width,
+                    height,
+                    align: opts.align.unwrap_or_default(),
+                }),
+            },
         }
     }
 }
@@ -0,0 +1,228 @@
+//! A proof sheet of every glyph in a font, laid out in a grid.
+//!
+//! Unlike text, where glyphs are shared through `<symbol>` elements, each glyph appears once so
+//! its path is written straight into its cell, reusing one buffer for the outlines.
+
+use allsorts::binary::read::ReadScope;
+use allsorts::cff::cff2::CFF2;
+use allsorts::cff::outline::CFF2Outlines;
+use allsorts::cff::CFF;
+use allsorts::error::ParseError;
+use allsorts::font::{Font, GlyphTableFlags};
+use allsorts::outline::OutlineBuilder;
+use allsorts::post::PostTable;
+use allsorts::tables::glyf::GlyfTable;
+use allsorts::tables::loca::LocaTable;
+use allsorts::tables::{FontTableProvider, SfntVersion};
+use allsorts::tag;
+use xmlwriter::XmlWriter;
+
+use crate::writer::{GlyphName, NamedOutliner, PathSink};
+use crate::BoxError;
+
+/// Width and height of each cell
+const CELL_SIZE: f32 = 120.;
+
+/// Space between the edges of a cell and its contents
+const CELL_PADDING: f32 = 8.;
+
+/// Height reserved at the bottom of each cell for the label
+const LABEL_HEIGHT: f32 = 14.;
+
+const LABEL_FONT_SIZE: f32 = 10.;
+
+/// Render every glyph of `font` into a grid with `columns` cells per row.
+///
+/// CFF2 outlines are drawn at the default instance. Returns `None` if the font has no glyf, CFF, or
+/// CFF2 table.
+pub(super) fn render<F: FontTableProvider>(
+    font: &mut Font<F>,
+    provider: &(impl FontTableProvider + SfntVersion),
+    columns: u16,
+) -> Result<Option<String>, BoxError> {
+    let grid = Grid {
+        num_glyphs: font.maxp_table.num_glyphs,
+        columns,
+        ascender: f32::from(font.hhea_table.ascender),
+        descender: f32::from(font.hhea_table.descender),
+    };
+    if font.glyph_table_flags.contains(GlyphTableFlags::CFF) && provider.sfnt_version() == tag::OTTO
+    {
+        let cff_data = provider.read_table_data(tag::CFF)?;
+        let mut cff = ReadScope::new(&cff_data).read::<CFF<'_>>()?;
+        grid.render(&mut cff).map(Some)
+    } else if font.glyph_table_flags.contains(GlyphTableFlags::CFF2)
+        && provider.sfnt_version() == tag::OTTO
+    {
+        let cff_data = provider.read_table_data(tag::CFF2)?;
+        let cff = ReadScope::new(&cff_data).read::<CFF2<'_>>()?;
+        let post_data = provider.table_data(tag::POST)?;
+        let post = post_data
+            .as_ref()
+            .map(|data| ReadScope::new(data).read::<PostTable<'_>>())
+            .transpose()?;
+        let mut cff2_post = NamedOutliner {
+            table: CFF2Outlines {
+                table: &cff,
+                tuple: None,
+            },
+            post,
+        };
+        grid.render(&mut cff2_post).map(Some)
+    } else if font.glyph_table_flags.contains(GlyphTableFlags::GLYF) {
+        let head = font.head_table()?.ok_or(ParseError::MissingValue)?;
+        let loca_data = provider.read_table_data(tag::LOCA)?;
+        let loca = ReadScope::new(&loca_data).read_dep::<LocaTable<'_>>((
+            usize::from(font.maxp_table.num_glyphs),
+            head.index_to_loc_format,
+        ))?;
+        let glyf_data = provider.read_table_data(tag::GLYF)?;
+        let glyf = ReadScope::new(&glyf_data).read_dep::<GlyfTable<'_>>(&loca)?;
+        let post_data = provider.table_data(tag::POST)?;
+        let post = post_data
+            .as_ref()
+            .map(|data| ReadScope::new(data).read::<PostTable<'_>>())
+            .transpose()?;
+        let mut glyf_post = NamedOutliner { table: glyf, post };
+        grid.render(&mut glyf_post).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+struct Grid {
+    num_glyphs: u16,
+    columns: u16,
+    ascender: f32,
+    descender: f32,
+}
+
+impl Grid {
+    fn render<T>(&self, builder: &mut T) -> Result<String, BoxError>
+    where
+        T: OutlineBuilder + GlyphName,
+    {
+        let columns = u32::from(self.columns);
+        let rows = (u32::from(self.num_glyphs) + columns - 1) / columns;
+        let width = columns as f32 * CELL_SIZE;
+        let height = rows as f32 * CELL_SIZE;
+
+        let mut w = XmlWriter::new(xmlwriter::Options::default());
+        w.write_declaration();
+        w.start_element("svg");
+        w.write_attribute("version", "1.1");
+        w.write_attribute("xmlns", "http://www.w3.org/2000/svg");
+        w.write_attribute("viewBox", &format!("0 0 {} {}", width, height));
+        w.write_attribute("width", &width);
+        w.write_attribute("height", &height);
+        w.start_element("rect");
+        w.write_attribute("width", &width);
+        w.write_attribute("height", &height);
+        w.write_attribute("fill", "white");
+        w.end_element();
+
+        let mut sink = PathSink::default();
+        for glyph_id in 0..self.num_glyphs {
+            sink.clear();
+            builder
+                .visit(glyph_id, &mut sink)
+                .map_err(|err| format!("error building SVG for glyph {}: {}", glyph_id, err))?;
+            let x = f32::from(glyph_id % self.columns) * CELL_SIZE;
+            let y = f32::from(glyph_id / self.columns) * CELL_SIZE;
+            let glyph_name = builder.gid_to_glyph_name(glyph_id);
+            self.write_cell(&mut w, glyph_id, glyph_name.as_deref(), &sink, x, y);
+        }
+
+        Ok(w.end_document())
+    }
+
+    fn write_cell(
+        &self,
+        w: &mut XmlWriter,
+        glyph_id: u16,
+        glyph_name: Option<&str>,
+        sink: &PathSink,
+        x: f32,
+        y: f32,
+    ) {
+        w.start_element("g");
+        w.write_attribute("data-glyph-index", &glyph_id);
+        if let Some(glyph_name) = glyph_name {
+            w.write_attribute("data-glyph-name", glyph_name);
+        }
+
+        w.start_element("rect");
+        w.write_attribute("x", &x);
+        w.write_attribute("y", &y);
+        w.write_attribute("width", &CELL_SIZE);
+        w.write_attribute("height", &CELL_SIZE);
+        w.write_attribute("fill", "none");
+        w.write_attribute("stroke", "lightgrey");
+        w.end_element();
+
+        let area_x = x + CELL_PADDING;
+        let area_y = y + CELL_PADDING;
+        let area_width = CELL_SIZE - 2. * CELL_PADDING;
+        let area_height = CELL_SIZE - 2. * CELL_PADDING - LABEL_HEIGHT;
+        match sink.bounds {
+            Some((min, max)) => {
+                // Glyphs within the ascender and descender share a scale and baseline, others
+                // are shrunk to fit
+                let top = max.y().max(self.ascender);
+                let bottom = min.y().min(self.descender);
+                let glyph_width = (max.x() - min.x()).max(1.);
+                let scale = (area_height / (top - bottom)).min(area_width / glyph_width);
+                let translate_x =
+                    area_x + (area_width - glyph_width * scale) / 2. - min.x() * scale;
+                let translate_y =
+                    area_y + (area_height - (top - bottom) * scale) / 2. + top * scale;
+                w.start_element("path");
+                w.write_attribute(
+                    "transform",
+                    &format!(
+                        "matrix({} 0 0 {} {} {})",
+                        scale, -scale, translate_x, translate_y
+                    ),
+                );
+                w.write_attribute("d", sink.path.trim_start());
+                w.end_element();
+            }
+            None => {
+                // Cross out the cells of glyphs without an outline
+                w.start_element("path");
+                w.write_attribute("data-empty", "true");
+                w.write_attribute(
+                    "d",
+                    &format!(
+                        "M{},{} L{},{} M{},{} L{},{}",
+                        area_x,
+                        area_y,
+                        area_x + area_width,
+                        area_y + area_height,
+                        area_x + area_width,
+                        area_y,
+                        area_x,
+                        area_y + area_height
+                    ),
+                );
+                w.write_attribute("stroke", "red");
+                w.write_attribute("stroke-dasharray", "4");
+                w.end_element();
+            }
+        }
+
+        w.start_element("text");
+        w.write_attribute("x", &(x + CELL_SIZE / 2.));
+        w.write_attribute("y", &(y + CELL_SIZE - CELL_PADDING));
+        w.write_attribute("font-family", "sans-serif");
+        w.write_attribute("font-size", &LABEL_FONT_SIZE);
+        w.write_attribute("text-anchor", "middle");
+        match glyph_name {
+            Some(glyph_name) => w.write_text(&format!("{} {}", glyph_id, glyph_name)),
+            None => w.write_text(&glyph_id.to_string()),
+        }
+        w.end_element();
+
+        w.end_element();
+    }
+}
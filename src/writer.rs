@@ -1,6 +1,6 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter, Write};
 use std::str::FromStr;
 
 use allsorts::cff::CFF;
@@ -15,15 +15,120 @@ use allsorts::pathfinder_geometry::vector::{vec2f, Vector2F, Vector2I};
 use allsorts::post::PostTable;
 use allsorts::tables::FontTableProvider;
 use allsorts::Font;
+use serde::Serialize;
 use xmlwriter::XmlWriter;
 
+use self::raster::Segment;
+use crate::svg_table::{glyph_element_id, SvgTable};
 use crate::BoxError;
 
+mod raster;
+
+/// Width in font units of the marker drawn for zero advance glyphs
+const ZERO_ADVANCE_MARKER_WIDTH: f32 = 100.;
+
+/// Radius in font units of the markers drawn at the ends of cursive attachments
+const CURSIVE_MARKER_RADIUS: f32 = 30.;
+
+/// Height in font units that the curve joining cursive attachment anchors rises to
+const CURSIVE_CURVE_HEIGHT: f32 = 200.;
+
+/// Colour of the markers and curves drawn for cursive attachments
+const CURSIVE_COLOUR: &str = "darkorange";
+
+/// Colour of the lines joining marks to the anchors of their base glyphs
+const ANCHOR_COLOUR: &str = "teal";
+
+/// Radius in font units of the markers drawn at the start point of each contour
+const CONTOUR_MARKER_RADIUS: f32 = 20.;
+
+/// Length in font units of the arrows showing the direction of each contour
+const CONTOUR_ARROW_LENGTH: f32 = 120.;
+
+/// Colour of the contour start point markers and direction arrows
+const CONTOUR_COLOUR: &str = "mediumvioletred";
+
+/// Colour of the bounding boxes drawn around each glyph
+const BBOX_COLOUR: &str = "orchid";
+
+/// Name of the elements written in place of the `SVG ` table documents until they're embedded
+const SVG_DOCUMENT_PLACEHOLDER: &str = "allsorts-svg-document";
+
+/// Colour of the baseline, ascender, and descender guide lines
+const GUIDE_COLOUR: &str = "deepskyblue";
+
+/// Size of the glyph index and Unicode labels, as a fraction of the height from descender to
+/// ascender
+const LABEL_SIZE: f32 = 0.12;
+
+/// Colour of the glyph index and Unicode labels
+const LABEL_COLOUR: &str = "dimgray";
+
+/// Fill of the glyphs that changed from the base runs of a comparison
+const DIFF_COLOUR: &str = "crimson";
+
+/// Opacity of the base runs of a comparison when drawn behind the runs compared with them
+const DIFF_BASE_OPACITY: f32 = 0.25;
+
+/// Namespace of the element in `<metadata>` that records the parameters of `view`
+const PARAMS_NAMESPACE: &str = "https://github.com/yeslogic/allsorts-tools";
+
+/// Fill colours for the clusters of `colour_by_cluster`, cycled through so that neighbouring
+/// clusters differ
+const CLUSTER_COLOURS: [&str; 8] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#17becf",
+];
+
+/// The character shapers insert a glyph for to carry a mark that has no base to attach to
+const DOTTED_CIRCLE: char = '\u{25CC}';
+
+/// Fill of the dotted circles inserted by the shaper
+const DOTTED_CIRCLE_COLOUR: &str = "darkorange";
+
+/// Fill colours for glyphs from fallback fonts, cycled through by font index
+const FALLBACK_TINTS: [Colour; 4] = [
+    Colour {
+        r: 0xd6,
+        g: 0x27,
+        b: 0x28,
+        a: 0xff,
+    },
+    Colour {
+        r: 0x1f,
+        g: 0x77,
+        b: 0xb4,
+        a: 0xff,
+    },
+    Colour {
+        r: 0x2c,
+        g: 0xa0,
+        b: 0x2c,
+        a: 0xff,
+    },
+    Colour {
+        r: 0x94,
+        g: 0x67,
+        b: 0xbd,
+        a: 0xff,
+    },
+];
+
 struct Symbol<'info> {
     glyph_name: String,
+    /// The glyph name made safe for use as an XML id and CSS selector
+    view_id: String,
     path: String,
+    /// The segments of `path`, recorded in view mode for rasterising
+    outline: Vec<Segment>,
     info: &'info Info,
+    /// Index of the font the glyph came from, 0 is the primary font
+    font_index: usize,
     origin: Option<Vector2F>,
+    /// Leftmost and rightmost x of the outline, in units of the glyph's font
+    x_extent: Option<(f32, f32)>,
+    /// The index of the `SVG ` table document that draws the glyph instead of its outline, and
+    /// the transform from the document's coordinates to SVG coordinates
+    svg_glyph: Option<(usize, Matrix2x2F)>,
 }
 
 pub trait GlyphName {
@@ -122,6 +227,107 @@ impl Colour {
     }
 }
 
+/// The kind of image that `view` writes
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "svg" => Ok(ImageFormat::Svg),
+            "png" => Ok(ImageFormat::Png),
+            _ => Err(format!("invalid format '{}', expected svg or png", s)),
+        }
+    }
+}
+
+/// Where the text is placed in a canvas with a different aspect ratio, such as `top-left`
+///
+/// Each field is the fraction of the unused space that goes before the text, so 0 is left or top
+/// and 1 is right or bottom.
+#[derive(Debug, Copy, Clone)]
+pub struct Align {
+    pub horizontal: f32,
+    pub vertical: f32,
+}
+
+impl Default for Align {
+    fn default() -> Self {
+        Align {
+            horizontal: 0.5,
+            vertical: 0.5,
+        }
+    }
+}
+
+impl FromStr for Align {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut align = Align::default();
+        for part in s.split('-') {
+            match part {
+                "left" => align.horizontal = 0.,
+                "right" => align.horizontal = 1.,
+                "top" => align.vertical = 0.,
+                "bottom" => align.vertical = 1.,
+                "centre" | "center" | "middle" => {}
+                _ => {
+                    return Err(format!(
+                        "invalid alignment '{}', expected combinations of top, bottom, left, \
+                         right, and centre such as top-left",
+                        part
+                    ))
+                }
+            }
+        }
+        Ok(align)
+    }
+}
+
+/// A fixed size for the SVG that the text is fitted into
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Canvas {
+    /// Width in pixels, calculated from the height and the size of the text if `None`
+    pub width: Option<u32>,
+    /// Height in pixels, calculated from the width and the size of the text if `None`
+    pub height: Option<u32>,
+    pub align: Align,
+}
+
+/// A list of font sizes to render the text at, one line per size
+#[derive(Debug, Clone)]
+pub struct Waterfall(pub Vec<f32>);
+
+impl FromStr for Waterfall {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let sizes = s
+            .split(',')
+            .map(|part| part.trim().parse())
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|err| err.to_string())?;
+        if sizes.iter().any(|&size| size <= 0.) {
+            return Err(String::from("waterfall sizes must be greater than zero"));
+        }
+        Ok(Waterfall(sizes))
+    }
+}
+
+impl Waterfall {
+    /// The scale of each line relative to text rendered at `font_size`
+    pub fn scales(&self, font_size: f32) -> Vec<f32> {
+        self.0.iter().map(|size| size / font_size).collect()
+    }
+}
+
 struct ViewBox {
     x: i32,
     y: i32,
@@ -129,6 +335,67 @@ struct ViewBox {
     height: i32,
 }
 
+impl ViewBox {
+    /// Rotate the box about the origin by `theta` radians, returning the box that bounds the result
+    fn rotate(&self, theta: f32) -> ViewBox {
+        let rotation = Matrix2x2F::from_rotation(theta);
+        let (x, y) = (self.x as f32, self.y as f32);
+        let (width, height) = (self.width as f32, self.height as f32);
+        let corners = [
+            rotation * vec2f(x, y),
+            rotation * vec2f(x + width, y),
+            rotation * vec2f(x, y + height),
+            rotation * vec2f(x + width, y + height),
+        ];
+        let min = corners
+            .iter()
+            .skip(1)
+            .fold(corners[0], |min, &p| min.min(p));
+        let max = corners
+            .iter()
+            .skip(1)
+            .fold(corners[0], |max, &p| max.max(p));
+        ViewBox {
+            x: min.x().floor() as i32,
+            y: min.y().floor() as i32,
+            width: (max.x().ceil() - min.x().floor()) as i32,
+            height: (max.y().ceil() - min.y().floor()) as i32,
+        }
+    }
+
+    /// Grow the box to the aspect ratio of `canvas`, placing the original box according to its
+    /// alignment.
+    ///
+    /// Returns the new box and the width and height of the canvas.
+    fn fit(&self, canvas: &Canvas) -> (ViewBox, f32, f32) {
+        // A box with no width or height, such as one around only spaces, can't be scaled
+        let text = ViewBox {
+            width: self.width.max(1),
+            height: self.height.max(1),
+            ..*self
+        };
+        let aspect = text.width as f32 / text.height as f32;
+        let (width, height) = match (canvas.width, canvas.height) {
+            (Some(width), Some(height)) => (width as f32, height as f32),
+            (Some(width), None) => (width as f32, (width as f32 / aspect).round()),
+            (None, Some(height)) => ((height as f32 * aspect).round(), height as f32),
+            (None, None) => (text.width as f32, text.height as f32),
+        };
+        let canvas_aspect = width.max(1.) / height.max(1.);
+        let mut view_box = ViewBox { ..text };
+        if aspect < canvas_aspect {
+            view_box.width = (text.height as f32 * canvas_aspect).round() as i32;
+            let extra = (view_box.width - text.width) as f32;
+            view_box.x -= (extra * canvas.align.horizontal).round() as i32;
+        } else {
+            view_box.height = (text.width as f32 / canvas_aspect).round() as i32;
+            let extra = (view_box.height - text.height) as f32;
+            view_box.y -= (extra * canvas.align.vertical).round() as i32;
+        }
+        (view_box, width, height)
+    }
+}
+
 impl Display for ViewBox {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let ViewBox {
@@ -187,15 +454,160 @@ pub enum SVGMode {
     View {
         mark_origin: bool,
         margin: Margin,
+        /// End the text at the advance of the last glyph with an outline, leaving out trailing
+        /// spaces
+        trim_advance: bool,
         fg: Option<Colour>,
         bg: Option<Colour>,
+        /// When present the text is repeated on one line per entry, scaled by
+        /// that factor.
+        waterfall: Option<Vec<f32>>,
+        /// Draw a marker for glyphs with an empty outline or zero advance
+        show_invisible: bool,
+        /// Fill glyphs from fallback fonts with a distinct colour per font
+        tint_fallbacks: bool,
+        /// Add a key to the colours and markers used below the text
+        legend: bool,
+        /// Mark the anchors of cursive attachments, numbered by chain
+        show_cursive: bool,
+        /// Draw lines joining each attached mark to the anchor of its base glyph
+        show_anchors: bool,
+        /// Mark the start point of each contour with an arrow in the direction it's drawn
+        show_points: bool,
+        /// Draw the bounding box of each glyph placed
+        show_bboxes: bool,
+        /// Draw lines at the baseline, ascender, and descender
+        guides: bool,
+        /// Write the glyph index of each glyph below the descender
+        label_indices: bool,
+        /// Write the codepoints each glyph came from below the descender
+        label_unicodes: bool,
+        /// Write a path for every glyph placed instead of sharing a `<symbol>` between its uses
+        inline_paths: bool,
+        /// Decimal places of the glyph outline coordinates
+        precision: usize,
+        /// Fill the glyphs of each cluster of characters with a different colour
+        colour_by_cluster: bool,
+        /// Add width and height attributes so that the SVG is rendered at the size of its
+        /// view box in pixels
+        pixel_size: bool,
+        /// Fit the text into a canvas of this size, overriding `pixel_size`
+        canvas: Option<Canvas>,
     },
 }
 
-pub struct SVGWriter {
+pub struct SVGWriter<'info> {
     mode: SVGMode,
     transform: Matrix2x2F,
-    usage: Vec<(usize, Vector2F)>,
+    symbols: Symbols<'info>,
+    /// Index into `symbols` keyed by font index and glyph index
+    symbol_map: HashMap<(usize, u16), usize>,
+    /// The symbol, position, and glyph info of each glyph placed
+    usage: Vec<(usize, Vector2F, &'info Info)>,
+    /// Position and advance of glyphs that have no outline or zero advance
+    invisible: Vec<(Vector2F, f32)>,
+    /// Position of the next glyph, in units of the primary font
+    pen: Vector2F,
+    /// Position of the pen after the last glyph with an outline, in units of the primary font
+    inked_pen: f32,
+    /// Whether any of the glyphs placed is a dotted circle inserted by the shaper
+    inserted_dotted_circles: bool,
+    /// Largest ascender and smallest descender of the fonts used, in units of the primary font
+    metrics: Option<(f32, f32)>,
+    /// Leftmost and rightmost x of the glyph outlines as placed, in units of the primary font.
+    ///
+    /// Marks and glyphs with negative side bearings can extend past the advances, on either side.
+    ink_extent: Option<(f32, f32)>,
+    /// Text to include as a transparent, selectable layer behind the glyphs
+    text: Option<String>,
+    /// The parameters the SVG was generated with, written to its metadata
+    params: Vec<(&'static str, String)>,
+    /// The characters of each glyph in logical order, with the position of the glyph
+    text_positions: Vec<(char, Vector2F)>,
+    /// Cursive attachments between glyphs
+    cursive: Vec<CursiveJoin>,
+    /// Number of cursive attachment chains found so far
+    cursive_chains: usize,
+    /// Marks attached to base glyphs
+    anchor_lines: Vec<AnchorLine>,
+    /// For vertical layout, the extent of the column either side of its centre line, in units of
+    /// the primary font
+    vertical: Option<(f32, f32)>,
+    /// The runs that the glyphs of `usage` are compared with, if any
+    comparison: Option<Comparison<'info>>,
+    /// When colouring by cluster, the cluster of each glyph of `usage`
+    clusters: Vec<usize>,
+    /// Number of clusters in the runs added so far
+    cluster_count: usize,
+    /// Where each glyph of `usage` was placed by the layout, for writing a file per glyph
+    placements: Vec<GlyphPlacement>,
+    /// The `SVG ` table of the primary font, whose glyphs are drawn instead of their outlines
+    svg_table: Option<SvgTable>,
+}
+
+/// The layout of a glyph placed by `add_run`, as written to `positions.json` by
+/// `finish_per_glyph`.
+///
+/// The pen position, offsets, and advances are in units of the primary font, with y up, while
+/// `x` and `y` are the position of the glyph in the SVG of the text.
+#[derive(Serialize)]
+struct GlyphPlacement {
+    file: String,
+    glyph_index: u16,
+    glyph_name: String,
+    font_index: usize,
+    pen_x: f32,
+    pen_y: f32,
+    x_offset: f32,
+    y_offset: f32,
+    hori_advance: f32,
+    vert_advance: f32,
+    x: f32,
+    y: f32,
+}
+
+/// The fill of a use of a glyph that differs from the fill of its font
+struct UseFill {
+    colour: String,
+    /// The data attribute that says why the fill differs
+    data: (&'static str, String),
+}
+
+/// Runs kept to compare the runs added after them with, to highlight the glyphs that changed
+struct Comparison<'info> {
+    /// The symbol, position, and glyph info of each glyph of the base runs
+    usage: Vec<(usize, Vector2F, &'info Info)>,
+    /// Advance of the base runs, in units of the primary font
+    advance: f32,
+    /// How far, in SVG units, a glyph can move from the base and still be unchanged
+    tolerance: f32,
+    /// Draw the base runs faintly behind the runs compared with them
+    show_base: bool,
+}
+
+/// The anchors joined by a cursive attachment, transformed into SVG coordinates
+struct CursiveJoin {
+    /// 1-based number of the chain of attachments this join is part of
+    chain: usize,
+    exit: Vector2F,
+    entry: Vector2F,
+    control: Vector2F,
+}
+
+/// A mark attached to a base glyph, with the points joined by its line transformed into SVG
+/// coordinates
+struct AnchorLine {
+    base_origin: Vector2F,
+    base_anchor: Vector2F,
+    mark_anchor: Vector2F,
+    mark_origin: Vector2F,
+}
+
+/// An item in the key added to the SVG by the `legend` option
+struct LegendEntry {
+    colour: String,
+    dashed: bool,
+    label: String,
 }
 
 struct Symbols<'info> {
@@ -204,111 +616,519 @@ struct Symbols<'info> {
     mode: SVGMode,
     initial_move_to: Vector2I,
     last_line_to: Option<Vector2I>,
+    view_ids: HashSet<String>,
+    sanitised_ids: usize,
 }
 
-impl SVGWriter {
+impl<'info> SVGWriter<'info> {
     pub fn new(mode: SVGMode, transform: Matrix2x2F) -> Self {
         SVGWriter {
+            symbols: Symbols {
+                transform,
+                symbols: Vec::new(),
+                mode: mode.clone(),
+                initial_move_to: Vector2I::zero(),
+                last_line_to: None,
+                view_ids: HashSet::new(),
+                sanitised_ids: 0,
+            },
             mode,
             transform,
+            symbol_map: HashMap::new(),
             usage: Vec::new(),
+            invisible: Vec::new(),
+            pen: Vector2F::zero(),
+            inked_pen: 0.,
+            inserted_dotted_circles: false,
+            metrics: None,
+            ink_extent: None,
+            text: None,
+            params: Vec::new(),
+            text_positions: Vec::new(),
+            cursive: Vec::new(),
+            cursive_chains: 0,
+            anchor_lines: Vec::new(),
+            vertical: None,
+            comparison: None,
+            clusters: Vec::new(),
+            cluster_count: 0,
+            placements: Vec::new(),
+            svg_table: None,
         }
     }
 
+    /// Draw the glyphs of the primary font that are in `table` with their SVG documents rather
+    /// than their outlines
+    pub(crate) fn set_svg_table(&mut self, table: SvgTable) {
+        self.svg_table = Some(table);
+    }
+
+    /// Include `text` in the SVG so that it can be selected, copied, and searched
+    pub fn set_selectable_text(&mut self, text: &str) {
+        self.text = Some(text.to_string());
+    }
+
+    /// Record the parameters used to generate the SVG in its `<metadata>` so that it can be
+    /// reproduced
+    pub fn set_params(&mut self, params: Vec<(&'static str, String)>) {
+        self.params = params;
+    }
+
+    /// Lay the text out vertically, in a column that extends `ascent` to the right of its centre
+    /// line and `descent` (usually negative) to the left, in units of the primary font
+    pub fn set_vertical(&mut self, ascent: f32, descent: f32) {
+        self.vertical = Some((ascent, descent));
+    }
+
+    /// Keep the runs added so far as the base that the runs added after them are compared with.
+    ///
+    /// Glyphs of the later runs that aren't in the base at the same position, give or take
+    /// `tolerance` in units of the primary font, are highlighted. With `show_base` the base runs
+    /// are drawn faintly behind them.
+    pub fn start_comparison(&mut self, tolerance: f32, show_base: bool) {
+        self.comparison = Some(Comparison {
+            usage: std::mem::take(&mut self.usage),
+            advance: self.advance(),
+            tolerance: tolerance * self.transform.extract_scale().x(),
+            show_base,
+        });
+        // The later runs start again from the origin, with their own markers and text
+        self.pen = Vector2F::zero();
+        self.inked_pen = 0.;
+        self.invisible.clear();
+        self.text_positions.clear();
+        self.cursive.clear();
+        self.cursive_chains = 0;
+        self.anchor_lines.clear();
+        self.clusters.clear();
+        self.cluster_count = 0;
+        self.placements.clear();
+    }
+
     pub fn glyphs_to_svg<F, T>(
-        self,
+        mut self,
         builder: &mut T,
         font: &mut Font<F>,
-        infos: &[Info],
+        infos: &'info [Info],
         direction: TextDirection,
     ) -> Result<String, BoxError>
     where
         T: OutlineBuilder + GlyphName,
         F: FontTableProvider,
     {
-        let mut layout = GlyphLayout::new(font, infos, direction, false);
-        let glyph_positions = layout.glyph_positions()?;
+        self.add_run(builder, font, infos, direction, 0, 1.)?;
+        Ok(self.finish())
+    }
+
+    /// Add a run of glyphs shaped with `font`, positioned after any previous runs.
+    ///
+    /// `font_index` is 0 for the primary font and `scale` converts the units of `font` into
+    /// units of the primary font.
+    pub fn add_run<F, T>(
+        &mut self,
+        builder: &mut T,
+        font: &mut Font<F>,
+        infos: &'info [Info],
+        direction: TextDirection,
+        font_index: usize,
+        scale: f32,
+    ) -> Result<(), BoxError>
+    where
+        T: OutlineBuilder + GlyphName,
+        F: FontTableProvider,
+    {
+        let vertical = self.vertical.is_some();
+        let mut layout = GlyphLayout::new(font, infos, direction, vertical);
+        let mut glyph_positions = layout.glyph_positions()?;
+        if vertical {
+            // Glyphs hang from the pen, centred on the column, and the pen moves down the page
+            let ascender = i32::from(font.hhea_table.ascender);
+            for (info, pos) in infos.iter().zip(glyph_positions.iter_mut()) {
+                let advance = font
+                    .horizontal_advance(info.glyph.glyph_index)
+                    .map_or(0, i32::from);
+                pos.x_offset -= advance / 2;
+                pos.y_offset -= ascender;
+                pos.hori_advance = 0;
+                pos.vert_advance = -pos.vert_advance.abs();
+            }
+        }
         let iter = infos.iter().zip(glyph_positions.iter().copied());
-        let svg = match direction {
-            TextDirection::LeftToRight => self.glyphs_to_svg_impl(builder, font, iter),
-            TextDirection::RightToLeft => self.glyphs_to_svg_impl(builder, font, iter.rev()),
+        let (mut glyph_chars, mut origins) = match direction {
+            TextDirection::LeftToRight => self.add_run_impl(builder, iter, font_index, scale),
+            TextDirection::RightToLeft => self.add_run_impl(builder, iter.rev(), font_index, scale),
         }
         .map_err(|err| format!("error building SVG: {}", err))?;
-        Ok(svg)
+        match direction {
+            TextDirection::LeftToRight => self
+                .text_positions
+                .extend(glyph_chars.into_iter().flatten()),
+            TextDirection::RightToLeft => {
+                // Right-to-left runs are added in visual order, so prepend to keep logical order
+                glyph_chars.reverse();
+                origins.reverse();
+                let run = glyph_chars.into_iter().flatten().collect::<Vec<_>>();
+                self.text_positions.splice(0..0, run);
+            }
+        }
+        if self.show_cursive() {
+            self.add_cursive_joins(infos, &origins, scale);
+        }
+        if self.show_anchors() {
+            self.add_anchor_lines(infos, &origins, scale);
+        }
+        if self.colour_by_cluster() {
+            let mut clusters = clusters(infos, self.cluster_count);
+            self.cluster_count = clusters.last().map_or(self.cluster_count, |last| last + 1);
+            // Glyphs are used in visual order
+            if matches!(direction, TextDirection::RightToLeft) {
+                clusters.reverse();
+            }
+            self.clusters.extend(clusters);
+        }
+
+        let ascender = f32::from(font.hhea_table.ascender) * scale;
+        let descender = f32::from(font.hhea_table.descender) * scale;
+        self.metrics = Some(match self.metrics {
+            Some((max_ascender, min_descender)) => {
+                (max_ascender.max(ascender), min_descender.min(descender))
+            }
+            None => (ascender, descender),
+        });
+        Ok(())
     }
 
-    fn glyphs_to_svg_impl<'infos, F, T, I>(
-        mut self,
+    fn add_run_impl<T, I>(
+        &mut self,
         builder: &mut T,
-        font: &mut Font<F>,
         iter: I,
-    ) -> Result<String, T::Error>
+        font_index: usize,
+        scale: f32,
+    ) -> Result<(Vec<Vec<(char, Vector2F)>>, Vec<Vector2F>), T::Error>
     where
         T: OutlineBuilder + GlyphName,
-        F: FontTableProvider,
-        I: Iterator<Item = (&'infos Info, GlyphPosition)>,
+        I: Iterator<Item = (&'info Info, GlyphPosition)>,
     {
         // Turn each glyph into an SVG...
-        let mut x = 0.;
-        let mut y = 0.;
-        let mut symbols = Symbols {
-            transform: self.transform,
-            symbols: Vec::new(),
-            mode: self.mode.clone(),
-            initial_move_to: Vector2I::zero(),
-            last_line_to: None,
-        };
-        let mut symbol_map = HashMap::new();
+        self.symbols.transform = self.transform * Matrix2x2F::from_scale(scale);
+        let mut glyph_chars = Vec::new();
+        let mut origins = Vec::new();
         for (info, pos) in iter {
             let glyph_index = info.get_glyph_index();
-            if let Some(&symbol_index) = symbol_map.get(&glyph_index) {
-                self.use_glyph(
-                    symbol_index,
-                    x + pos.x_offset as f32,
-                    y + pos.y_offset as f32,
-                )
+            let symbol_index = if let Some(&symbol_index) =
+                self.symbol_map.get(&(font_index, glyph_index))
+            {
+                symbol_index
             } else {
                 let glyph_name = builder
                     .gid_to_glyph_name(glyph_index)
                     .unwrap_or_else(|| format!("gid{}", glyph_index));
-                let symbol_index = symbols.new_glyph(glyph_name, info);
-                symbol_map.insert(glyph_index, symbol_index);
-                builder.visit(glyph_index, &mut symbols)?;
+                let symbol_index = self.symbols.new_glyph(glyph_name, info, font_index);
+                self.symbol_map
+                    .insert((font_index, glyph_index), symbol_index);
+                builder.visit(glyph_index, &mut self.symbols)?;
+                let document_index = match (font_index, &self.svg_table) {
+                    (0, Some(table)) => table.document_index(glyph_index),
+                    _ => None,
+                };
+                if let Some(document_index) = document_index {
+                    // The documents are in font units with the y axis pointing down
+                    let transform = self.symbols.transform * Matrix2x2F::from_scale(vec2f(1., -1.));
+                    self.symbols.symbols[symbol_index].svg_glyph =
+                        Some((document_index, transform));
+                }
                 if self.annotate() {
-                    symbols.annotate(symbol_index, pos.x_offset as f32, pos.y_offset as f32);
+                    self.symbols
+                        .annotate(symbol_index, pos.x_offset as f32, pos.y_offset as f32);
                 }
-                self.use_glyph(
-                    symbol_index,
-                    x + pos.x_offset as f32,
-                    y + pos.y_offset as f32,
-                );
+                symbol_index
+            };
+            let x = self.pen.x() + pos.x_offset as f32 * scale;
+            let y = self.pen.y() + pos.y_offset as f32 * scale;
+            self.use_glyph(symbol_index, info, x, y);
+            let symbol = &self.symbols.symbols[symbol_index];
+            let point = self.transform * vec2f(x, y);
+            self.placements.push(GlyphPlacement {
+                file: format!("{:03}-{}.svg", self.placements.len(), symbol.view_id),
+                glyph_index,
+                glyph_name: symbol.glyph_name.clone(),
+                font_index,
+                pen_x: self.pen.x(),
+                pen_y: self.pen.y(),
+                x_offset: pos.x_offset as f32 * scale,
+                y_offset: pos.y_offset as f32 * scale,
+                hori_advance: pos.hori_advance as f32 * scale,
+                vert_advance: pos.vert_advance as f32 * scale,
+                x: point.x().round(),
+                y: point.y().round(),
+            });
+            if let Some((min, max)) = self.symbols.symbols[symbol_index].x_extent {
+                let (min, max) = (x + min * scale, x + max * scale);
+                self.ink_extent = Some(match self.ink_extent {
+                    Some((ink_min, ink_max)) => (ink_min.min(min), ink_max.max(max)),
+                    None => (min, max),
+                });
+            }
+            origins.push(vec2f(x, y));
+            if self.show_invisible()
+                && ((self.symbols.symbols[symbol_index].path.is_empty()
+                    && self.symbols.symbols[symbol_index].svg_glyph.is_none())
+                    || (pos.hori_advance == 0 && pos.vert_advance == 0))
+            {
+                self.invisible.push((
+                    self.transform * vec2f(x, y),
+                    pos.hori_advance as f32 * scale,
+                ));
+            }
+            if !info.glyph.multi_subst_dup() {
+                // Spread the characters of a ligature across its advance
+                let advance = pos.hori_advance as f32 * scale;
+                let count = info.glyph.unicodes.len() as f32;
+                let chars = info
+                    .glyph
+                    .unicodes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &ch)| {
+                        let x = x + advance * i as f32 / count;
+                        (ch, self.transform * vec2f(x, y))
+                    })
+                    .collect();
+                glyph_chars.push(chars);
+            }
+            self.pen += vec2f(pos.hori_advance as f32, pos.vert_advance as f32) * scale;
+            let symbol = &self.symbols.symbols[symbol_index];
+            if symbol.x_extent.is_some() || symbol.svg_glyph.is_some() {
+                self.inked_pen = self.pen.x();
             }
-            x += pos.hori_advance as f32;
-            y += pos.vert_advance as f32;
         }
 
-        Ok(self.end(
-            x,
-            font.hhea_table.ascender,
-            font.hhea_table.descender,
-            symbols,
-        ))
+        Ok((glyph_chars, origins))
+    }
+
+    /// Record the cursive attachments in a run, numbering each chain of attached glyphs.
+    ///
+    /// `origins` holds the position of each glyph in `infos`, in units of the primary font.
+    fn add_cursive_joins(&mut self, infos: &[Info], origins: &[Vector2F], scale: f32) {
+        let mut chains: HashMap<usize, usize> = HashMap::new();
+        for (i, info) in infos.iter().enumerate() {
+            let (exit_index, exit_anchor, entry_anchor) = match info.placement {
+                Placement::CursiveAnchor(exit_index, _, exit_anchor, entry_anchor) => {
+                    (exit_index, exit_anchor, entry_anchor)
+                }
+                _ => continue,
+            };
+            let (exit_origin, entry_origin) = match (origins.get(exit_index), origins.get(i)) {
+                (Some(&exit_origin), Some(&entry_origin)) => (exit_origin, entry_origin),
+                _ => continue,
+            };
+            let chain = match chains.get(&exit_index).or_else(|| chains.get(&i)) {
+                Some(&chain) => chain,
+                None => {
+                    self.cursive_chains += 1;
+                    self.cursive_chains
+                }
+            };
+            chains.insert(exit_index, chain);
+            chains.insert(i, chain);
+
+            let exit =
+                exit_origin + vec2f(f32::from(exit_anchor.x), f32::from(exit_anchor.y)) * scale;
+            let entry =
+                entry_origin + vec2f(f32::from(entry_anchor.x), f32::from(entry_anchor.y)) * scale;
+            let control = (exit + entry) * 0.5 + vec2f(0., CURSIVE_CURVE_HEIGHT * scale);
+            self.cursive.push(CursiveJoin {
+                chain,
+                exit: self.transform * exit,
+                entry: self.transform * entry,
+                control: self.transform * control,
+            });
+        }
+    }
+
+    /// Record the marks in a run that are attached to the anchor of a base glyph.
+    ///
+    /// `origins` holds the position of each glyph in `infos`, in units of the primary font.
+    fn add_anchor_lines(&mut self, infos: &[Info], origins: &[Vector2F], scale: f32) {
+        for (i, info) in infos.iter().enumerate() {
+            let (base_index, base_anchor, mark_anchor) = match info.placement {
+                Placement::MarkAnchor(base_index, base_anchor, mark_anchor) => {
+                    (base_index, base_anchor, mark_anchor)
+                }
+                _ => continue,
+            };
+            let (base_origin, mark_origin) = match (origins.get(base_index), origins.get(i)) {
+                (Some(&base_origin), Some(&mark_origin)) => (base_origin, mark_origin),
+                _ => continue,
+            };
+            let base_anchor =
+                base_origin + vec2f(f32::from(base_anchor.x), f32::from(base_anchor.y)) * scale;
+            let mark_anchor =
+                mark_origin + vec2f(f32::from(mark_anchor.x), f32::from(mark_anchor.y)) * scale;
+            self.anchor_lines.push(AnchorLine {
+                base_origin: self.transform * base_origin,
+                base_anchor: self.transform * base_anchor,
+                mark_anchor: self.transform * mark_anchor,
+                mark_origin: self.transform * mark_origin,
+            });
+        }
+    }
+
+    /// Warn on stderr if any glyph names had to be changed to be used as ids and file names.
+    ///
+    /// Call once all runs are added, before finishing in any format.
+    pub fn warn_sanitised_ids(&self) {
+        if self.symbols.sanitised_ids > 0 {
+            eprintln!(
+                "warning: {} glyph name(s) were not valid SVG ids and have been changed, \
+                 see the data-glyph-name attribute for the original name",
+                self.symbols.sanitised_ids
+            );
+        }
+    }
+
+    /// Finish writing, returning the SVG for all of the runs added
+    pub fn finish(self) -> String {
+        let (ascender, descender) = self.metrics.unwrap_or((0., 0.));
+        let x_max = match &self.comparison {
+            Some(comparison) => self.advance().max(comparison.advance),
+            None => self.advance(),
+        };
+        self.end(x_max, ascender, descender)
+    }
+
+    /// Return a file name and SVG for each glyph placed, in visual order, and a `positions.json`
+    /// giving the layout of each one.
+    ///
+    /// Each SVG holds just the outline of the glyph, with a view box around its bounding box and
+    /// the margin. Glyphs without an outline get a view box of just the margin around their origin.
+    /// Placing each SVG at the `x` and `y` of its entry in `positions.json` reconstructs the SVG
+    /// of the text.
+    pub fn per_glyph_files(&self) -> Result<Vec<(String, String)>, BoxError> {
+        let mut files = self
+            .placements
+            .iter()
+            .zip(self.glyph_svgs())
+            .map(|(placement, svg)| (placement.file.clone(), svg))
+            .collect::<Vec<_>>();
+        files.push((
+            String::from("positions.json"),
+            serde_json::to_string_pretty(&self.placements)?,
+        ));
+        Ok(files)
     }
 
-    fn use_glyph(&mut self, symbol_index: usize, x: f32, y: f32) {
+    /// The SVG of each glyph placed, in visual order
+    fn glyph_svgs(&self) -> Vec<String> {
+        let Margin {
+            top,
+            right,
+            bottom,
+            left,
+        } = self.margin();
+        let scale = self.transform.extract_scale();
+        (0..self.usage.len())
+            .map(|index| {
+                let symbol = &self.symbols.symbols[self.usage[index].0];
+                let (min, max) =
+                    outline_bounds(&symbol.outline).unwrap_or((Vector2F::zero(), Vector2F::zero()));
+                let x = (min.x() - left * scale.x()).floor();
+                let y = (min.y() - top * scale.y()).floor();
+                let view_box = ViewBox {
+                    x: x as i32,
+                    y: y as i32,
+                    width: (max.x() + right * scale.x() - x).ceil() as i32,
+                    height: (max.y() + bottom * scale.y() - y).ceil() as i32,
+                };
+
+                let mut w = XmlWriter::new(xmlwriter::Options::default());
+                w.write_declaration();
+                w.start_element("svg");
+                w.write_attribute("version", "1.1");
+                w.write_attribute("xmlns", "http://www.w3.org/2000/svg");
+                if symbol.svg_glyph.is_some() {
+                    w.write_attribute("xmlns:xlink", "http://www.w3.org/1999/xlink");
+                }
+                w.write_attribute("viewBox", &view_box);
+                if let Some(colour) = self.bg_colour() {
+                    w.start_element("rect");
+                    w.write_attribute("x", &view_box.x);
+                    w.write_attribute("y", &view_box.y);
+                    w.write_attribute("width", &view_box.width);
+                    w.write_attribute("height", &view_box.height);
+                    w.write_attribute("fill", &colour);
+                    if colour.opacity() != 1. {
+                        w.write_attribute("fill-opacity", &colour.opacity());
+                    }
+                    w.end_element()
+                }
+                self.write_svg_documents(&mut w, std::iter::once(symbol));
+                self.start_outline(&mut w, symbol, true, None);
+                self.write_fill(&mut w, symbol.font_index, self.use_fill(index).as_ref());
+                self.end_outline(&mut w, symbol);
+                self.write_contour_points(&mut w, symbol, None);
+
+                self.embed_svg_documents(w.end_document())
+            })
+            .collect()
+    }
+
+    /// The x the text ends at: the pen position, or with `trim_advance` the position after the
+    /// last glyph with an outline
+    fn advance(&self) -> f32 {
+        if self.trim_advance() {
+            self.inked_pen
+        } else {
+            self.pen.x()
+        }
+    }
+
+    fn use_glyph(&mut self, symbol_index: usize, info: &'info Info, x: f32, y: f32) {
+        self.inserted_dotted_circles |= is_inserted_dotted_circle(info);
         self.usage
-            .push((symbol_index, self.transform * vec2f(x, y)));
+            .push((symbol_index, self.transform * vec2f(x, y), info));
     }
 
-    fn end(self, x_max: f32, ascender: i16, descender: i16, symbols: Symbols) -> String {
+    fn end(self, x_max: f32, ascender: f32, descender: f32) -> String {
         let mut w = XmlWriter::new(xmlwriter::Options::default());
         w.write_declaration();
         w.start_element("svg");
         w.write_attribute("version", "1.1");
         w.write_attribute("xmlns", "http://www.w3.org/2000/svg");
         w.write_attribute("xmlns:xlink", "http://www.w3.org/1999/xlink");
-        let view_box = self.view_box(x_max, f32::from(ascender), f32::from(descender));
+        let mut view_box = self.text_view_box(x_max, ascender, descender);
+        let legend = self.legend_entries();
+        let legend_size =
+            ((ascender - descender) * self.transform.extract_scale().y() * 0.1).round();
+        let legend_top = view_box.y + view_box.height;
+        if !legend.is_empty() {
+            view_box.height += ((legend.len() as f32 * 1.5 + 1.) * legend_size) as i32;
+        }
+        let (view_box, canvas_size) = self.fit_canvas(view_box);
         w.write_attribute("viewBox", &view_box);
+        if let Some((width, height)) = canvas_size {
+            w.write_attribute("width", &width);
+            w.write_attribute("height", &height);
+        } else if matches!(
+            self.mode,
+            SVGMode::View {
+                pixel_size: true,
+                ..
+            }
+        ) {
+            w.write_attribute("width", &view_box.width);
+            w.write_attribute("height", &view_box.height);
+        }
+        if !self.params.is_empty() {
+            w.start_element("metadata");
+            w.start_element("params");
+            w.write_attribute("xmlns", PARAMS_NAMESPACE);
+            for (key, value) in &self.params {
+                w.write_attribute(key, value);
+            }
+            w.end_element();
+            w.end_element();
+        }
         if let Some(colour) = self.bg_colour() {
             w.start_element("rect");
             w.write_attribute("x", &view_box.x);
@@ -322,100 +1142,1044 @@ impl SVGWriter {
             w.end_element()
         }
 
+        self.write_svg_documents(&mut w, self.symbols.symbols.iter());
+
         // Write symbols
-        for symbol in &symbols.symbols {
-            w.start_element("symbol");
-            w.write_attribute("id", &symbol.id(&self.mode));
-            for (key, value) in symbol.data(&self.mode) {
-                w.write_attribute(key, &value);
-            }
-            w.write_attribute("overflow", "visible");
-            w.start_element("path");
-            w.write_attribute("d", &symbol.path);
-            if let Some(colour) = self.fg_colour() {
-                w.write_attribute("fill", &colour);
-                if colour.opacity() != 1. {
-                    w.write_attribute("fill-opacity", &colour.opacity());
+        if !self.inline_paths() {
+            for symbol in &self.symbols.symbols {
+                w.start_element("symbol");
+                w.write_attribute("id", &symbol.id(&self.mode));
+                for (key, value) in symbol.data(&self.mode) {
+                    w.write_attribute(key, &value);
                 }
-            }
-            w.end_element();
-            if let Some(origin) = symbol.origin {
-                w.start_element("path");
-                w.write_attribute("d", &self.crosshair_path(origin));
-                w.write_attribute("stroke", "red");
-                w.write_attribute("stroke-width", &(self.transform.extract_scale().x() * 10.));
+                w.write_attribute("overflow", "visible");
+                self.write_glyph(&mut w, symbol, None);
                 w.end_element();
             }
-            w.end_element();
         }
 
-        // Write use statements
-        for (symbol_index, point) in self.usage {
-            w.start_element("use");
-            let symbol = &symbols.symbols[symbol_index];
-            w.write_attribute("xlink:href", &format!("#{}", symbol.id(&self.mode)));
-            w.write_attribute("x", &point.x().round());
-            w.write_attribute("y", &point.y().round());
-            w.end_element();
+        if let Some(text) = &self.text {
+            self.write_text(&mut w, text, x_max, ascender, descender);
         }
 
-        w.end_document()
-    }
+        // The guides span the view box, which includes any ink beyond the advance
+        let (guide_min, guide_max) = self.x_range(x_max);
+        if self.guides() && self.waterfall().is_none() {
+            self.write_guides(&mut w, guide_min, guide_max, ascender, descender);
+        }
 
-    fn view_box(&self, x_max: f32, ascender: f32, descender: f32) -> ViewBox {
-        let Margin {
-            top,
-            right,
-            bottom,
-            left,
-        } = self.margin();
-        let is_flipped = self.transform.m22() < 0.0;
-        let min_y = if is_flipped { -ascender } else { descender };
-        let scale_x = self.transform.extract_scale().x();
-        let scale_y = self.transform.extract_scale().y();
+        // Write use statements
+        match self.waterfall() {
+            Some(scales) => {
+                for (translate, scale) in self.waterfall_lines(scales, ascender, descender) {
+                    w.start_element("g");
+                    w.write_attribute(
+                        "transform",
+                        &format!(
+                            "translate({} {}) scale({})",
+                            translate.x(),
+                            translate.y(),
+                            scale
+                        ),
+                    );
+                    if self.guides() {
+                        // Span the width of the largest line
+                        let max_scale = scales.iter().copied().fold(0., f32::max);
+                        self.write_guides(
+                            &mut w,
+                            guide_min * max_scale / scale,
+                            guide_max * max_scale / scale,
+                            ascender,
+                            descender,
+                        );
+                    }
+                    self.write_usage(&mut w, ascender, descender);
+                    w.end_element();
+                }
+            }
+            None => self.write_usage(&mut w, ascender, descender),
+        }
 
-        let x = ((0. - left) * scale_x).round() as i32;
-        let y = ((min_y - top) * scale_y).round() as i32;
-        let width = ((x_max + left + right) * scale_x).round() as i32;
-        let height = ((ascender - descender + top + bottom) * scale_y).round() as i32;
-        ViewBox {
-            x,
-            y,
-            width,
-            height,
+        if !legend.is_empty() {
+            let x = view_box.x as f32 + legend_size;
+            let y = legend_top as f32 + legend_size;
+            self.write_legend(&mut w, &legend, x, y, legend_size);
         }
-    }
 
-    fn crosshair_path(&self, origin: Vector2F) -> String {
-        let x = origin.x();
-        let y = origin.y();
-        let crosshair_size = 100. * self.transform.extract_scale().x();
-        let xl = x - crosshair_size;
-        let xr = x + crosshair_size;
-        let yb = y - crosshair_size;
-        let yt = y + crosshair_size;
-        format!("M{},{} L{},{} M{},{} L{},{}", xl, y, xr, y, x, yb, x, yt)
+        self.embed_svg_documents(w.end_document())
     }
 
-    fn annotate(&self) -> bool {
-        matches!(
-            self.mode,
-            SVGMode::View {
-                mark_origin: true,
-                ..
-            }
-        )
+    /// Write a placeholder in `<defs>` for each `SVG ` table document used by `symbols`, to be
+    /// replaced by the document by `embed_svg_documents`.
+    ///
+    /// xmlwriter can only write markup it has escaped, so the documents can't be written directly.
+    fn write_svg_documents<'a>(
+        &self,
+        w: &mut XmlWriter,
+        symbols: impl Iterator<Item = &'a Symbol<'a>>,
+    ) {
+        let mut document_indices = symbols
+            .filter_map(|symbol| symbol.svg_glyph.map(|(document_index, _)| document_index))
+            .collect::<Vec<_>>();
+        if document_indices.is_empty() {
+            return;
+        }
+        document_indices.sort_unstable();
+        document_indices.dedup();
+        w.start_element("defs");
+        w.write_attribute("class", "svg-table");
+        for document_index in document_indices {
+            w.start_element(SVG_DOCUMENT_PLACEHOLDER);
+            w.write_attribute("index", &document_index);
+            w.end_element();
+        }
+        w.end_element();
     }
 
-    fn margin(&self) -> Margin {
-        match self.mode {
-            SVGMode::TextRenderingTests(_) => Margin::default(),
-            SVGMode::View { margin, .. } => margin,
+    /// Replace the placeholders written by `write_svg_documents` in `svg` with their documents
+    fn embed_svg_documents(&self, mut svg: String) -> String {
+        let table = match &self.svg_table {
+            Some(table) => table,
+            None => return svg,
+        };
+        for document_index in 0..table.document_count() {
+            let placeholder = format!(
+                "<{} index=\"{}\"/>",
+                SVG_DOCUMENT_PLACEHOLDER, document_index
+            );
+            if svg.contains(&placeholder) {
+                svg = svg.replace(&placeholder, table.document(document_index));
+            }
         }
+        svg
     }
 
-    fn fg_colour(&self) -> Option<Colour> {
-        match self.mode {
+    /// Start the element that draws the outline of `symbol`, a path or, for glyphs drawn by a
+    /// document of the `SVG ` table, a group that uses the glyph's element of the document.
+    ///
+    /// With `data` the element carries the data attributes of the symbol, and `translate` is
+    /// prepended to its transform.
+    fn start_outline(
+        &self,
+        w: &mut XmlWriter,
+        symbol: &Symbol<'_>,
+        data: bool,
+        translate: Option<&str>,
+    ) {
+        w.start_element(if symbol.svg_glyph.is_some() {
+            "g"
+        } else {
+            "path"
+        });
+        if data {
+            for (key, value) in symbol.data(&self.mode) {
+                w.write_attribute(key, &value);
+            }
+        }
+        match symbol.svg_glyph {
+            Some((_, matrix)) => {
+                let matrix = format!(
+                    "matrix({} {} {} {} 0 0)",
+                    matrix.m11(),
+                    matrix.m21(),
+                    matrix.m12(),
+                    matrix.m22()
+                );
+                match translate {
+                    Some(translate) => {
+                        w.write_attribute("transform", &format!("{} {}", translate, matrix))
+                    }
+                    None => w.write_attribute("transform", &matrix),
+                }
+            }
+            None => {
+                if let Some(translate) = translate {
+                    w.write_attribute("transform", translate);
+                }
+                w.write_attribute("d", &symbol.path);
+            }
+        }
+    }
+
+    /// End the element started by `start_outline`, once its attributes have been written
+    fn end_outline(&self, w: &mut XmlWriter, symbol: &Symbol<'_>) {
+        if let Some((document_index, _)) = symbol.svg_glyph {
+            let glyph_id = symbol.info.get_glyph_index();
+            w.start_element("use");
+            w.write_attribute(
+                "xlink:href",
+                &format!("#{}", glyph_element_id(document_index, glyph_id)),
+            );
+            w.end_element();
+        }
+        w.end_element();
+    }
+
+    /// Write the outline of `symbol`, and its origin marker if it has one.
+    ///
+    /// With `placement` the outline is placed at the point and carries the data attributes of the
+    /// symbol, for writing glyphs inline rather than as a `<symbol>`, along with the fill of that
+    /// use of the glyph if it has its own.
+    fn write_glyph(
+        &self,
+        w: &mut XmlWriter,
+        symbol: &Symbol<'_>,
+        placement: Option<(Vector2F, Option<&UseFill>)>,
+    ) {
+        let translate = placement
+            .map(|(point, _)| format!("translate({} {})", point.x().round(), point.y().round()));
+        self.start_outline(w, symbol, translate.is_some(), translate.as_deref());
+        match placement {
+            Some((_, fill)) => self.write_fill(w, symbol.font_index, fill),
+            // Uses that can be filled differently have to give the fill of the font themselves
+            None if !self.fill_per_use() => self.write_fill(w, symbol.font_index, None),
+            None => {}
+        }
+        self.end_outline(w, symbol);
+        if let Some(origin) = symbol.origin {
+            w.start_element("path");
+            if let Some(translate) = &translate {
+                w.write_attribute("transform", translate);
+            }
+            w.write_attribute("d", &self.crosshair_path(origin));
+            w.write_attribute("stroke", "red");
+            w.write_attribute("stroke-width", &(self.transform.extract_scale().x() * 10.));
+            w.end_element();
+        }
+        self.write_contour_points(w, symbol, translate.as_deref());
+    }
+
+    /// Write a marker at the start point of each contour of `symbol`, with an arrow pointing
+    /// along its first segment so that the winding direction can be seen
+    fn write_contour_points(
+        &self,
+        w: &mut XmlWriter,
+        symbol: &Symbol<'_>,
+        translate: Option<&str>,
+    ) {
+        if !self.show_points() || symbol.outline.is_empty() {
+            return;
+        }
+        let scale = self.transform.extract_scale().x();
+        let radius = CONTOUR_MARKER_RADIUS * scale;
+        let length = CONTOUR_ARROW_LENGTH * scale;
+        w.start_element("g");
+        w.write_attribute("class", "contour-points");
+        if let Some(translate) = translate {
+            w.write_attribute("transform", translate);
+        }
+        for (start, direction) in contour_starts(&symbol.outline) {
+            w.start_element("circle");
+            w.write_attribute("cx", &start.x());
+            w.write_attribute("cy", &start.y());
+            w.write_attribute("r", &radius);
+            w.write_attribute("fill", CONTOUR_COLOUR);
+            w.end_element();
+            if let Some(direction) = direction {
+                let tip = start + direction * length;
+                let back = tip - direction * (length / 3.);
+                let side = vec2f(-direction.y(), direction.x()) * (length / 6.);
+                let (left, right) = (back + side, back - side);
+                w.start_element("path");
+                w.write_attribute(
+                    "d",
+                    &format!(
+                        "M{},{} L{},{} M{},{} L{},{} L{},{}",
+                        start.x(),
+                        start.y(),
+                        tip.x(),
+                        tip.y(),
+                        left.x(),
+                        left.y(),
+                        tip.x(),
+                        tip.y(),
+                        right.x(),
+                        right.y()
+                    ),
+                );
+                w.write_attribute("fill", "none");
+                w.write_attribute("stroke", CONTOUR_COLOUR);
+                w.write_attribute("stroke-width", &(scale * 5.));
+                w.end_element();
+            }
+        }
+        w.end_element();
+    }
+
+    /// Write the fill of a glyph from the font at `font_index`, or `fill` if the use of the glyph
+    /// has its own
+    fn write_fill(&self, w: &mut XmlWriter, font_index: usize, fill: Option<&UseFill>) {
+        if let Some(fill) = fill {
+            w.write_attribute("fill", &fill.colour);
+            w.write_attribute(fill.data.0, &fill.data.1);
+        } else if let Some(colour) = self.fill_colour(font_index) {
+            w.write_attribute("fill", &colour);
+            if colour.opacity() != 1. {
+                w.write_attribute("fill-opacity", &colour.opacity());
+            }
+        }
+    }
+
+    /// Whether the glyph of `symbol_index` at `point` isn't in the base runs of the comparison
+    /// at the same position, within the tolerance
+    fn changed(&self, symbol_index: usize, point: Vector2F) -> bool {
+        self.comparison.as_ref().map_or(false, |comparison| {
+            !comparison.usage.iter().any(|&(base_index, base_point, _)| {
+                base_index == symbol_index && (base_point - point).length() <= comparison.tolerance
+            })
+        })
+    }
+
+    /// The fill of the glyph at `index` in `usage`, if it differs from the fill of its font: the
+    /// highlight of a glyph that changed from the base runs of a comparison or of a dotted circle
+    /// inserted by the shaper, or the colour of its cluster
+    fn use_fill(&self, index: usize) -> Option<UseFill> {
+        let (symbol_index, point, info) = self.usage[index];
+        if self.changed(symbol_index, point) {
+            return Some(UseFill {
+                colour: String::from(DIFF_COLOUR),
+                data: ("data-changed", String::from("true")),
+            });
+        }
+        if is_inserted_dotted_circle(info) {
+            return Some(UseFill {
+                colour: String::from(DOTTED_CIRCLE_COLOUR),
+                data: ("data-inserted-dotted-circle", String::from("true")),
+            });
+        }
+        let cluster = *self.clusters.get(index)?;
+        Some(UseFill {
+            colour: CLUSTER_COLOURS[cluster % CLUSTER_COLOURS.len()].to_string(),
+            data: ("data-cluster", cluster.to_string()),
+        })
+    }
+
+    /// Whether glyphs can be filled differently each time they're used, so the fill goes on each
+    /// use rather than the symbol
+    fn fill_per_use(&self) -> bool {
+        self.comparison.is_some() || self.colour_by_cluster() || self.inserted_dotted_circles
+    }
+
+    /// Write a glyph placed at `point`, as a `<use>` of its symbol or inline
+    fn write_use(
+        &self,
+        w: &mut XmlWriter,
+        symbol_index: usize,
+        point: Vector2F,
+        fill: Option<&UseFill>,
+    ) {
+        let symbol = &self.symbols.symbols[symbol_index];
+        if self.inline_paths() {
+            self.write_glyph(w, symbol, Some((point, fill)));
+        } else {
+            w.start_element("use");
+            w.write_attribute("xlink:href", &format!("#{}", symbol.id(&self.mode)));
+            w.write_attribute("x", &point.x().round());
+            w.write_attribute("y", &point.y().round());
+            if self.fill_per_use() {
+                self.write_fill(w, symbol.font_index, fill);
+            }
+            w.end_element();
+        }
+    }
+
+    /// The view box of the text, including every line of a waterfall
+    fn text_view_box(&self, x_max: f32, ascender: f32, descender: f32) -> ViewBox {
+        let (x_min, x_max) = self.x_range(x_max);
+        if let Some((ascent, descent)) = self.vertical {
+            return self.vertical_view_box(ascent, descent);
+        }
+        // Make room for the labels below the descender
+        let descender =
+            descender - self.label_size(ascender, descender) * 1.5 * self.label_lines() as f32;
+        match self.waterfall() {
+            Some(scales) => {
+                let max_scale = scales.iter().copied().fold(0., f32::max);
+                let first = scales.first().copied().unwrap_or(1.);
+                let rest = scales.iter().skip(1).sum::<f32>();
+                self.view_box(
+                    x_min * max_scale,
+                    x_max * max_scale,
+                    ascender * first,
+                    descender * first - (ascender - descender) * rest,
+                )
+            }
+            None => self.view_box(x_min, x_max, ascender, descender),
+        }
+    }
+
+    /// Grow `view_box` to fit the canvas, if there is one, returning it with the size of the
+    /// canvas
+    fn fit_canvas(&self, view_box: ViewBox) -> (ViewBox, Option<(f32, f32)>) {
+        match &self.mode {
+            SVGMode::View {
+                canvas: Some(canvas),
+                ..
+            } => {
+                let (fitted, width, height) = view_box.fit(canvas);
+                (fitted, Some((width, height)))
+            }
+            _ => (view_box, None),
+        }
+    }
+
+    /// The translation, in SVG coordinates, and scale of each line of a waterfall.
+    ///
+    /// Each line is placed so that its ascender sits on the descender of the previous line.
+    fn waterfall_lines(
+        &self,
+        scales: &[f32],
+        ascender: f32,
+        descender: f32,
+    ) -> Vec<(Vector2F, f32)> {
+        let mut offset = 0.;
+        let mut lines = Vec::with_capacity(scales.len());
+        for (i, &scale) in scales.iter().enumerate() {
+            if i > 0 {
+                offset += ascender * scale - descender * scales[i - 1];
+            }
+            let translate = self.transform * vec2f(0., -offset);
+            lines.push((vec2f(translate.x().round(), translate.y().round()), scale));
+        }
+        lines
+    }
+
+    /// Write dashed lines at the baseline, `ascender`, and `descender`, extending from `x_min` less
+    /// the left margin to `x_max` plus the right margin
+    fn write_guides(
+        &self,
+        w: &mut XmlWriter,
+        x_min: f32,
+        x_max: f32,
+        ascender: f32,
+        descender: f32,
+    ) {
+        let Margin { left, right, .. } = self.margin();
+        let stroke_width = self.transform.extract_scale().x() * 5.;
+        w.start_element("g");
+        w.write_attribute("class", "guides");
+        w.write_attribute("stroke", GUIDE_COLOUR);
+        w.write_attribute("stroke-width", &stroke_width);
+        w.write_attribute("stroke-dasharray", &(stroke_width * 4.));
+        for (guide, y) in [
+            ("baseline", 0.),
+            ("ascender", ascender),
+            ("descender", descender),
+        ] {
+            let start = self.transform * vec2f(x_min - left, y);
+            let end = self.transform * vec2f(x_max + right, y);
+            w.start_element("line");
+            w.write_attribute("data-guide", guide);
+            w.write_attribute("x1", &start.x());
+            w.write_attribute("y1", &start.y());
+            w.write_attribute("x2", &end.x());
+            w.write_attribute("y2", &end.y());
+            w.end_element();
+        }
+        w.end_element();
+    }
+
+    /// The swatch colour and label for each colour or marker that appears in the SVG
+    fn legend_entries(&self) -> Vec<LegendEntry> {
+        if !matches!(self.mode, SVGMode::View { legend: true, .. }) {
+            return Vec::new();
+        }
+
+        let mut entries = Vec::new();
+        let mut font_indices = self
+            .symbols
+            .symbols
+            .iter()
+            .map(|symbol| symbol.font_index)
+            .filter(|&font_index| font_index > 0)
+            .collect::<Vec<_>>();
+        font_indices.sort_unstable();
+        font_indices.dedup();
+        let tinted = matches!(
+            self.mode,
+            SVGMode::View {
+                tint_fallbacks: true,
+                ..
+            }
+        );
+        if tinted && !font_indices.is_empty() {
+            let primary = self
+                .fg_colour()
+                .map_or_else(|| String::from("black"), |colour| colour.to_string());
+            entries.push(LegendEntry {
+                colour: primary,
+                dashed: false,
+                label: String::from("primary font"),
+            });
+            for font_index in font_indices {
+                if let Some(colour) = self.fill_colour(font_index) {
+                    entries.push(LegendEntry {
+                        colour: colour.to_string(),
+                        dashed: false,
+                        label: format!("fallback font {}", font_index),
+                    });
+                }
+            }
+        }
+        if self.annotate() {
+            entries.push(LegendEntry {
+                colour: String::from("red"),
+                dashed: false,
+                label: String::from("glyph origin"),
+            });
+        }
+        if self.show_cursive() && !self.cursive.is_empty() {
+            entries.push(LegendEntry {
+                colour: String::from(CURSIVE_COLOUR),
+                dashed: false,
+                label: String::from("cursive attachment, numbered by chain"),
+            });
+        }
+        if self.show_anchors() && !self.anchor_lines.is_empty() {
+            entries.push(LegendEntry {
+                colour: String::from(ANCHOR_COLOUR),
+                dashed: false,
+                label: String::from("mark attachment, from base origin through anchors to mark"),
+            });
+        }
+        if self.show_points() {
+            entries.push(LegendEntry {
+                colour: String::from(CONTOUR_COLOUR),
+                dashed: false,
+                label: String::from("contour start point and direction"),
+            });
+        }
+        if self.show_bboxes() {
+            entries.push(LegendEntry {
+                colour: String::from(BBOX_COLOUR),
+                dashed: false,
+                label: String::from("glyph bounding box"),
+            });
+        }
+        if self.comparison.is_some() {
+            entries.push(LegendEntry {
+                colour: String::from(DIFF_COLOUR),
+                dashed: false,
+                label: String::from("changed by the compared features"),
+            });
+        }
+        if self.guides() {
+            entries.push(LegendEntry {
+                colour: String::from(GUIDE_COLOUR),
+                dashed: true,
+                label: String::from("baseline, ascender, and descender"),
+            });
+        }
+        if self.show_invisible() && !self.invisible.is_empty() {
+            entries.push(LegendEntry {
+                colour: String::from("gray"),
+                dashed: true,
+                label: String::from("empty or zero advance glyph"),
+            });
+        }
+        if self.inserted_dotted_circles {
+            entries.push(LegendEntry {
+                colour: String::from(DOTTED_CIRCLE_COLOUR),
+                dashed: false,
+                label: String::from("dotted circle inserted for a mark without a base"),
+            });
+        }
+        entries
+    }
+
+    /// Write a swatch and label for each entry, one per line starting at `x`, `y`
+    fn write_legend(&self, w: &mut XmlWriter, entries: &[LegendEntry], x: f32, y: f32, size: f32) {
+        w.start_element("g");
+        w.write_attribute("data-legend", "true");
+        for (i, entry) in entries.iter().enumerate() {
+            let y = y + i as f32 * size * 1.5;
+            w.start_element("rect");
+            w.write_attribute("x", &x);
+            w.write_attribute("y", &y);
+            w.write_attribute("width", &size);
+            w.write_attribute("height", &size);
+            if entry.dashed {
+                w.write_attribute("fill", "none");
+                w.write_attribute("stroke", &entry.colour);
+                w.write_attribute("stroke-width", &(size / 10.));
+                w.write_attribute("stroke-dasharray", &(size / 4.));
+            } else {
+                w.write_attribute("fill", &entry.colour);
+            }
+            w.end_element();
+            w.start_element("text");
+            w.write_attribute("x", &(x + size * 1.5));
+            w.write_attribute("y", &(y + size * 0.85));
+            w.write_attribute("font-family", "sans-serif");
+            w.write_attribute("font-size", &size);
+            w.write_text(&entry.label);
+            w.end_element();
+        }
+        w.end_element();
+    }
+
+    /// Write `text` as transparent text along the baseline, behind the glyphs
+    fn write_text(&self, w: &mut XmlWriter, text: &str, x_max: f32, ascender: f32, descender: f32) {
+        let positions = if self
+            .text_positions
+            .iter()
+            .map(|&(ch, _)| ch)
+            .eq(text.chars())
+        {
+            self.text_positions
+                .iter()
+                .map(|&(_, point)| point)
+                .collect::<Vec<_>>()
+        } else {
+            // The glyphs don't map back to the text so spread it evenly across the run instead
+            let count = text.chars().count().max(1) as f32;
+            text.chars()
+                .enumerate()
+                .map(|(i, _)| self.transform * vec2f(x_max * i as f32 / count, 0.))
+                .collect()
+        };
+        let coords = |coord: fn(&Vector2F) -> f32| {
+            positions
+                .iter()
+                .map(|point| coord(point).round().to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        w.start_element("text");
+        w.write_attribute("x", &coords(|point| point.x()));
+        w.write_attribute("y", &coords(|point| point.y()));
+        w.write_attribute(
+            "font-size",
+            &((ascender - descender) * self.transform.extract_scale().y()).round(),
+        );
+        w.write_attribute("fill", "transparent");
+        w.write_attribute("aria-hidden", "false");
+        w.write_attribute("xml:space", "preserve");
+        w.write_text(text);
+        w.end_element();
+    }
+
+    fn write_usage(&self, w: &mut XmlWriter, ascender: f32, descender: f32) {
+        let scale = self.transform.extract_scale();
+        let label_size = self.label_size(ascender, descender);
+        if let Some(comparison) = self.comparison.as_ref().filter(|c| c.show_base) {
+            w.start_element("g");
+            w.write_attribute("class", "diff-base");
+            w.write_attribute("opacity", &DIFF_BASE_OPACITY);
+            for &(symbol_index, point, _) in &comparison.usage {
+                self.write_use(w, symbol_index, point, None);
+            }
+            w.end_element();
+        }
+        // Labels go per use rather than per symbol as a symbol may be used several times, and for
+        // different characters
+        for (index, &(symbol_index, point, info)) in self.usage.iter().enumerate() {
+            let fill = self.use_fill(index);
+            self.write_use(w, symbol_index, point, fill.as_ref());
+            for (line, (kind, label)) in self.labels(info).into_iter().enumerate() {
+                let offset = descender - label_size * (1. + 1.5 * line as f32);
+                let position = point + self.transform * vec2f(0., offset);
+                w.start_element("text");
+                w.write_attribute("x", &position.x().round());
+                w.write_attribute("y", &position.y().round());
+                w.write_attribute("font-family", "sans-serif");
+                w.write_attribute("font-size", &(label_size * scale.y()).round());
+                w.write_attribute("fill", LABEL_COLOUR);
+                w.write_attribute(kind, "true");
+                w.write_text(&label);
+                w.end_element();
+            }
+        }
+
+        // Outline the bounding box of each glyph placed, skipping those without contours
+        if self.show_bboxes() {
+            for &(symbol_index, point, _) in &self.usage {
+                let symbol = &self.symbols.symbols[symbol_index];
+                let (min, max) = match outline_bounds(&symbol.outline) {
+                    Some(bounds) => bounds,
+                    None => continue,
+                };
+                let origin = point.round();
+                w.start_element("rect");
+                w.write_attribute("class", "bbox");
+                w.write_attribute("x", &(origin.x() + min.x()));
+                w.write_attribute("y", &(origin.y() + min.y()));
+                w.write_attribute("width", &(max.x() - min.x()));
+                w.write_attribute("height", &(max.y() - min.y()));
+                w.write_attribute("fill", "none");
+                w.write_attribute("stroke", BBOX_COLOUR);
+                w.write_attribute("stroke-width", &(scale.x() * 3.));
+                w.end_element();
+            }
+        }
+
+        // Draw a dotted box spanning the advance of each invisible glyph
+        let rotation = self.rotation().to_degrees();
+        for &(point, advance) in &self.invisible {
+            let advance = if advance > 0. {
+                advance
+            } else {
+                ZERO_ADVANCE_MARKER_WIDTH
+            };
+            w.start_element("rect");
+            w.write_attribute("x", &point.x().round());
+            w.write_attribute("y", &(point.y() - ascender * scale.y()).round());
+            w.write_attribute("width", &(advance * scale.x()).round());
+            w.write_attribute("height", &((ascender - descender) * scale.y()).round());
+            w.write_attribute("fill", "none");
+            w.write_attribute("stroke", "gray");
+            w.write_attribute("stroke-width", &(scale.x() * 10.));
+            w.write_attribute("stroke-dasharray", &(scale.x() * 40.));
+            if rotation != 0. {
+                w.write_attribute(
+                    "transform",
+                    &format!(
+                        "rotate({} {} {})",
+                        rotation,
+                        point.x().round(),
+                        point.y().round()
+                    ),
+                );
+            }
+            w.write_attribute("data-invisible", "true");
+            w.end_element();
+        }
+
+        // Join the anchors of each cursive attachment and label both ends with the chain number
+        let radius = CURSIVE_MARKER_RADIUS * scale.x();
+        for join in &self.cursive {
+            w.start_element("g");
+            w.write_attribute("data-cursive-chain", &join.chain);
+            w.start_element("path");
+            w.write_attribute(
+                "d",
+                &format!(
+                    "M{},{} Q{},{} {},{}",
+                    join.exit.x(),
+                    join.exit.y(),
+                    join.control.x(),
+                    join.control.y(),
+                    join.entry.x(),
+                    join.entry.y()
+                ),
+            );
+            w.write_attribute("fill", "none");
+            w.write_attribute("stroke", CURSIVE_COLOUR);
+            w.write_attribute("stroke-width", &(scale.x() * 5.));
+            w.end_element();
+            for point in [join.exit, join.entry] {
+                w.start_element("circle");
+                w.write_attribute("cx", &point.x());
+                w.write_attribute("cy", &point.y());
+                w.write_attribute("r", &radius);
+                w.write_attribute("fill", CURSIVE_COLOUR);
+                w.end_element();
+                w.start_element("text");
+                w.write_attribute("x", &(point.x() + radius * 1.5));
+                w.write_attribute("y", &(point.y() - radius * 1.5));
+                w.write_attribute("font-family", "sans-serif");
+                w.write_attribute("font-size", &(radius * 4.));
+                w.write_attribute("fill", CURSIVE_COLOUR);
+                w.write_text(&join.chain.to_string());
+                w.end_element();
+            }
+            w.end_element();
+        }
+
+        // Join each mark to its base through their anchors, which coincide unless the mark was
+        // moved after it was attached
+        for line in &self.anchor_lines {
+            let points = [
+                line.base_origin,
+                line.base_anchor,
+                line.mark_anchor,
+                line.mark_origin,
+            ];
+            let path = points
+                .iter()
+                .enumerate()
+                .map(|(i, point)| {
+                    let command = if i == 0 { 'M' } else { 'L' };
+                    format!("{}{},{}", command, point.x(), point.y())
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            w.start_element("path");
+            w.write_attribute("class", "anchor-line");
+            w.write_attribute("d", &path);
+            w.write_attribute("fill", "none");
+            w.write_attribute("stroke", ANCHOR_COLOUR);
+            w.write_attribute("stroke-width", &(scale.x() * 5.));
+            w.end_element();
+        }
+    }
+
+    /// The horizontal extent of the text from the pen start to `x_max`, in units of the primary
+    /// font.
+    ///
+    /// When viewing, this grows to include any outlines that extend past the advances, such as
+    /// marks at the start of a right-to-left run. The text rendering tests expect the view box of
+    /// the advances alone.
+    fn x_range(&self, x_max: f32) -> (f32, f32) {
+        match (&self.mode, self.ink_extent) {
+            (SVGMode::View { .. }, Some((ink_min, ink_max))) => {
+                (ink_min.min(0.), ink_max.max(x_max))
+            }
+            _ => (0., x_max),
+        }
+    }
+
+    fn view_box(&self, x_min: f32, x_max: f32, ascender: f32, descender: f32) -> ViewBox {
+        let Margin {
+            top,
+            right,
+            bottom,
+            left,
+        } = self.margin();
+        let is_flipped = self.transform.det() < 0.0;
+        let min_y = if is_flipped { -ascender } else { descender };
+        let scale_x = self.transform.extract_scale().x();
+        let scale_y = self.transform.extract_scale().y();
+
+        let x = ((x_min - left) * scale_x).round() as i32;
+        let y = ((min_y - top) * scale_y).round() as i32;
+        let width = ((x_max - x_min + left + right) * scale_x).round() as i32;
+        let height = ((ascender - descender + top + bottom) * scale_y).round() as i32;
+        let view_box = ViewBox {
+            x,
+            y,
+            width,
+            height,
+        };
+
+        let rotation = self.rotation();
+        if rotation != 0. {
+            view_box.rotate(rotation)
+        } else {
+            view_box
+        }
+    }
+
+    /// The view box of a vertical column from the top of the text to the pen, extending `ascent`
+    /// right of the centre line and `descent` left of it
+    fn vertical_view_box(&self, ascent: f32, descent: f32) -> ViewBox {
+        let Margin {
+            top,
+            right,
+            bottom,
+            left,
+        } = self.margin();
+        let scale_x = self.transform.extract_scale().x();
+        let scale_y = self.transform.extract_scale().y();
+
+        let view_box = ViewBox {
+            x: ((descent - left) * scale_x).round() as i32,
+            y: ((0. - top) * scale_y).round() as i32,
+            width: ((ascent - descent + left + right) * scale_x).round() as i32,
+            height: ((-self.pen.y() + top + bottom) * scale_y).round() as i32,
+        };
+
+        let rotation = self.rotation();
+        if rotation != 0. {
+            view_box.rotate(rotation)
+        } else {
+            view_box
+        }
+    }
+
+    /// The angle, in radians, that the transform rotates the output by
+    fn rotation(&self) -> f32 {
+        self.transform.m21().atan2(self.transform.m11())
+    }
+
+    fn crosshair_path(&self, origin: Vector2F) -> String {
+        let x = origin.x();
+        let y = origin.y();
+        let crosshair_size = 100. * self.transform.extract_scale().x();
+        let xl = x - crosshair_size;
+        let xr = x + crosshair_size;
+        let yb = y - crosshair_size;
+        let yt = y + crosshair_size;
+        format!("M{},{} L{},{} M{},{} L{},{}", xl, y, xr, y, x, yb, x, yt)
+    }
+
+    fn annotate(&self) -> bool {
+        matches!(
+            self.mode,
+            SVGMode::View {
+                mark_origin: true,
+                ..
+            }
+        )
+    }
+
+    fn margin(&self) -> Margin {
+        match self.mode {
+            SVGMode::TextRenderingTests(_) => Margin::default(),
+            SVGMode::View { margin, .. } => margin,
+        }
+    }
+
+    fn trim_advance(&self) -> bool {
+        matches!(
+            self.mode,
+            SVGMode::View {
+                trim_advance: true,
+                ..
+            }
+        )
+    }
+
+    fn show_invisible(&self) -> bool {
+        matches!(
+            self.mode,
+            SVGMode::View {
+                show_invisible: true,
+                ..
+            }
+        )
+    }
+
+    fn guides(&self) -> bool {
+        matches!(self.mode, SVGMode::View { guides: true, .. })
+    }
+
+    fn label_indices(&self) -> bool {
+        matches!(
+            self.mode,
+            SVGMode::View {
+                label_indices: true,
+                ..
+            }
+        )
+    }
+
+    fn label_unicodes(&self) -> bool {
+        matches!(
+            self.mode,
+            SVGMode::View {
+                label_unicodes: true,
+                ..
+            }
+        )
+    }
+
+    /// The number of lines of labels below each glyph
+    fn label_lines(&self) -> usize {
+        usize::from(self.label_indices()) + usize::from(self.label_unicodes())
+    }
+
+    fn colour_by_cluster(&self) -> bool {
+        matches!(
+            self.mode,
+            SVGMode::View {
+                colour_by_cluster: true,
+                ..
+            }
+        )
+    }
+
+    fn inline_paths(&self) -> bool {
+        matches!(
+            self.mode,
+            SVGMode::View {
+                inline_paths: true,
+                ..
+            }
+        )
+    }
+
+    /// The size of the labels, in units of the primary font
+    fn label_size(&self, ascender: f32, descender: f32) -> f32 {
+        (ascender - descender) * LABEL_SIZE
+    }
+
+    /// The data attribute marking each label of the glyph placed for `info`, and its text.
+    ///
+    /// The codepoints of a ligature are joined with `+`, and glyphs that didn't come from a
+    /// character are labelled with a dash.
+    fn labels(&self, info: &Info) -> Vec<(&'static str, String)> {
+        let mut labels = Vec::new();
+        if self.label_indices() {
+            labels.push(("data-glyph-index-label", info.glyph.glyph_index.to_string()));
+        }
+        if self.label_unicodes() {
+            let label = match info.glyph.glyph_origin {
+                GlyphOrigin::Direct => String::from("-"),
+                GlyphOrigin::Char(_) => info
+                    .glyph
+                    .unicodes
+                    .iter()
+                    .map(|ch| format!("U+{:04X}", u32::from(*ch)))
+                    .collect::<Vec<_>>()
+                    .join("+"),
+            };
+            labels.push(("data-unicode-label", label));
+        }
+        labels
+    }
+
+    fn show_cursive(&self) -> bool {
+        matches!(
+            self.mode,
+            SVGMode::View {
+                show_cursive: true,
+                ..
+            }
+        )
+    }
+
+    fn show_anchors(&self) -> bool {
+        matches!(
+            self.mode,
+            SVGMode::View {
+                show_anchors: true,
+                ..
+            }
+        )
+    }
+
+    fn show_points(&self) -> bool {
+        matches!(
+            self.mode,
+            SVGMode::View {
+                show_points: true,
+                ..
+            }
+        )
+    }
+
+    fn show_bboxes(&self) -> bool {
+        matches!(
+            self.mode,
+            SVGMode::View {
+                show_bboxes: true,
+                ..
+            }
+        )
+    }
+
+    fn waterfall(&self) -> Option<&[f32]> {
+        match &self.mode {
+            SVGMode::TextRenderingTests(_) => None,
+            SVGMode::View { waterfall, .. } => waterfall.as_deref(),
+        }
+    }
+
+    /// The fill colour for glyphs from the font at `font_index`
+    fn fill_colour(&self, font_index: usize) -> Option<Colour> {
+        match self.mode {
+            SVGMode::View {
+                tint_fallbacks: true,
+                ..
+            } if font_index > 0 => Some(FALLBACK_TINTS[(font_index - 1) % FALLBACK_TINTS.len()]),
+            _ => self.fg_colour(),
+        }
+    }
+
+    fn fg_colour(&self) -> Option<Colour> {
+        match self.mode {
             SVGMode::TextRenderingTests(_) => None,
             SVGMode::View { fg, .. } => fg,
         }
@@ -430,28 +2194,87 @@ impl SVGWriter {
 }
 
 impl<'info> Symbols<'info> {
-    fn new_glyph(&mut self, glyph_name: String, info: &'info Info) -> usize {
+    fn new_glyph(&mut self, glyph_name: String, info: &'info Info, font_index: usize) -> usize {
         let index = self.symbols.len();
-        self.symbols.push(Symbol::new(glyph_name, info));
+        let view_id = match self.mode {
+            SVGMode::TextRenderingTests(_) => glyph_name.clone(),
+            SVGMode::View { .. } => self.view_id(&glyph_name),
+        };
+        self.symbols
+            .push(Symbol::new(glyph_name, view_id, info, font_index));
         index
     }
 
+    /// Generate a unique id for the glyph that is valid in XML and CSS selectors.
+    ///
+    /// Invalid characters are replaced with `_` and collisions are resolved by appending a
+    /// number.
+    fn view_id(&mut self, glyph_name: &str) -> String {
+        let mut id = glyph_name
+            .chars()
+            .map(|ch| {
+                if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
+                    ch
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        if !id.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '_') {
+            id.insert(0, '_');
+        }
+        if self.view_ids.contains(&id) {
+            let mut n = 2;
+            while self.view_ids.contains(&format!("{}_{}", id, n)) {
+                n += 1;
+            }
+            id = format!("{}_{}", id, n);
+        }
+        if id != glyph_name {
+            self.sanitised_ids += 1;
+        }
+        self.view_ids.insert(id.clone());
+        id
+    }
+
     fn current_path(&mut self) -> &mut String {
         &mut self.symbols.last_mut().unwrap().path
     }
 
+    /// Extend the x extent of the current glyph to include `points`, in font units.
+    ///
+    /// Control points are included so the extent may be slightly larger than the outline.
+    fn extend_x(&mut self, points: &[Vector2F]) {
+        let symbol = self.symbols.last_mut().unwrap();
+        for point in points {
+            symbol.x_extent = Some(match symbol.x_extent {
+                Some((min, max)) => (min.min(point.x()), max.max(point.x())),
+                None => (point.x(), point.x()),
+            });
+        }
+    }
+
+    fn push_segment(&mut self, segment: Segment) {
+        self.symbols.last_mut().unwrap().outline.push(segment);
+    }
+
     fn annotate(&mut self, index: usize, x: f32, y: f32) {
         self.symbols[index].annotate(vec2f(x, y));
     }
 }
 
 impl<'info> Symbol<'info> {
-    fn new(glyph_name: String, info: &'info Info) -> Self {
+    fn new(glyph_name: String, view_id: String, info: &'info Info, font_index: usize) -> Self {
         Symbol {
             glyph_name,
+            view_id,
             path: String::new(),
+            outline: Vec::new(),
             info,
+            font_index,
             origin: None,
+            x_extent: None,
+            svg_glyph: None,
         }
     }
 
@@ -460,7 +2283,7 @@ impl<'info> Symbol<'info> {
             SVGMode::TextRenderingTests(id_prefix) => {
                 format!("{}.{}", id_prefix, self.glyph_name).into()
             }
-            SVGMode::View { .. } => Cow::from(&self.glyph_name),
+            SVGMode::View { .. } => Cow::from(&self.view_id),
         }
     }
 
@@ -477,6 +2300,12 @@ impl<'info> Symbol<'info> {
                     data.insert("data-mark", bool_true.clone());
                 }
                 data.insert("data-glyph-index", self.info.glyph.glyph_index.to_string());
+                if self.font_index > 0 {
+                    data.insert("data-font-index", self.font_index.to_string());
+                }
+                if self.view_id != self.glyph_name {
+                    data.insert("data-glyph-name", self.glyph_name.clone());
+                }
                 data.insert(
                     "data-liga-component-pos",
                     self.info.glyph.liga_component_pos.to_string(),
@@ -488,6 +2317,13 @@ impl<'info> Symbol<'info> {
                         GlyphOrigin::Direct => String::from("direct"),
                     },
                 );
+                let is_space = matches!(
+                    self.info.glyph.glyph_origin,
+                    GlyphOrigin::Char(ch) if ch.is_whitespace()
+                );
+                if is_space {
+                    data.insert("data-space", bool_true.clone());
+                }
                 if self.info.glyph.small_caps() {
                     data.insert("data-small-caps", bool_true.clone());
                 }
@@ -513,6 +2349,14 @@ impl<'info> Symbol<'info> {
     }
 }
 
+/// Whether the glyph is a dotted circle the shaper inserted, rather than one from the text.
+///
+/// Shapers insert one before a mark that has no base, such as a vowel sign at the start of a word.
+fn is_inserted_dotted_circle(info: &Info) -> bool {
+    matches!(info.glyph.glyph_origin, GlyphOrigin::Direct)
+        && info.glyph.unicodes[..] == [DOTTED_CIRCLE]
+}
+
 // When rendering in TextRenderingTests mode the paths are "normalised" by
 // truncating them. The matches what the other test harnesses do and makes the
 // output SVGs match the expectations, which have had the same treatment.
@@ -522,6 +2366,7 @@ impl<'info> Symbol<'info> {
 // is dropped, as close-path will handle that.
 impl<'info> OutlineSink for Symbols<'info> {
     fn move_to(&mut self, point: Vector2F) {
+        self.extend_x(&[point]);
         let point = self.transform * point;
         let path = match self.mode {
             SVGMode::TextRenderingTests(_) => {
@@ -530,12 +2375,16 @@ impl<'info> OutlineSink for Symbols<'info> {
                 self.last_line_to = None;
                 format!(" M{},{}", point.x(), point.y())
             }
-            SVGMode::View { .. } => format!(" M{},{}", point.x(), point.y()),
+            SVGMode::View { precision, .. } => {
+                self.push_segment(Segment::MoveTo(point));
+                format!(" M{}", format_point(point, precision))
+            }
         };
         self.current_path().push_str(&path);
     }
 
     fn line_to(&mut self, point: Vector2F) {
+        self.extend_x(&[point]);
         let point = self.transform * point;
         let path = match self.mode {
             SVGMode::TextRenderingTests(_) => {
@@ -543,12 +2392,16 @@ impl<'info> OutlineSink for Symbols<'info> {
                 self.last_line_to = Some(point);
                 format!(" L{},{}", point.x(), point.y())
             }
-            SVGMode::View { .. } => format!(" L{},{}", point.x(), point.y()),
+            SVGMode::View { precision, .. } => {
+                self.push_segment(Segment::LineTo(point));
+                format!(" L{}", format_point(point, precision))
+            }
         };
         self.current_path().push_str(&path);
     }
 
     fn quadratic_curve_to(&mut self, control: Vector2F, point: Vector2F) {
+        self.extend_x(&[control, point]);
         let control = self.transform * control;
         let point = self.transform * point;
         let path = match self.mode {
@@ -562,18 +2415,20 @@ impl<'info> OutlineSink for Symbols<'info> {
                     point.y() as i32
                 )
             }
-            SVGMode::View { .. } => format!(
-                " Q{},{} {},{}",
-                control.x(),
-                control.y(),
-                point.x(),
-                point.y()
-            ),
+            SVGMode::View { precision, .. } => {
+                self.push_segment(Segment::QuadTo(control, point));
+                format!(
+                    " Q{} {}",
+                    format_point(control, precision),
+                    format_point(point, precision)
+                )
+            }
         };
         self.current_path().push_str(&path);
     }
 
     fn cubic_curve_to(&mut self, ctrl: LineSegment2F, to: Vector2F) {
+        self.extend_x(&[ctrl.from(), ctrl.to(), to]);
         let ctrl_from = self.transform * ctrl.from();
         let ctrl_to = self.transform * ctrl.to();
         let to = self.transform * to;
@@ -590,20 +2445,23 @@ impl<'info> OutlineSink for Symbols<'info> {
                     to.y() as i32
                 )
             }
-            SVGMode::View { .. } => format!(
-                " C{},{} {},{} {},{}",
-                ctrl_from.x(),
-                ctrl_from.y(),
-                ctrl_to.x(),
-                ctrl_to.y(),
-                to.x(),
-                to.y()
-            ),
+            SVGMode::View { precision, .. } => {
+                self.push_segment(Segment::CubicTo(ctrl_from, ctrl_to, to));
+                format!(
+                    " C{} {} {}",
+                    format_point(ctrl_from, precision),
+                    format_point(ctrl_to, precision),
+                    format_point(to, precision)
+                )
+            }
         };
         self.current_path().push_str(&path);
     }
 
     fn close(&mut self) {
+        if matches!(self.mode, SVGMode::View { .. }) {
+            self.push_segment(Segment::Close);
+        }
         if matches!(self.mode, SVGMode::TextRenderingTests(_)) {
             match self.last_line_to {
                 Some(last_line_to) if last_line_to == self.initial_move_to => {
@@ -619,3 +2477,148 @@ impl<'info> OutlineSink for Symbols<'info> {
         self.current_path().push_str(" Z"); // close path
     }
 }
+
+/// The cluster of each glyph of `infos`, in logical order, numbering the clusters from `first`.
+///
+/// allsorts doesn't track clusters, so they're inferred from the characters of each glyph: a
+/// glyph with characters starts a new cluster, which holds all the characters of a ligature, and
+/// copies made by multiple substitution and glyphs that didn't come from a character join the
+/// cluster before them.
+fn clusters(infos: &[Info], first: usize) -> Vec<usize> {
+    let mut next = first;
+    infos
+        .iter()
+        .map(|info| {
+            let joins_previous = info.glyph.multi_subst_dup() || info.glyph.unicodes.is_empty();
+            if !joins_previous || next == first {
+                next += 1;
+            }
+            next - 1
+        })
+        .collect()
+}
+
+/// The top left and bottom right of the points of `outline`, including control points, or `None`
+/// if it's empty
+fn outline_bounds(outline: &[Segment]) -> Option<(Vector2F, Vector2F)> {
+    outline
+        .iter()
+        .flat_map(|segment| match *segment {
+            Segment::MoveTo(to) | Segment::LineTo(to) => vec![to],
+            Segment::QuadTo(control, to) => vec![control, to],
+            Segment::CubicTo(control1, control2, to) => vec![control1, control2, to],
+            Segment::Close => vec![],
+        })
+        .fold(None, |bounds, point| match bounds {
+            Some((min, max)) => Some((point.min(min), point.max(max))),
+            None => Some((point, point)),
+        })
+}
+
+/// The start point of each contour of `outline`, and the unit vector from it towards the next
+/// distinct point, or `None` if the contour doesn't leave its start point
+fn contour_starts(outline: &[Segment]) -> Vec<(Vector2F, Option<Vector2F>)> {
+    let mut starts = Vec::new();
+    let mut pending = None;
+    for segment in outline {
+        let points = match *segment {
+            Segment::MoveTo(to) => {
+                if let Some(start) = pending.replace(to) {
+                    starts.push((start, None));
+                }
+                continue;
+            }
+            Segment::LineTo(to) => vec![to],
+            Segment::QuadTo(control, to) => vec![control, to],
+            Segment::CubicTo(control1, control2, to) => vec![control1, control2, to],
+            Segment::Close => {
+                if let Some(start) = pending.take() {
+                    starts.push((start, None));
+                }
+                continue;
+            }
+        };
+        if let Some(start) = pending {
+            if let Some(&next) = points.iter().find(|&&point| point != start) {
+                starts.push((start, Some((next - start).normalize())));
+                pending = None;
+            }
+        }
+    }
+    if let Some(start) = pending {
+        starts.push((start, None));
+    }
+    starts
+}
+
+/// Format `point` as `x,y` rounded to `precision` decimal places, without trailing zeros
+fn format_point(point: Vector2F, precision: usize) -> String {
+    format!(
+        "{},{}",
+        format_coordinate(point.x(), precision),
+        format_coordinate(point.y(), precision)
+    )
+}
+
+fn format_coordinate(value: f32, precision: usize) -> String {
+    let mut formatted = format!("{:.*}", precision, value);
+    if formatted.contains('.') {
+        let len = formatted.trim_end_matches('0').trim_end_matches('.').len();
+        formatted.truncate(len);
+    }
+    if formatted == "-0" {
+        formatted.remove(0);
+    }
+    formatted
+}
+
+/// An `OutlineSink` that builds an SVG path in font units and tracks its bounds
+#[derive(Default)]
+pub struct PathSink {
+    pub path: String,
+    pub bounds: Option<(Vector2F, Vector2F)>,
+}
+
+impl PathSink {
+    pub fn clear(&mut self) {
+        self.path.clear();
+        self.bounds = None;
+    }
+
+    fn command(&mut self, command: char, points: &[Vector2F]) {
+        self.path.push(' ');
+        self.path.push(command);
+        for (i, point) in points.iter().enumerate() {
+            if i > 0 {
+                self.path.push(' ');
+            }
+            let _ = write!(self.path, "{},{}", point.x(), point.y());
+            self.bounds = Some(match self.bounds {
+                Some((min, max)) => (min.min(*point), max.max(*point)),
+                None => (*point, *point),
+            });
+        }
+    }
+}
+
+impl OutlineSink for PathSink {
+    fn move_to(&mut self, point: Vector2F) {
+        self.command('M', &[point]);
+    }
+
+    fn line_to(&mut self, point: Vector2F) {
+        self.command('L', &[point]);
+    }
+
+    fn quadratic_curve_to(&mut self, control: Vector2F, point: Vector2F) {
+        self.command('Q', &[control, point]);
+    }
+
+    fn cubic_curve_to(&mut self, control: LineSegment2F, point: Vector2F) {
+        self.command('C', &[control.from(), control.to(), point]);
+    }
+
+    fn close(&mut self) {
+        self.command('Z', &[]);
+    }
+}
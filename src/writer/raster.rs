@@ -0,0 +1,257 @@
+//! Rasterising the glyphs of an `SVGWriter` into a PNG instead of writing an SVG.
+//!
+//! The outline of each symbol is recorded in SVG coordinates, so glyphs are placed the same way
+//! as the `<use>` elements and then mapped from the view box onto the pixels of the image.
+
+use ab_glyph_rasterizer::{point, Point, Rasterizer};
+use allsorts::pathfinder_geometry::transform2d::Transform2F;
+use allsorts::pathfinder_geometry::vector::{vec2f, Vector2F};
+
+use super::{Colour, SVGWriter};
+use crate::BoxError;
+
+/// Fill colour of the glyphs when no colour is specified, matching the SVG default
+const BLACK: Colour = Colour {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 0xff,
+};
+
+/// Length in pixels of the lines that curves are flattened into
+const FLATTEN_STEP: f32 = 2.;
+
+/// The most lines a single curve is flattened into
+const MAX_CURVE_LINES: usize = 64;
+
+/// A segment of a glyph outline, in SVG coordinates
+pub(super) enum Segment {
+    MoveTo(Vector2F),
+    LineTo(Vector2F),
+    QuadTo(Vector2F, Vector2F),
+    CubicTo(Vector2F, Vector2F, Vector2F),
+    Close,
+}
+
+impl<'info> SVGWriter<'info> {
+    /// Finish writing, returning a PNG of the glyphs of all of the runs added.
+    ///
+    /// Only the glyphs and background are drawn, markers, anchor lines, contour points, bounding
+    /// boxes, guides, the legend, and selectable text are SVG only.
+    pub fn finish_png(self) -> Result<Vec<u8>, BoxError> {
+        if self.annotate()
+            || self.show_invisible()
+            || self.show_cursive()
+            || self.show_anchors()
+            || self.show_points()
+            || self.show_bboxes()
+            || self.guides()
+            || self.label_lines() > 0
+            || self.text.is_some()
+            || self.fill_per_use()
+            || !self.legend_entries().is_empty()
+        {
+            eprintln!("warning: only the glyphs and background are drawn in PNG output");
+        }
+        if self
+            .symbols
+            .symbols
+            .iter()
+            .any(|symbol| symbol.svg_glyph.is_some())
+        {
+            eprintln!(
+                "warning: glyphs in the SVG table are drawn with their outlines in PNG output"
+            );
+        }
+
+        let (ascender, descender) = self.metrics.unwrap_or((0., 0.));
+        let view_box = self.text_view_box(self.advance(), ascender, descender);
+        let (view_box, canvas_size) = self.fit_canvas(view_box);
+        let (width, height) =
+            canvas_size.unwrap_or((view_box.width as f32, view_box.height as f32));
+        let width = width.round().max(1.) as u32;
+        let height = height.round().max(1.) as u32;
+        let to_pixels =
+            Transform2F::from_scale(vec2f(
+                width as f32 / view_box.width.max(1) as f32,
+                height as f32 / view_box.height.max(1) as f32,
+            )) * Transform2F::from_translation(vec2f(-view_box.x as f32, -view_box.y as f32));
+        let lines = match self.waterfall() {
+            Some(scales) => self.waterfall_lines(scales, ascender, descender),
+            None => vec![(Vector2F::zero(), 1.)],
+        };
+
+        let mut image = Image::new(width, height, self.bg_colour());
+        // Each font is filled separately so that fallback fonts can be tinted
+        let mut font_indices = self
+            .symbols
+            .symbols
+            .iter()
+            .map(|symbol| symbol.font_index)
+            .collect::<Vec<_>>();
+        font_indices.sort_unstable();
+        font_indices.dedup();
+        for font_index in font_indices {
+            let mut pen = Pen::new(width, height);
+            for &(translate, scale) in &lines {
+                let line = to_pixels
+                    * Transform2F::from_translation(translate)
+                    * Transform2F::from_scale(vec2f(scale, scale));
+                for &(symbol_index, origin, _) in &self.usage {
+                    let symbol = &self.symbols.symbols[symbol_index];
+                    if symbol.font_index == font_index {
+                        pen.draw(
+                            &symbol.outline,
+                            line * Transform2F::from_translation(origin),
+                        );
+                    }
+                }
+            }
+            let colour = self.fill_colour(font_index).unwrap_or(BLACK);
+            image.fill(&pen.rasterizer, colour);
+        }
+
+        image.encode_png()
+    }
+}
+
+/// Draws outlines into a `Rasterizer`, flattening curves and clamping points to its bounds.
+///
+/// The rasterizer accumulates coverage from left to right, so clamping gives the same result as
+/// clipping.
+struct Pen {
+    rasterizer: Rasterizer,
+    max: Vector2F,
+    start: Vector2F,
+    current: Vector2F,
+}
+
+impl Pen {
+    fn new(width: u32, height: u32) -> Self {
+        Pen {
+            rasterizer: Rasterizer::new(width as usize, height as usize),
+            // Stay just inside the right edge, which isn't part of the image
+            max: vec2f(width as f32 - 0.01, height as f32),
+            start: Vector2F::zero(),
+            current: Vector2F::zero(),
+        }
+    }
+
+    fn draw(&mut self, outline: &[Segment], transform: Transform2F) {
+        for segment in outline {
+            match *segment {
+                Segment::MoveTo(to) => {
+                    self.close();
+                    self.start = transform * to;
+                    self.current = self.start;
+                }
+                Segment::LineTo(to) => self.line_to(transform * to),
+                Segment::QuadTo(control, to) => {
+                    let from = self.current;
+                    let (control, to) = (transform * control, transform * to);
+                    let steps = curve_steps(&[from, control, to]);
+                    for step in 1..=steps {
+                        let t = step as f32 / steps as f32;
+                        let mt = 1. - t;
+                        self.line_to(from * (mt * mt) + control * (2. * mt * t) + to * (t * t));
+                    }
+                }
+                Segment::CubicTo(control1, control2, to) => {
+                    let from = self.current;
+                    let (control1, control2, to) =
+                        (transform * control1, transform * control2, transform * to);
+                    let steps = curve_steps(&[from, control1, control2, to]);
+                    for step in 1..=steps {
+                        let t = step as f32 / steps as f32;
+                        let mt = 1. - t;
+                        self.line_to(
+                            from * (mt * mt * mt)
+                                + control1 * (3. * mt * mt * t)
+                                + control2 * (3. * mt * t * t)
+                                + to * (t * t * t),
+                        );
+                    }
+                }
+                Segment::Close => self.close(),
+            }
+        }
+        self.close();
+    }
+
+    fn line_to(&mut self, to: Vector2F) {
+        self.rasterizer
+            .draw_line(self.clamp(self.current), self.clamp(to));
+        self.current = to;
+    }
+
+    fn close(&mut self) {
+        if self.current != self.start {
+            self.line_to(self.start);
+        }
+    }
+
+    fn clamp(&self, position: Vector2F) -> Point {
+        let clamped = position.max(Vector2F::zero()).min(self.max);
+        point(clamped.x(), clamped.y())
+    }
+}
+
+/// The number of lines to flatten the curve with control polygon `points` into
+fn curve_steps(points: &[Vector2F]) -> usize {
+    let length = points
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).length())
+        .sum::<f32>();
+    ((length / FLATTEN_STEP).ceil() as usize).clamp(1, MAX_CURVE_LINES)
+}
+
+/// An RGBA image with 8 bits per channel and straight (not premultiplied) alpha
+struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: u32, height: u32, background: Option<Colour>) -> Self {
+        let Colour { r, g, b, a } = background.unwrap_or_default();
+        Image {
+            width,
+            height,
+            pixels: [r, g, b, a].repeat(width as usize * height as usize),
+        }
+    }
+
+    /// Composite `colour` over the image where the outlines drawn into `rasterizer` cover it
+    fn fill(&mut self, rasterizer: &Rasterizer, colour: Colour) {
+        let pixels = &mut self.pixels;
+        rasterizer.for_each_pixel(|index, coverage| {
+            let alpha = coverage.min(1.) * colour.opacity();
+            if alpha <= 0. {
+                return;
+            }
+            let pixel = &mut pixels[index * 4..index * 4 + 4];
+            let dst_alpha = f32::from(pixel[3]) / 255.;
+            let out_alpha = alpha + dst_alpha * (1. - alpha);
+            for (channel, src) in pixel[..3].iter_mut().zip([colour.r, colour.g, colour.b]) {
+                let value = (f32::from(src) * alpha
+                    + f32::from(*channel) * dst_alpha * (1. - alpha))
+                    / out_alpha;
+                *channel = value.round() as u8;
+            }
+            pixel[3] = (out_alpha * 255.).round() as u8;
+        });
+    }
+
+    fn encode_png(&self) -> Result<Vec<u8>, BoxError> {
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, self.width, self.height);
+            encoder.set_color(png::ColorType::RGBA);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&self.pixels)?;
+        }
+        Ok(data)
+    }
+}
@@ -3,6 +3,26 @@ use std::process::Command;
 use assert_cmd::prelude::*;
 use predicates::prelude::*;
 
+/// The values of the `viewBox` of the root element of `svg`
+fn view_box(svg: &str) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+    let start = svg.find("viewBox=\"").ok_or("no viewBox")? + "viewBox=\"".len();
+    let end = start + svg[start..].find('"').ok_or("no viewBox")?;
+    let values = svg[start..end]
+        .split(' ')
+        .map(str::parse)
+        .collect::<Result<Vec<f32>, _>>()?;
+    Ok(values)
+}
+
+/// The name of each glyph in the output of `shape --json`
+fn glyph_names(json: &str) -> Vec<String> {
+    json.split("\"glyph_name\":\"")
+        .skip(1)
+        .filter_map(|rest| rest.split('"').next())
+        .map(String::from)
+        .collect()
+}
+
 #[test]
 fn dump_glyph() -> Result<(), Box<dyn std::error::Error>> {
     let mut cmd = Command::cargo_bin("allsorts")?;
@@ -28,6 +48,79 @@ fn dump_glyph() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn dump_glyph_svg_path() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "-g", "1", "--svg-path", "tests/Basic-Regular.ttf"]);
+    cmd.assert().success().stdout(predicate::str::is_match(
+        "\n<path transform=\"scale\\(1 -1\\)\" d=\"M[^\"]+ Z\"/>\n$",
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn dump_provenance() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--provenance", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("ttfautohint\n - TTFA table\n"))
+        .stdout(predicate::str::contains(" - name 5 version \"Version 1.003; ttfautohint (v1.3)"))
+        .stdout(predicate::str::contains(
+            "vendor: Magnus Gaarde\n - manufacturer \"Magnus Gaarde\"\n - vendor URL \"www.sorkintype.com\"\n - OS/2 achVendID \"STC \"\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn dump_glyph_range() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "-g", "463..470", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("=== glyph 463 ===\n"))
+        .stdout(predicate::str::contains("=== glyph 464 ===\n"))
+        .stdout(predicate::str::contains("=== glyph 465 ===").not())
+        .stderr(predicate::str::contains("glyph ids after 464 are skipped"));
+
+    Ok(())
+}
+
+#[test]
+fn dump_glyph_tuple() -> Result<(), Box<dyn std::error::Error>> {
+    // Glyph 5 is l, its right-hand points and advance move by 60 at the heaviest weight
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "dump",
+        "-g",
+        "5",
+        "--tuple",
+        "900",
+        "tests/Fixture-Variable.ttf",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "glyph 5: default vs tuple 900\n\
+             point  default        varied         delta\n\
+             0      (124, 1439)    (124, 1439)    (0, 0)\n\
+             1      (331, 1439)    (391, 1439)    (60, 0)\n",
+        ))
+        .stdout(predicate::str::ends_with(
+            "advance: 483 -> 543 (+60)\nlsb: 124 -> 124 (+0)\n",
+        ));
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--tuple", "900", "tests/Fixture-Variable.ttf"]);
+    cmd.assert()
+        .code(1)
+        .stderr("Error: --tuple requires --glyph\n");
+
+    Ok(())
+}
+
 #[test]
 fn dump_empty_glyph() -> Result<(), Box<dyn std::error::Error>> {
     // Glyph 112 is .null
@@ -45,3 +138,2609 @@ fn dump_empty_glyph() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn dump_hhea() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--hhea", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("ascender: 2066,"))
+        .stdout(predicate::str::contains("descender: -511,"))
+        .stdout(predicate::str::contains("advance_width_max: 2531,"))
+        .stdout(predicate::str::contains("num_h_metrics: 465,"));
+
+    Ok(())
+}
+
+#[test]
+fn dump_maxp() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--maxp", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("num_glyphs: 465,"))
+        .stdout(predicate::str::contains("max_points: 152,"))
+        .stdout(predicate::str::contains("max_component_elements: 3,"))
+        .stdout(predicate::str::contains("max_component_depth: 1,"));
+
+    Ok(())
+}
+
+#[test]
+fn dump_overlap() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--overlap", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .success()
+        .stdout("no glyphs set overlap flags\n");
+
+    Ok(())
+}
+
+#[test]
+fn dump_use_my_metrics() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--use-my-metrics", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "176: component 0 (glyph 10), component advance 992, own hmtx advance 992\n",
+        ))
+        .stdout(predicate::str::ends_with(
+            "432: component 0 (glyph 370), component advance 1534, own hmtx advance 1534\n\
+             173 composite glyphs set USE_MY_METRICS\n",
+        ))
+        .stdout(predicate::str::contains("(differs)").not());
+
+    Ok(())
+}
+
+#[test]
+fn dump_anomalies() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--anomalies", "tests/Basic-Regular.ttf"]);
+    let expected = "\
+note: no GDEF glyph classes, so marks with zero advance are included
+zero advance and not a mark: 2 glyph(s)
+ - 111 (nonmarkingreturn)
+ - 112 (.null)
+duplicate consecutive points: 16 glyph(s)
+ - 7 (comma)
+ - 37 (acute)
+ - 74 (four)
+ - 97 (acute.cap)
+ - 100 (macron)
+ - 101 (macron.cap)
+ - 130 (guilsinglleft)
+ - 149 (sterling)
+ - 150 (Euro)
+ - 151 (yen)
+ - and 6 more
+";
+    cmd.assert().success().stdout(expected);
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--anomalies", "tests/Fixture-Variable.ttf"]);
+    cmd.assert().success().stdout("no glyph anomalies found\n");
+
+    Ok(())
+}
+
+#[test]
+fn dump_locate_glyph() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--locate", "0x243e", "tests/Basic-Regular.ttf"]);
+    let expected = "offset 9278 (0x243e)
+glyf: offset 5714 within table (table offset 3564, length 51926)
+ - glyph 40: offset 10 within glyph
+";
+    cmd.assert().success().stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn dump_locate_outside_tables() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--locate", "100", "tests/Basic-Regular.ttf"]);
+    let expected = "offset 100 (0x64)
+offset is not within any table
+";
+    cmd.assert().success().stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn dump_gaps() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--gaps", "tests/Basic-Regular.ttf"]);
+    cmd.assert().success().stdout("no gaps between tables\n");
+
+    Ok(())
+}
+
+#[test]
+fn dump_head() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--head", "tests/Basic-Regular.ttf"]);
+    cmd.assert().success().stdout(predicate::str::ends_with(
+        "loca format: short (0), 16-bit offsets / 2\nglyph data format: 0\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn dump_head_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--head", "-"])
+        .write_stdin(std::fs::read("tests/Basic-Regular.ttf")?);
+    cmd.assert().success().stdout(predicate::str::ends_with(
+        "loca format: short (0), 16-bit offsets / 2\nglyph data format: 0\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn instance_stdout_into_dump() -> Result<(), Box<dyn std::error::Error>> {
+    // Glyph 5 is l, whose advance grows by 60 at the heaviest weight
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "instance",
+        "-t",
+        "900",
+        "tests/Fixture-Variable.ttf",
+        "-o",
+        "-",
+    ]);
+    let instance = cmd.assert().success().get_output().stdout.clone();
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--hmtx", "-"]).write_stdin(instance);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "\n5: LongHorMetric { advance_width: 543, lsb: 124 }\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn dump_encodings() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--encodings", "tests/Basic-Regular.ttf"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "cmap encodings:\n - 0 3 Sub-table format 4, language 0 (any)\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn dump_post() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--post", "tests/Basic-Regular.ttf"]);
+    cmd.assert().success().stdout(
+        "version: 2.0 (0x00020000)\n\
+         glyph names: stored for 465 glyphs\n\
+         italic angle: 0 degrees (0x00000000)\n\
+         underline position: -75\n\
+         underline thickness: 50\n\
+         fixed pitch: no\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn dump_gdef_without_gdef() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--gdef", "tests/Basic-Regular.ttf"]);
+    cmd.assert().success().stdout("font has no GDEF table\n");
+
+    Ok(())
+}
+
+#[test]
+fn dump_cff_widths() -> Result<(), Box<dyn std::error::Error>> {
+    // The Top DICT has no CharstringType
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "tests/Basic-Regular.otf"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "  - widths:\n    - CharstringType: 2 (default)\n    - defaultWidthX: 521\n    \
+         - nominalWidthX: 1068\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn dump_cff_fd_select() -> Result<(), Box<dyn std::error::Error>> {
+    // The second Font DICT's Private DICT has no StdHW or StdVW
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--cff-verbose", "tests/Fixture-CID.otf"]);
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            " - FDSelect\n  - glyphs 0..=99: Font DICT 0\n  - glyphs 100..=464: Font DICT 1\n",
+        )
+        .and(predicate::str::contains("    - StdHW: not set\n").count(1))
+        .and(predicate::str::contains("    - StdVW: not set\n").count(1)),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn dump_eblc() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--eblc", "tests/Fixture-Bitmap.ttf"]);
+    let expected = "EBLC:
+ - version: 2.0
+ - num sizes: 2
+
+ - Strike 0
+  - ppem: 12x12
+  - bit depth: 1
+  - flags: horizontal
+  - glyphs: 2..=3
+  - index sub-tables: 1
+   - glyphs 2..=3: index format 1, image format 1
+
+ - Strike 1
+  - ppem: 16x16
+  - bit depth: 1
+  - flags: horizontal, vertical, reserved bits 0x0080
+  - glyphs: 2..=3
+  - index sub-tables: 1
+   - glyphs 2..=3: index format 1, image format 1
+";
+    cmd.assert().success().stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn dump_collection_sharing() -> Result<(), Box<dyn std::error::Error>> {
+    // The second font has its own name table and a copy of the first font's OS/2 table
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "tests/Fixture-Collection.ttc"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Collection summary\n \
+         - DSIG: none\n \
+         - GDEF: fonts 0,1 @ 460 (shared)\n \
+         - GPOS: fonts 0,1 @ 500 (shared)\n \
+         - GSUB: fonts 0,1 @ 740 (shared)\n \
+         - OS/2: fonts 0 @ 1012; fonts 1 @ 4112 (identical copies)\n \
+         - cmap: fonts 0,1 @ 1108 (shared)\n \
+         - glyf: fonts 0,1 @ 1348 (shared)\n \
+         - head: fonts 0,1 @ 2652 (shared)\n \
+         - hhea: fonts 0,1 @ 2708 (shared)\n \
+         - hmtx: fonts 0,1 @ 2744 (shared)\n \
+         - loca: fonts 0,1 @ 2800 (shared)\n \
+         - maxp: fonts 0,1 @ 2860 (shared)\n \
+         - name: fonts 0 @ 2892; fonts 1 @ 4208\n \
+         - post: fonts 0,1 @ 4048 (shared)\n \
+         - table bytes: 4949 actual, 4853 if identical tables were shared\n\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn dump_os2() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--os2", "tests/Basic-Regular.ttf"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "weight class: 400 (Regular)\nwidth class: 5 (Medium)\n\
+             fsType: 0x0000 INSTALLABLE_EMBEDDING\nfsSelection: 0x0040 REGULAR\n\
+             panose: 2 1 5 3 4 5 0 2 0 4\n - family kind: Latin Text (2)\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn dump_panose() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--panose", "tests/Basic-Regular.ttf"]);
+    let expected = r#"panose: 2 1 5 3 4 5 0 2 0 4
+ - family kind: Latin Text (2)
+ - serif style: No Fit (1)
+ - weight: Book (5)
+ - proportion: Modern (3)
+ - contrast: Low (4)
+ - stroke variation: Gradual/Vertical (5)
+ - arm style: Any (0)
+ - letterform: Normal/Contact (2)
+ - midline: Any (0)
+ - x-height: Constant/Large (4)
+"#;
+    cmd.assert().success().stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn dump_fstype() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "dump",
+        "--fstype",
+        "tests/Basic-Regular.ttf",
+        "tests/Basic-Regular.ttf",
+    ]);
+    cmd.assert().success().stdout(
+        "tests/Basic-Regular.ttf: fsType 0x0000 INSTALLABLE_EMBEDDING\n\
+         tests/Basic-Regular.ttf: fsType 0x0000 INSTALLABLE_EMBEDDING\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn dump_table_equal() -> Result<(), Box<dyn std::error::Error>> {
+    // A collection of two fonts that share the table directory of the test font
+    let ttf = std::fs::read("tests/Basic-Regular.ttf")?;
+    let mut ttc = b"ttcf".to_vec();
+    for value in [0x00010000u32, 2, 20, 20].iter() {
+        ttc.extend_from_slice(&value.to_be_bytes());
+    }
+    let mut font = ttf.clone();
+    let num_tables = usize::from(u16::from_be_bytes([ttf[4], ttf[5]]));
+    for i in 0..num_tables {
+        let at = 12 + 16 * i + 8;
+        let offset = u32::from_be_bytes([font[at], font[at + 1], font[at + 2], font[at + 3]]);
+        font[at..at + 4].copy_from_slice(&(offset + 20).to_be_bytes());
+    }
+    ttc.extend_from_slice(&font);
+    let collection = std::env::temp_dir().join("allsorts-table-equal.ttc");
+    std::fs::write(&collection, ttc)?;
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--table-equal", "head"])
+        .arg(&collection);
+    cmd.assert().success().stdout(
+        predicate::str::starts_with("head:\n - group 1: 54 bytes, fonts 0,1 @ ").and(
+            predicate::str::ends_with(" (shared)\nidentical in all 2 fonts\n"),
+        ),
+    );
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--table-equal", "head", "tests/Basic-Regular.ttf"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--table-equal requires a font collection",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn dump_verify_checksums() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--verify-checksums", "tests/Basic-Regular.ttf"]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("head (checksum: 0x860cb869): OK\n")
+            .and(predicate::str::contains("MISMATCH").not())
+            .and(predicate::str::ends_with(
+                "checkSumAdjustment (0x9547c0cc): OK\n",
+            )),
+    );
+
+    // Change a byte of the last table so its checksum and the adjustment no longer match
+    let mut data = std::fs::read("tests/Basic-Regular.ttf")?;
+    // The last byte of the file is padding, so change the one before it
+    let last = data.len() - 2;
+    data[last] ^= 0xff;
+    let corrupt = std::env::temp_dir().join("allsorts-corrupt-checksum.ttf");
+    std::fs::write(&corrupt, data)?;
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--verify-checksums"]).arg(&corrupt);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("MISMATCH, computed").count(2).and(
+            predicate::str::contains("checkSumAdjustment (0x9547c0cc): MISMATCH"),
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn dump_count() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--count", "tests/Basic-Regular.ttf"]);
+    cmd.assert().success().stdout(
+        "tables=15 glyphs=465 outlines=glyf gsub=no gpos=no gdef=no variable=no upm=2048\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn dump_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--json", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("{\n  \"format\": \"TTF\","))
+        .stdout(predicate::str::contains("\"num_glyphs\": 465"));
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--json", "--head", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .code(1)
+        .stderr("Error: --json can't be combined with --head\n");
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--json", "-g", "1", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .code(1)
+        .stderr("Error: --json can't be combined with --glyph\n");
+
+    Ok(())
+}
+
+#[test]
+fn dump_name() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--name", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "PostScript Name (Windows)\n\"Basic-Regular\"\n",
+        ))
+        .stderr("");
+
+    Ok(())
+}
+
+#[test]
+fn dump_name_platform() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "dump",
+        "--name-platform",
+        "1",
+        "--name-language",
+        "0x0",
+        "tests/Basic-Regular.ttf",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("(Macintosh)"))
+        .stdout(predicate::str::contains("(Windows)").not());
+
+    Ok(())
+}
+
+#[test]
+fn dump_kern_without_kerning() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--kern", "tests/Basic-Regular.ttf"]);
+    cmd.assert().success().stdout("no kern table\n");
+
+    Ok(())
+}
+
+#[test]
+fn dump_compare_kerning_without_kerning() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["dump", "--compare-kerning", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .success()
+        .stdout("font has neither a kern table nor a GPOS table\n");
+
+    Ok(())
+}
+
+#[test]
+fn shape_porcelain() -> Result<(), Box<dyn std::error::Error>> {
+    let tests = std::env::temp_dir().join("allsorts-shape-porcelain.txt");
+    std::fs::write(&tests, "ab\n\nc\n")?;
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+    ])
+    .arg("--porcelain")
+    .arg("--tests")
+    .arg(&tests);
+    cmd.assert()
+        .success()
+        .stdout(predicate::function(|out: &str| {
+            let lines = out.lines().collect::<Vec<_>>();
+            lines.len() == 3
+                && lines[0].split('\t').count() == 2
+                && lines[1].is_empty()
+                && lines[2].split('\t').count() == 1
+        }));
+
+    Ok(())
+}
+
+#[test]
+fn view_output() -> Result<(), Box<dyn std::error::Error>> {
+    let output = std::env::temp_dir().join("allsorts-view-output.svg");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "--all-glyphs",
+        "-o",
+    ])
+    .arg(&output);
+    cmd.assert().success().stdout("");
+    let svg = std::fs::read_to_string(&output)?;
+    assert!(svg.contains("<svg") && svg.ends_with("</svg>\n"));
+
+    Ok(())
+}
+
+#[test]
+fn view_output_svgz() -> Result<(), Box<dyn std::error::Error>> {
+    let output = std::env::temp_dir().join("allsorts-view-output.svgz");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "abc",
+        "-o",
+    ])
+    .arg(&output);
+    cmd.assert().success().stdout("");
+    let svgz = std::fs::read(&output)?;
+    // The gzip magic number
+    assert!(svgz.starts_with(&[0x1f, 0x8b]));
+
+    Ok(())
+}
+
+#[test]
+fn view_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "abc",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("<metadata>"))
+        .stdout(predicate::str::contains(
+            "font=\"Basic-Regular.ttf\" font-checksum=\"0x",
+        ))
+        .stdout(predicate::str::contains(
+            "script=\"latn\" text=\"abc\" direction=\"ltr\"",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn view_trim_collapse_spaces() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "  a \t b  ",
+        "--trim-spaces",
+        "--collapse-spaces",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("<use ")
+            .count(3)
+            .and(predicate::str::contains("data-space=\"true\"").count(1)),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn view_show_invisible() -> Result<(), Box<dyn std::error::Error>> {
+    // The space has no outline and the combining acute has no advance
+    let args = [
+        "view",
+        "-f",
+        "tests/Fixture-Layout.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "o\u{301} b",
+    ];
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).arg("--show-invisible");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("data-invisible=\"true\"").count(2));
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("data-invisible").not());
+
+    Ok(())
+}
+
+#[test]
+fn view_selectable_text() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-t",
+        "a b",
+        "--selectable-text",
+    ]);
+    // Each character is placed at the origin of its glyph, on the baseline
+    cmd.assert().success().stdout(predicate::str::is_match(
+        "<text x=\"0 \\d+ \\d+\" y=\"0 0 0\" font-size=\"\\d+\" fill=\"transparent\" \
+         aria-hidden=\"false\" xml:space=\"preserve\">a b</text>",
+    )?);
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "--indices",
+        "10,11",
+        "--selectable-text",
+    ]);
+    cmd.assert()
+        .code(1)
+        .stderr("--selectable-text requires --text OR --codepoints\n");
+
+    Ok(())
+}
+
+#[test]
+fn view_legend() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-t",
+        "a b",
+        "--legend",
+        "--mark-origin",
+        "--show-invisible",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("<g data-legend=\"true\">")
+            .count(1)
+            .and(predicate::str::contains(">glyph origin</text>"))
+            .and(predicate::str::contains(
+                ">empty or zero advance glyph</text>",
+            ))
+            .and(predicate::str::contains("bounding box").not()),
+    );
+
+    // Without any annotations there's nothing to describe
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-t",
+        "a b",
+        "--legend",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("data-legend").not());
+
+    Ok(())
+}
+
+#[test]
+fn view_waterfall() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "ab",
+        "--waterfall",
+        "500,1000",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains(") scale(0.5)\">")
+            .and(predicate::str::contains(") scale(1)\">"))
+            .and(predicate::str::contains("<use ").count(4)),
+    );
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-t",
+        "ab",
+        "--waterfall",
+        "12,0",
+    ]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "waterfall sizes must be greater than zero",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn view_rotate() -> Result<(), Box<dyn std::error::Error>> {
+    let rotated = |rotate: &str| -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("allsorts")?;
+        cmd.args(&[
+            "view",
+            "-f",
+            "tests/Basic-Regular.ttf",
+            "-t",
+            "ab",
+            "--rotate",
+            rotate,
+        ]);
+        let svg = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+        view_box(&svg)
+    };
+    // A quarter turn swaps the width and height, allowing for rounding
+    let (upright, turned) = (rotated("0")?, rotated("90")?);
+    assert!((turned[2] - upright[3]).abs() <= 1.);
+    assert!((turned[3] - upright[2]).abs() <= 1.);
+
+    Ok(())
+}
+
+#[test]
+fn view_canvas() -> Result<(), Box<dyn std::error::Error>> {
+    let fitted = |text: &str| -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("allsorts")?;
+        cmd.args(&[
+            "view",
+            "-f",
+            "tests/Basic-Regular.ttf",
+            "-t",
+            text,
+            "--width",
+            "400",
+            "--height",
+            "100",
+        ]);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let svg = String::from_utf8(output)?;
+        assert!(svg.contains(" width=\"400\" height=\"100\""));
+        view_box(&svg)
+    };
+    // The view box grows to the aspect ratio of the canvas, allowing for rounding
+    let text = fitted("ab")?;
+    assert!((text[2] - text[3] * 4.).abs() <= 4.);
+
+    // Only spaces have no outlines to measure, but still fill the canvas
+    let spaces = fitted(" ")?;
+    assert!(spaces[2] > 0. && spaces[3] > 0.);
+
+    Ok(())
+}
+
+#[test]
+fn view_fallback() -> Result<(), Box<dyn std::error::Error>> {
+    // Basic has no Devanagari, the fixture maps KA
+    let args = [
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "--fallback",
+        "tests/Fixture-Layout.ttf",
+        "-t",
+        "a\u{915}b",
+    ];
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args);
+    cmd.assert().success().stdout(
+        predicate::str::contains("<use ")
+            .count(3)
+            .and(predicate::str::contains("data-font-index=\"1\"").count(1))
+            .and(predicate::str::contains("#d62728").not()),
+    );
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).arg("--tint-fallbacks");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("fill=\"#d62728\""));
+
+    Ok(())
+}
+
+#[test]
+fn view_sanitised_ids() -> Result<(), Box<dyn std::error::Error>> {
+    // Glyph 0 is .notdef and glyph 97 is acute.cap, neither of which is a valid id
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["view", "-f", "tests/Basic-Regular.ttf", "-i", "0,97"]);
+    cmd.assert()
+        .success()
+        .stdout(
+            predicate::str::contains("id=\"_notdef\"")
+                .and(predicate::str::contains("data-glyph-name=\".notdef\""))
+                .and(predicate::str::contains("id=\"acute_cap\""))
+                .and(predicate::str::contains("xlink:href=\"#acute_cap\""))
+                .and(predicate::str::contains("data-glyph-name=\"acute.cap\"")),
+        )
+        .stderr(
+            "warning: 2 glyph name(s) were not valid SVG ids and have been changed, \
+             see the data-glyph-name attribute for the original name\n",
+        );
+
+    // Both fonts have a glyph named k, the fixture maps KA to it
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "--fallback",
+        "tests/Fixture-Layout.ttf",
+        "-t",
+        "k\u{915}",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(
+            predicate::str::contains("id=\"k\"")
+                .and(predicate::str::contains("id=\"k_2\""))
+                .and(predicate::str::contains("data-glyph-name=\"k\"").count(1)),
+        )
+        .stderr(predicate::str::contains("warning: 1 glyph name(s)"));
+
+    Ok(())
+}
+
+#[test]
+fn view_show_cursive() -> Result<(), Box<dyn std::error::Error>> {
+    // Beh, beh, alef form one chain and the two hehs after the space another
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Fixture-Arabic.ttf",
+        "-t",
+        "\u{628}\u{628}\u{627} \u{647}\u{647}",
+        "--show-cursive",
+        "--legend",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("<g data-cursive-chain=\"1\">")
+            .count(2)
+            .and(predicate::str::contains("<g data-cursive-chain=\"2\">").count(1))
+            .and(predicate::str::contains("data-cursive-chain=\"3\"").not())
+            .and(predicate::str::contains(
+                ">cursive attachment, numbered by chain</text>",
+            )),
+    );
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Fixture-Arabic.ttf",
+        "-t",
+        "\u{628}\u{628}\u{627} \u{647}\u{647}",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("data-cursive-chain").not());
+
+    Ok(())
+}
+
+#[test]
+fn view_show_anchors() -> Result<(), Box<dyn std::error::Error>> {
+    // Each fatha is attached to the mark anchor of the beh or heh before it
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Fixture-Arabic.ttf",
+        "-t",
+        "\u{628}\u{64E}\u{647}\u{64E}",
+        "--show-anchors",
+        "--legend",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            ">mark attachment, from base origin through anchors to mark</text>",
+        ))
+        .stdout(predicate::function(|out: &str| {
+            let lines = out
+                .split("class=\"anchor-line\" d=\"")
+                .skip(1)
+                .filter_map(|line| line.split('"').next())
+                .map(|path| {
+                    path.split(' ')
+                        .filter_map(|point| point[1..].split_once(','))
+                        .filter_map(|(x, y)| Some((x.parse::<f32>().ok()?, y.parse::<f32>().ok()?)))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            // The anchors of the mark and base coincide as the mark isn't moved after attaching
+            lines.len() == 2
+                && lines.iter().all(|points| {
+                    points.len() == 4
+                        && (points[1].0 - points[2].0).abs() < 0.01
+                        && (points[1].1 - points[2].1).abs() < 0.01
+                })
+        }));
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Fixture-Arabic.ttf",
+        "-t",
+        "\u{628}\u{64E}\u{647}\u{64E}",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("anchor-line").not());
+
+    Ok(())
+}
+
+#[test]
+fn view_inserted_dotted_circle() -> Result<(), Box<dyn std::error::Error>> {
+    // A vowel sign I on its own has no consonant, so the shaper inserts a dotted circle for it
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Fixture-Layout.ttf",
+        "-s",
+        "deva",
+        "-t",
+        "\u{93F}",
+        "--legend",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("data-inserted-dotted-circle=\"true\"")
+            .count(1)
+            .and(predicate::str::contains(
+                ">dotted circle inserted for a mark without a base</text>",
+            )),
+    );
+
+    // A dotted circle in the text isn't highlighted
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Fixture-Layout.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "\u{25CC}\u{301}",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("data-inserted-dotted-circle").not());
+
+    Ok(())
+}
+
+#[test]
+fn view_vertical() -> Result<(), Box<dyn std::error::Error>> {
+    // The fixture has 2048 units per em, so a font size of 2048 draws in font units. Its vmtx
+    // gives a a vertical advance of 1800 and b 2200
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Fixture-Bitmap.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "ab",
+        "--vertical",
+        "--font-size",
+        "2048",
+        "--margin",
+        "0",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let svg = String::from_utf8(output)?;
+    assert!(svg.contains("vertical=\"true\""));
+    let uses = svg
+        .split("<use ")
+        .skip(1)
+        .map(|element| {
+            let start = element.find(" y=\"").ok_or("no y")? + " y=\"".len();
+            let end = start + element[start..].find('"').ok_or("no y")?;
+            Ok(element[start..end].parse::<f32>()?)
+        })
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+    assert_eq!(uses.len(), 2);
+    // y increases downwards in SVG, so b is below a by the advance of a
+    assert_eq!(uses[1] - uses[0], 1800.);
+    assert_eq!(view_box(&svg)?[3], 4000.);
+
+    Ok(())
+}
+
+#[test]
+fn view_label_indices() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "--indices",
+        "68,69,68",
+        "--label-indices",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("data-glyph-index-label=\"true\"")
+            .count(3)
+            .and(predicate::str::contains(">69</text>")),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn view_label_unicodes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "aba",
+        "--label-unicodes",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("data-unicode-label=\"true\">U+0061</text>")
+            .count(2)
+            .and(predicate::str::contains(
+                "data-unicode-label=\"true\">U+0062</text>",
+            )),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn view_show_points() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "aba",
+        "--show-points",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("<g class=\"contour-points\">")
+            .count(2)
+            .and(predicate::str::contains("<circle")),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn view_show_bboxes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "a b",
+        "--show-bboxes",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("<rect class=\"bbox\"").count(2));
+
+    Ok(())
+}
+
+#[test]
+fn view_inline_paths() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "aba",
+        "--inline-paths",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("<symbol")
+            .not()
+            .and(predicate::str::contains("<use").not())
+            .and(predicate::str::contains("data-glyph-index=").count(3))
+            .and(predicate::str::contains("transform=\"translate(").count(3)),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn view_precision() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "a",
+        "--precision",
+        "0",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::function(|out: &str| {
+            out.split(" d=\"")
+                .skip(1)
+                .filter_map(|path| path.split('"').next())
+                .all(|path| !path.is_empty() && !path.contains('.'))
+        }));
+
+    Ok(())
+}
+
+#[test]
+fn view_all_glyphs_cff2() -> Result<(), Box<dyn std::error::Error>> {
+    // Every glyph but space has an outline
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["view", "-f", "tests/Fixture-CFF2.otf", "--all-glyphs"]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("<g data-glyph-index=")
+            .count(7)
+            .and(predicate::str::contains("data-empty=\"true\"").count(1)),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn view_cff2_tuple() -> Result<(), Box<dyn std::error::Error>> {
+    // The CFF2 outline of l is a rectangle whose right side moves by 60 at the heaviest weight
+    // and by -30 at the lightest
+    let width = |tuple: Option<&str>| -> Result<f32, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("allsorts")?;
+        cmd.args(&[
+            "view",
+            "-f",
+            "tests/Fixture-CFF2.otf",
+            "-s",
+            "latn",
+            "-t",
+            "l",
+        ]);
+        if let Some(tuple) = tuple {
+            cmd.args(&["-T", tuple]);
+        }
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let svg = String::from_utf8(output)?;
+        let path = svg
+            .split(" d=\"")
+            .nth(1)
+            .and_then(|path| path.split('"').next())
+            .ok_or("no path")?;
+        let xs = path
+            .split(|c: char| c.is_ascii_alphabetic() || c.is_whitespace())
+            .filter_map(|point| point.split(',').next())
+            .filter(|x| !x.is_empty())
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<_>, _>>()?;
+        let min = xs.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = xs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        Ok(max - min)
+    };
+
+    let default = width(None)?;
+    let regular = width(Some("400"))?;
+    let black = width(Some("900"))?;
+    let thin = width(Some("100"))?;
+    assert_eq!(default, regular);
+    assert!(thin < regular && regular < black);
+    assert!(((black - regular) / (regular - thin) - 2.).abs() < 0.01);
+
+    Ok(())
+}
+
+#[test]
+fn view_diff_features() -> Result<(), Box<dyn std::error::Error>> {
+    // The font has no GSUB so nothing changes
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "fi",
+        "--diff-features",
+        "liga",
+        "--diff-show-base",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("class=\"diff-base\"")
+            .and(predicate::str::contains("<use").count(4))
+            .and(predicate::str::contains("data-changed").not()),
+    );
+
+    // ss01 isn't a feature allsorts knows, it replaces a with A
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Fixture-Layout.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "ab",
+        "--diff-features",
+        "ss01",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("data-changed=\"true\"").count(1));
+
+    // Added to a list of features, salt replaces o with its first alternate
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Fixture-Layout.ttf",
+        "-s",
+        "latn",
+        "-F",
+        "liga",
+        "-t",
+        "fio",
+        "--diff-features",
+        "salt",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("data-changed=\"true\"").count(1));
+
+    Ok(())
+}
+
+#[test]
+fn view_trim_advance() -> Result<(), Box<dyn std::error::Error>> {
+    let trimmed = |text: &str| -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("allsorts")?;
+        cmd.args(&[
+            "view",
+            "-f",
+            "tests/Basic-Regular.ttf",
+            "--trim-advance",
+            "-t",
+            text,
+        ]);
+        let svg = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+        view_box(&svg)
+    };
+    assert_eq!(trimmed("ab  ")?, trimmed("ab")?);
+
+    Ok(())
+}
+
+#[test]
+fn view_guides() -> Result<(), Box<dyn std::error::Error>> {
+    // The fatha overhangs the left of the text, moving the view box left of the origin
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Fixture-Arabic.ttf",
+        "-s",
+        "arab",
+        "-t",
+        "\u{628}\u{627}\u{64E}",
+        "--guides",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let svg = String::from_utf8(output)?;
+    assert!(svg.contains("<g class=\"guides\""));
+    let attribute = |element: &str, name: &str| -> Result<f32, Box<dyn std::error::Error>> {
+        let pattern = format!(" {}=\"", name);
+        let start = element.find(&pattern).ok_or("no attribute")? + pattern.len();
+        let end = start + element[start..].find('"').ok_or("no attribute")?;
+        Ok(element[start..end].parse()?)
+    };
+    let view_box = view_box(&svg)?;
+    let guide = |name: &str| -> Result<[f32; 3], Box<dyn std::error::Error>> {
+        let pattern = format!("<line data-guide=\"{}\"", name);
+        let start = svg.find(&pattern).ok_or("no guide")?;
+        let line = &svg[start..start + svg[start..].find("/>").ok_or("no guide")?];
+        Ok([
+            attribute(line, "x1")?,
+            attribute(line, "x2")?,
+            attribute(line, "y1")?,
+        ])
+    };
+
+    let baseline = guide("baseline")?;
+    assert!(view_box[0] < 0.);
+    assert!((baseline[0] - view_box[0]).abs() <= 1.);
+    assert!((baseline[1] - (view_box[0] + view_box[2])).abs() <= 1.);
+    // y increases downwards in SVG
+    assert!(guide("ascender")?[2] < baseline[2]);
+    assert!(guide("descender")?[2] > baseline[2]);
+
+    Ok(())
+}
+
+#[test]
+fn view_svg_table() -> Result<(), Box<dyn std::error::Error>> {
+    // a is drawn by a plain document and b by a gzip compressed one, which both define a gradient
+    // with the id fill, while o has no document so it's drawn with its outline
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["view", "-f", "tests/Fixture-SVG.ttf", "-t", "abo"]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("<defs class=\"svg-table\">")
+            .and(predicate::str::contains("<?xml").count(1))
+            .and(predicate::str::contains("<g id=\"svgdoc0-glyph2\">"))
+            .and(predicate::str::contains("<g id=\"svgdoc1-glyph3\">"))
+            .and(predicate::str::contains("xlink:href=\"#svgdoc0-glyph2\""))
+            .and(predicate::str::contains("xlink:href=\"#svgdoc1-glyph3\""))
+            .and(predicate::str::contains("stop-color=\"red\""))
+            .and(predicate::str::contains("stop-color=\"blue\""))
+            .and(predicate::str::contains("id=\"svgdoc0-fill\""))
+            .and(predicate::str::contains("fill=\"url(#svgdoc1-fill)\""))
+            .and(predicate::str::contains("id=\"fill\"").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn view_disable() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-t",
+        "fi",
+        "--disable",
+        "liga,kern",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("<use").count(2));
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-t",
+        "fi",
+        "--disable",
+        "smcp",
+    ]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "smcp isn't one of the default features so can't be disabled",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn view_direction() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join("allsorts-view-direction");
+    let _ = std::fs::remove_dir_all(&dir);
+    let args = [
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "ab",
+    ];
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args)
+        .args(&["--direction", "rtl", "--per-glyph-dir"])
+        .arg(&dir);
+    cmd.assert().success();
+    assert!(dir.join("000-b.svg").exists() && dir.join("001-a.svg").exists());
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).args(&["--direction", "up"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "invalid direction 'up', expected ltr or rtl",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn view_per_glyph_dir() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join("allsorts-view-per-glyph");
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "aba",
+        "--per-glyph-dir",
+    ])
+    .arg(&dir);
+    // The SVG of the text is written as well
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("<use").count(3));
+
+    let mut files = std::fs::read_dir(&dir)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>, std::io::Error>>()?;
+    files.sort();
+    assert_eq!(
+        files,
+        ["000-a.svg", "001-b.svg", "002-a.svg", "positions.json"]
+    );
+    let svg = std::fs::read_to_string(dir.join("001-b.svg"))?;
+    assert!(svg.contains("viewBox=") && svg.contains("<path"));
+    assert!(!svg.contains("<use"));
+    let positions = std::fs::read_to_string(dir.join("positions.json"))?;
+    assert_eq!(positions.matches("\"glyph_index\"").count(), 3);
+    assert!(positions.contains("\"file\": \"002-a.svg\""));
+
+    Ok(())
+}
+
+#[test]
+fn view_colour_by_cluster() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "aba",
+        "--colour-by-cluster",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("fill=\"#1f77b4\" data-cluster=\"0\"")
+            .and(predicate::str::contains(
+                "fill=\"#ff7f0e\" data-cluster=\"1\"",
+            ))
+            .and(predicate::str::contains(
+                "fill=\"#2ca02c\" data-cluster=\"2\"",
+            )),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn view_rtl_outlines_in_view_box() -> Result<(), Box<dyn std::error::Error>> {
+    // Glyph 9 extends 118 units left of its origin and is leftmost once the run is reversed
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "arab",
+        "--indices",
+        "10,9",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("viewBox=\"-58 "));
+
+    Ok(())
+}
+
+#[test]
+fn view_rtl_marks_in_view_box() -> Result<(), Box<dyn std::error::Error>> {
+    // Beh, then alef with a fatha that overhangs its left side at the end of the run
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Fixture-Arabic.ttf",
+        "-s",
+        "arab",
+        "-t",
+        "\u{628}\u{627}\u{64E}",
+        "--show-bboxes",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let svg = String::from_utf8(output)?;
+    let attribute = |element: &str, name: &str| -> Result<f32, Box<dyn std::error::Error>> {
+        let pattern = format!(" {}=\"", name);
+        let start = element.find(&pattern).ok_or("no attribute")? + pattern.len();
+        let end = start + element[start..].find('"').ok_or("no attribute")?;
+        Ok(element[start..end].parse()?)
+    };
+    let view_box = view_box(&svg)?;
+    let bboxes = svg
+        .split("<rect class=\"bbox\"")
+        .skip(1)
+        .map(|rect| Ok((attribute(rect, "x")?, attribute(rect, "width")?)))
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+
+    assert_eq!(bboxes.len(), 3);
+    assert!(view_box[0] < 0.);
+    for &(x, width) in &bboxes {
+        assert!(x >= view_box[0] && x + width <= view_box[0] + view_box[2]);
+    }
+    // Glyphs are placed in visual order, so beh, the first in logical order, is rightmost
+    let beh = bboxes[2].0;
+    assert!(bboxes.iter().all(|&(x, _)| x <= beh));
+
+    Ok(())
+}
+
+#[test]
+fn view_png() -> Result<(), Box<dyn std::error::Error>> {
+    let output = std::env::temp_dir().join("allsorts-view-png.png");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "view",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-t",
+        "abc",
+        "--font-size",
+        "32",
+        "--format",
+        "png",
+        "-o",
+    ])
+    .arg(&output);
+    cmd.assert().success().stdout("");
+    let png = std::fs::read(&output)?;
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    Ok(())
+}
+
+#[test]
+fn shape_config() -> Result<(), Box<dyn std::error::Error>> {
+    let config = std::env::temp_dir().join("allsorts-shape-config.json");
+    std::fs::write(
+        &config,
+        r#"{"script": "latn", "lang": "ENG", "features": "-kern", "text": "ab"}"#,
+    )?;
+    let expected = Command::cargo_bin("allsorts")?
+        .args(&[
+            "shape",
+            "-f",
+            "tests/Basic-Regular.ttf",
+            "-s",
+            "latn",
+            "-l",
+            "ENG",
+            "ab",
+        ])
+        .output()?;
+    // The text in the config replaces the text given on the command line
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["shape", "-f", "tests/Basic-Regular.ttf", "--config"])
+        .arg(&config)
+        .arg("xyz");
+    cmd.assert().success().stdout(expected.stdout);
+
+    std::fs::write(&config, r#"{"script": "latn", "colour": "red"}"#)?;
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["shape", "-f", "tests/Basic-Regular.ttf", "--config"])
+        .arg(&config);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown field `colour`"));
+
+    Ok(())
+}
+
+#[test]
+fn shape_extents() -> Result<(), Box<dyn std::error::Error>> {
+    // Both glyphs have only straight lines, so their extents are their points' bounds
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Fixture-Layout.ttf",
+        "-s",
+        "latn",
+        "--extents",
+        "k \u{25CA}",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::starts_with("985,0 (0, 0) extents: (136, 0, 964, 1440) Info {\n")
+            .and(predicate::str::contains(
+                "\n573,0 (0, 0) extents: none Info {\n",
+            ))
+            .and(predicate::str::contains(
+                "\n1196,0 (0, 0) extents: (1664, 0, 2633, 1349) Info {\n",
+            ))
+            .and(predicate::str::ends_with(
+                "\nrun extents: (136, 0, 2633, 1349)\n",
+            )),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn shape_stage_svgs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join("allsorts-shape-stage-svgs");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["shape", "-f", "tests/Fixture-Layout.ttf", "-s", "latn"])
+        .arg("--stage-svgs")
+        .arg(&dir)
+        .arg("fio\u{301}");
+    cmd.assert().success();
+
+    // The ligature is formed by GSUB but the mark is only attached by GPOS
+    let cmap = std::fs::read_to_string(dir.join("1-cmap.svg"))?;
+    assert_eq!(cmap.matches("<use ").count(), 4);
+    let gsub = std::fs::read_to_string(dir.join("2-gsub.svg"))?;
+    assert_eq!(gsub.matches("<use ").count(), 3);
+    assert!(!gsub.contains("data-mark"));
+    let gpos = std::fs::read_to_string(dir.join("3-gpos.svg"))?;
+    assert_eq!(gpos.matches("<use ").count(), 3);
+    assert!(gpos.contains("data-mark=\"true\""));
+
+    Ok(())
+}
+
+#[test]
+fn shape_fallback_codepoint() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "--json",
+        "--fallback-codepoint",
+        "U+003F",
+        "a\u{915}",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            "\"glyph_name\":\"a\".*\"glyph_name\":\"question\"",
+        )?)
+        .stderr("");
+
+    // The fixture has no question mark, so the .notdef is left in place
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Fixture-Layout.ttf",
+        "-s",
+        "latn",
+        "--json",
+        "--fallback-codepoint",
+        "U+003F",
+        "a\u{915}\u{916}",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"glyph_name\":\".notdef\""))
+        .stderr("warning: U+003F is not mapped by the font, leaving .notdef in place\n");
+
+    Ok(())
+}
+
+#[test]
+fn shape_synthesis_flags() -> Result<(), Box<dyn std::error::Error>> {
+    // Only a caller of allsorts can ask for synthesised styles, so shaping text never flags any
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+        "ab",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::starts_with("992,0 (0, 0) Info {\n")
+            .and(predicate::str::contains("\n1089,0 (0, 0) Info {\n"))
+            .and(predicate::str::contains("fake-").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn shape_bench() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "--bench",
+        "3",
+        "abc",
+    ]);
+    cmd.assert().success().stdout(predicate::str::is_match(
+        "^shaped 3 times in \\S+\n\
+         per iteration: mean \\S+, min \\S+, max \\S+\n\
+         3 glyphs per iteration, \\d+ glyphs per second\n$",
+    )?);
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "--bench",
+        "0",
+        "abc",
+    ]);
+    cmd.assert()
+        .code(1)
+        .stderr("Error: --bench requires at least one iteration\n");
+
+    Ok(())
+}
+
+#[test]
+fn shape_variation_sequences() -> Result<(), Box<dyn std::error::Error>> {
+    // The fixture maps a with VS2 to A, and a with VS1 to the default glyph
+    let shape = |text: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("allsorts")?;
+        cmd.args(&[
+            "shape",
+            "-f",
+            "tests/Fixture-Layout.ttf",
+            "-s",
+            "latn",
+            "--json",
+            text,
+        ]);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        Ok(String::from_utf8(output)?)
+    };
+    assert_eq!(glyph_names(&shape("a\u{FE01}b")?), ["A", "b"]);
+    assert_eq!(glyph_names(&shape("a\u{FE00}b")?), ["a", "b"]);
+    assert_eq!(glyph_names(&shape("ab")?), ["a", "b"]);
+
+    Ok(())
+}
+
+#[test]
+fn shape_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+        "--json",
+        "ab",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "[{\"glyph_index\":10,\"glyph_name\":\"a\",\"cluster\":0,\"unicodes\":[\"U+0061\"],",
+        ))
+        .stdout(predicate::str::contains(
+            "{\"glyph_index\":11,\"glyph_name\":\"b\",\"cluster\":1,",
+        ))
+        .stdout(predicate::str::ends_with("}]\n"));
+
+    Ok(())
+}
+
+#[test]
+fn shape_text_file_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+        "--text-file",
+        "-",
+    ])
+    .write_stdin("ab\nba\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("line 1: ab\n"))
+        .stdout(predicate::str::contains("\nline 2: ba\n"));
+
+    Ok(())
+}
+
+#[test]
+fn shape_expect_glyphs() -> Result<(), Box<dyn std::error::Error>> {
+    let args = [
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+        "abc",
+    ];
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).args(&["--expect-glyphs", "3"]);
+    cmd.assert().success().stdout("");
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).args(&["--expect-glyphs", "2"]);
+    cmd.assert()
+        .code(1)
+        .stderr("expected 2 glyphs but shaping 'abc' gave 3\n");
+
+    for option in ["--extents", "--feature-stats"] {
+        let mut cmd = Command::cargo_bin("allsorts")?;
+        cmd.args(&args).args(&["--expect-glyphs", "3", option]);
+        cmd.assert().code(1).stderr(predicate::str::contains(
+            "--expect-glyphs can't be combined with",
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn shape_convention() -> Result<(), Box<dyn std::error::Error>> {
+    let args = [
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+        "ab",
+    ];
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).args(&["--convention", "coretext"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("10@0,0+"))
+        .stdout(predicate::str::contains("\t11@"));
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).args(&["--convention", "directwrite"]);
+    cmd.assert().success().stdout(predicate::str::is_match(
+        "^10@0,0\\+\\d+\t11@0,0\\+\\d+\n$",
+    )?);
+
+    // The fatha is attached above the beh, which is to its right in visual order
+    let args = [
+        "shape",
+        "-f",
+        "tests/Fixture-Arabic.ttf",
+        "-s",
+        "arab",
+        "\u{628}\u{64E}",
+    ];
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).args(&["--convention", "coretext"]);
+    cmd.assert()
+        .success()
+        .stdout("5@110,400+0,0\t2@0,0+1089,0\n");
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).args(&["--convention", "directwrite"]);
+    cmd.assert().success().stdout("2@0,0+1089\t5@-110,400+0\n");
+
+    Ok(())
+}
+
+#[test]
+fn shape_trace() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+        "--trace",
+        "ab",
+    ]);
+    // The font has no layout tables so shaping changes nothing
+    cmd.assert().success().stdout(predicate::str::ends_with(
+        "substitutions:\n none\npositioning:\n none\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn shape_trace_inserted_dotted_circle() -> Result<(), Box<dyn std::error::Error>> {
+    // The dotted circle inserted before the lone vowel sign I isn't one of the characters
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Fixture-Layout.ttf",
+        "-s",
+        "deva",
+        "--trace",
+        "\u{93F}",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("substitutions:\n - -: - -> 13 (inserted)\npositioning:\n")
+            .and(predicate::str::contains("(deleted)").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn shape_vertical_metrics() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+        "--vertical",
+        "ab",
+    ]);
+    // The font has no vhea or vmtx
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(" vertical: advance ").count(2))
+        .stderr(predicate::str::contains(
+            "note: no vhea and vmtx tables, vertical metrics are synthesized from hhea\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn shape_both_advances() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Fixture-Bitmap.ttf",
+        "-s",
+        "latn",
+        "--both-advances",
+        "ab",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("\n992,0 (0, 0) advances: 992,1800 Info {\n").and(
+            predicate::str::contains("\n1089,0 (0, 0) advances: 1089,2200 Info {\n"),
+        ),
+    );
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Fixture-Bitmap.ttf",
+        "-s",
+        "latn",
+        "--both-advances",
+        "--vertical",
+        "ab",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            " advances: 992,1800 vertical: advance 1800, origin 1900, tsb 849 Info {\n",
+        )
+        .and(predicate::str::contains(
+            " advances: 1089,2200 vertical: advance 2200, origin 1900, tsb 461 Info {\n",
+        )),
+    );
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "--both-advances",
+        "ab",
+    ]);
+    cmd.assert()
+        .code(1)
+        .stderr("Error: --both-advances requires vhea and vmtx tables\n");
+
+    Ok(())
+}
+
+#[test]
+fn shape_format_hb() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+        "--format",
+        "hb",
+        "ab",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("[10=0+"))
+        .stdout(predicate::str::contains("|11=1+"))
+        .stdout(predicate::str::ends_with("]\n"));
+
+    Ok(())
+}
+
+#[test]
+fn shape_feature_stats() -> Result<(), Box<dyn std::error::Error>> {
+    // The ligature joins the clusters of f and i, ss01 replaces a
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Fixture-Layout.ttf",
+        "-s",
+        "latn",
+        "-F",
+        "liga,ss01",
+        "--feature-stats",
+        "fia",
+    ]);
+    cmd.assert().success().stdout(predicate::str::starts_with(
+        "feature stats (clusters changed by disabling the feature):\n \
+         liga: 2\n  - GSUB lookup 0: 2\n ss01: 1\n  - GSUB lookup 1: 1\n\n",
+    ));
+
+    // Features that do nothing for the text count no clusters
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Fixture-Layout.ttf",
+        "-s",
+        "latn",
+        "-F",
+        "liga,ss02",
+        "--feature-stats",
+        "fia",
+    ]);
+    cmd.assert().success().stdout(predicate::str::starts_with(
+        "feature stats (clusters changed by disabling the feature):\n \
+         liga: 2\n  - GSUB lookup 0: 2\n ss02: 0\n  - GSUB lookup 2: 0\n\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn shape_feature_stats_without_layout_tables() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+        "--feature-stats",
+        "abc",
+    ]);
+    cmd.assert().success().stdout(predicate::str::starts_with(
+        "feature stats (clusters changed by disabling the feature):\n\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn shape_context() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&[
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+        "--context-before",
+        "a",
+        "--context-after",
+        "c",
+        "b",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("glyph_index").count(1))
+        .stdout(predicate::str::ends_with(
+            "break at start of text: safe\nbreak at end of text: safe\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn shape_features() -> Result<(), Box<dyn std::error::Error>> {
+    let args = [
+        "shape",
+        "-f",
+        "tests/Basic-Regular.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+    ];
+    let default = Command::cargo_bin("allsorts")?
+        .args(&args)
+        .arg("abc")
+        .output()?;
+    assert!(default.status.success());
+    // The font has no layout tables, so toggling features changes nothing
+    for features in ["liga,smcp", "-kern", "-kern,+liga"] {
+        let mut cmd = Command::cargo_bin("allsorts")?;
+        cmd.args(&args)
+            .arg(format!("--features={}", features))
+            .arg("abc");
+        cmd.assert().success().stdout(default.stdout.clone());
+    }
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).args(&["--features=-zzzz", "abc"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "zzzz isn't one of the default features so can't be disabled",
+    ));
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).args(&["--features=liga,-kern", "abc"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "liga,-kern has features both with and without a + or - prefix",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn shape_features_add_unknown() -> Result<(), Box<dyn std::error::Error>> {
+    // allsorts doesn't know ss02, which maps b to B, so it's added to the defaults by tag
+    let shape = |features: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("allsorts")?;
+        cmd.args(&[
+            "shape",
+            "-f",
+            "tests/Fixture-Layout.ttf",
+            "-s",
+            "latn",
+            "--json",
+        ])
+        .arg(format!("--features={}", features))
+        .arg("fib");
+        let output = cmd.assert().success().get_output().stdout.clone();
+        Ok(String::from_utf8(output)?)
+    };
+    assert_eq!(glyph_names(&shape("+ss02")?), ["fi", "B"]);
+    assert_eq!(glyph_names(&shape("-liga,+ss02")?), ["f", "i", "B"]);
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["shape", "-f", "tests/Fixture-Layout.ttf", "-s", "latn"])
+        .args(&["--features=+ss02,-ss02", "fib"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "ss02 isn't one of the default features so can't be disabled",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn shape_features_file() -> Result<(), Box<dyn std::error::Error>> {
+    let profile = std::env::temp_dir().join("allsorts-shape-features.txt");
+    std::fs::write(&profile, "# no ligatures\nliga=0\nss01, salt=2\n")?;
+    let args = [
+        "shape",
+        "-f",
+        "tests/Fixture-Layout.ttf",
+        "-s",
+        "latn",
+        "-l",
+        "ENG",
+        "--json",
+        "fiao",
+    ];
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args).arg("--features-file").arg(&profile);
+    cmd.assert().success().stdout(predicate::str::is_match(
+        "\"glyph_name\":\"f\".*\"glyph_name\":\"i\".*\"glyph_name\":\"A\".*\
+         \"glyph_name\":\"lozenge\"",
+    )?);
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&args)
+        .args(&["--features", "liga", "--features-file"])
+        .arg(&profile);
+    cmd.assert()
+        .code(1)
+        .stderr("only one of --features OR --features-file may be supplied\n");
+
+    Ok(())
+}
+
+#[test]
+fn stats() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["stats", "tests/Fixture-Layout.ttf"]);
+    cmd.assert().success().stdout(
+        predicate::str::starts_with(
+            "Format:          OpenType\n\
+             Outlines:        TrueType (glyf)\n\
+             Glyphs:          14\n\
+             Units per em:    2048\n\
+             cmap sub-tables: 3\n",
+        )
+        .and(predicate::str::contains(
+            "Variable:        no\n\
+             Colour:          no\n\
+             File size:       3872 bytes\n\
+             Largest tables:  glyf (1304 bytes), name (1155 bytes), GSUB (272 bytes)\n\
+             GSUB features:   4\n\
+             GPOS features:   2\n",
+        )),
+    );
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["stats", "tests/Fixture-Bitmap.ttf"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Colour:          no\n"))
+        .stdout(predicate::str::contains("GSUB features").not());
+
+    Ok(())
+}
+
+#[test]
+fn subset_preserve_tables() -> Result<(), Box<dyn std::error::Error>> {
+    let output = std::env::temp_dir().join("allsorts-subset-preserve-tables.ttf");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["subset", "-t", "abc", "--preserve-tables", "name,OS/2"])
+        .arg("tests/Basic-Regular.ttf")
+        .arg(&output);
+    cmd.assert().success();
+
+    for table in ["name", "OS/2"] {
+        let original = Command::cargo_bin("allsorts")?
+            .args(&["dump", "-t", table, "tests/Basic-Regular.ttf"])
+            .output()?;
+        let subset = Command::cargo_bin("allsorts")?
+            .args(&["dump", "-t", table])
+            .arg(&output)
+            .output()?;
+        assert!(!original.stdout.is_empty());
+        assert_eq!(original.stdout, subset.stdout, "{} table differs", table);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn subset_check() -> Result<(), Box<dyn std::error::Error>> {
+    let output = std::env::temp_dir().join("allsorts-subset-check.ttf");
+    let _ = std::fs::remove_file(&output);
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["subset", "-t", "abc", "--check=fail"])
+        .arg("tests/Basic-Regular.ttf")
+        .arg(&output);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Number of glyphs in new font: 4\n",
+    ));
+    assert!(output.exists());
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["subset", "-t", "abc", "--check=strict"])
+        .arg("tests/Basic-Regular.ttf")
+        .arg(&output);
+    cmd.assert().code(2).stderr(predicate::str::contains(
+        "invalid check mode 'strict', expected fail or warn",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn subset_union() -> Result<(), Box<dyn std::error::Error>> {
+    let output = std::env::temp_dir().join("allsorts-subset-union.ttf");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["subset", "-t", "ab", "-t", "bc", "--unicodes", "U+63-64"])
+        .arg("tests/Basic-Regular.ttf")
+        .arg(&output);
+    cmd.assert()
+        .success()
+        .stdout("Number of glyphs in new font: 5\n")
+        .stderr(
+            "--text #1: 2 new characters\n\
+             --text #2: 1 new characters\n\
+             --unicodes U+63-64: 1 new characters\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn subset_shape() -> Result<(), Box<dyn std::error::Error>> {
+    let output = std::env::temp_dir().join("allsorts-subset-shape.ttf");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["subset", "-t", "abba", "--shape", "latn"])
+        .arg("tests/Basic-Regular.ttf")
+        .arg(&output);
+    cmd.assert()
+        .success()
+        .stdout("Number of glyphs in new font: 3\n")
+        .stderr("--text #1: 2 new glyphs\n");
+
+    // The ligature replaces f and i, which aren't kept
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["subset", "-t", "fi", "--shape", "latn"])
+        .arg("tests/Fixture-Layout.ttf")
+        .arg(&output);
+    cmd.assert()
+        .success()
+        .stdout("Number of glyphs in new font: 2\n")
+        .stderr("--text #1: 1 new glyphs\n");
+
+    Ok(())
+}
+
+#[test]
+fn layout_features_version() -> Result<(), Box<dyn std::error::Error>> {
+    // The fixture has a version 1.0 GPOS table, so no FeatureVariations, and no GSUB table
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["layout-features", "tests/Fixture-Arabic.ttf"]);
+    let expected = "\
+Table: GPOS
+  Version: 1.0
+  FeatureVariations: no
+  Script: DFLT
+    Language: default
+      Feature: curs
+        Lookups: 0
+      Feature: mark
+        Lookups: 1
+  Script: arab
+    Language: default
+      Feature: curs
+        Lookups: 0
+      Feature: mark
+        Lookups: 1
+";
+    cmd.assert().success().stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn subset_closure() -> Result<(), Box<dyn std::error::Error>> {
+    let output = std::env::temp_dir().join("allsorts-subset-closure.ttf");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["subset", "-t", "abc", "--closure"])
+        .arg("tests/Basic-Regular.ttf")
+        .arg(&output);
+    // The font has no GSUB table so nothing more can be reached
+    cmd.assert()
+        .success()
+        .stdout("Number of glyphs in new font: 4\n")
+        .stderr("--text #1: 3 new characters\n--closure: 0 new glyphs\n");
+
+    Ok(())
+}
+
+#[test]
+fn subset_closure_gsub() -> Result<(), Box<dyn std::error::Error>> {
+    let output = std::env::temp_dir().join("allsorts-subset-closure-gsub.ttf");
+    // Without --closure the fi ligature and the ss01/ss02 substitutions are reported as missing
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["subset", "-t", "fiab"])
+        .arg("tests/Fixture-Layout.ttf")
+        .arg(&output);
+    cmd.assert()
+        .success()
+        .stdout("Number of glyphs in new font: 5\n")
+        .stderr(
+            predicate::str::contains("GSUB can substitute 3 glyph(s) that aren't in the subset")
+                .and(predicate::str::contains(
+                    " - glyph 8: ss01\n - glyph 9: ss02\n - glyph 12: liga\n",
+                )),
+        );
+
+    // With --closure they're kept
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["subset", "-t", "fiab", "--closure"])
+        .arg("tests/Fixture-Layout.ttf")
+        .arg(&output);
+    cmd.assert()
+        .success()
+        .stdout("Number of glyphs in new font: 8\n")
+        .stderr("--text #1: 4 new characters\n--closure: 3 new glyphs\n");
+
+    Ok(())
+}
+
+#[test]
+fn subset_preserve_missing_table() -> Result<(), Box<dyn std::error::Error>> {
+    let output = std::env::temp_dir().join("allsorts-subset-preserve-missing.ttf");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["subset", "-t", "abc", "--preserve-tables", "GSUB"])
+        .arg("tests/Basic-Regular.ttf")
+        .arg(&output);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "table 'GSUB' is not in the source font",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn validate_baseline_unchanged() -> Result<(), Box<dyn std::error::Error>> {
+    let baseline = std::env::temp_dir().join("allsorts-basic-regular-baseline.txt");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.arg("validate")
+        .arg("--baseline")
+        .arg(&baseline)
+        .arg("--write-baseline")
+        .arg("tests/Basic-Regular.ttf");
+    cmd.assert().success().stdout("");
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.arg("validate")
+        .arg("--baseline")
+        .arg(&baseline)
+        .arg("tests/Basic-Regular.ttf");
+    cmd.assert().success().stdout("");
+
+    Ok(())
+}
+
+#[test]
+fn validate_baseline_drift() -> Result<(), Box<dyn std::error::Error>> {
+    let baseline = std::env::temp_dir().join("allsorts-fixture-variable-baseline.txt");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.arg("validate")
+        .arg("--baseline")
+        .arg(&baseline)
+        .arg("--write-baseline")
+        .arg("tests/Fixture-Variable.ttf");
+    cmd.assert().success().stdout("");
+
+    let instance = std::env::temp_dir().join("allsorts-fixture-variable-900.ttf");
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["instance", "-t", "900", "tests/Fixture-Variable.ttf", "-o"])
+        .arg(&instance);
+    cmd.assert().success();
+
+    // The heaviest weight moves the right side of every glyph with an outline, space is glyph 1
+    let mut expected = String::new();
+    for change in [
+        "advances changed",
+        "glyph 0 outline changed",
+        "glyph 2 outline changed",
+        "glyph 3 outline changed",
+        "glyph 4 outline changed",
+        "glyph 5 outline changed",
+        "glyph 6 outline changed",
+    ] {
+        expected.push_str(&format!("{}: baseline: {}\n", instance.display(), change));
+    }
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.arg("validate")
+        .arg("--baseline")
+        .arg(&baseline)
+        .arg(&instance);
+    cmd.assert().code(1).stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn validate_variable_instance() -> Result<(), Box<dyn std::error::Error>> {
+    for at in [None, Some("wght:100"), Some("wght:900")] {
+        let mut cmd = Command::cargo_bin("allsorts")?;
+        cmd.arg("validate").arg("tests/Fixture-Variable.ttf");
+        if let Some(at) = at {
+            cmd.args(&["--at", at]);
+        }
+        cmd.assert().success().stdout("");
+    }
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["validate", "--at", "wdth:75", "tests/Fixture-Variable.ttf"]);
+    cmd.assert()
+        .failure()
+        .stderr("Error: font has no wdth axis\n");
+
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["validate", "--at", "wght:700", "tests/Basic-Regular.ttf"]);
+    cmd.assert()
+        .code(1)
+        .stderr("--at requires a variable font\n");
+
+    Ok(())
+}
+
+#[test]
+fn cmap_lint() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("allsorts")?;
+    cmd.args(&["cmap", "--lint", "-f", "tests/Basic-Regular.ttf"]);
+    cmd.assert().success().stdout("no problems found\n");
+
+    Ok(())
+}
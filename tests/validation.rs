@@ -0,0 +1,27 @@
+use allsorts::binary::read::ReadScope;
+use allsorts::font_data::FontData;
+use allsorts_tools::validation::{self, ValidationOptions};
+
+fn validate(path: &str) -> Result<Vec<validation::Finding>, Box<dyn std::error::Error>> {
+    let buffer = std::fs::read(path)?;
+    let font_file = ReadScope::new(&buffer).read::<FontData>()?;
+    let provider = font_file.table_provider(0)?;
+    let findings = validation::validate(&provider, ValidationOptions::default())?;
+    Ok(findings)
+}
+
+#[test]
+fn validate_ttf() -> Result<(), Box<dyn std::error::Error>> {
+    let findings = validate("tests/Basic-Regular.ttf")?;
+    assert!(findings.is_empty(), "{:?}", findings);
+
+    Ok(())
+}
+
+#[test]
+fn validate_otf() -> Result<(), Box<dyn std::error::Error>> {
+    let findings = validate("tests/Basic-Regular.otf")?;
+    assert!(findings.is_empty(), "{:?}", findings);
+
+    Ok(())
+}